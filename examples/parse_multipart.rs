@@ -0,0 +1,38 @@
+use smtpc::mail::message::Message;
+
+const MULTIPART_MAIL: &str = "\
+From: Bob <sender@example.com>\r\n\
+To: Alice <recipient@example.com>\r\n\
+Subject: Multipart Test\r\n\
+Content-Type: multipart/mixed; boundary=\"some-boundary\"\r\n\
+\r\n\
+--some-boundary\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+Hello Alice, attachment is below. Best regards, Bob =C5=81\r\n\
+--some-boundary\r\n\
+Content-Type: application/octet-stream; name=\"data.bin\"\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+U29tZSBiaW5hcnkg\r\n\
+ZGF0YQ==\r\n\
+--some-boundary--\r\n\
+";
+
+fn main() {
+    println!("Parsing multipart mail: ");
+    println!("---\n{}\n---", MULTIPART_MAIL);
+
+    let message = Message::parse(MULTIPART_MAIL.as_bytes())
+        .expect("Message parsing filed");
+    println!("Parsed mail headers: {:#?}", message.root().parsed_headers());
+
+    for part in message.parts() {
+        println!("Part {}/{}", part.mime_type(), part.mime_subtype());
+        if part.children().is_empty() {
+            let body = part.decode_body().expect("Body decoding filed");
+            println!("Part contents(PARSED): {:#?}", String::from_utf8_lossy(&body));
+        }
+    }
+}
@@ -32,6 +32,12 @@ impl<R> Base64Reader<R> {
             is_err: false,
         }
     }
+
+    /// into_inner returns underlying reader. Any data buffered by decoder is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 }
 
 impl<R: Read> Read for Base64Reader<R> {
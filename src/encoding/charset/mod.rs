@@ -0,0 +1,370 @@
+//! Charset module converts text in charsets other than UTF-8 to rust strings.
+//!
+//! Supported are ISO-8859-x, Windows-125x, KOI8-R/KOI8-U and multibyte Shift_JIS, EUC-JP, ISO-2022-JP,
+//! GBK (and it's subset GB2312), Big5 and EUC-KR.
+//! Only decoding is supported as messages composed by this crate always use UTF-8.
+//!
+//! # Example
+//! ```rust
+//!# use smtpc::encoding::charset::Charset;
+//!let charset = Charset::for_label("ISO-8859-2").unwrap();
+//!assert_eq!(charset.decode(b"\xA3\xF3d\xBC"), "Łódź");
+//! ```
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+use multi_byte::*;
+use single_byte::*;
+
+mod multi_byte;
+mod single_byte;
+mod tables;
+
+/// CharsetDecodeError is returned when text contains bytes which are not valid in given charset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharsetDecodeError;
+
+impl fmt::Display for CharsetDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "text is not valid in given charset")
+    }
+}
+
+impl error::Error for CharsetDecodeError {}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Charset {
+    Utf8,
+    Iso8859_1,
+    Iso8859_2,
+    Iso8859_3,
+    Iso8859_4,
+    Iso8859_5,
+    Iso8859_6,
+    Iso8859_7,
+    Iso8859_8,
+    Iso8859_9,
+    Iso8859_10,
+    Iso8859_11,
+    Iso8859_13,
+    Iso8859_14,
+    Iso8859_15,
+    Iso8859_16,
+    Windows1250,
+    Windows1251,
+    Windows1252,
+    Windows1253,
+    Windows1254,
+    Windows1255,
+    Windows1256,
+    Windows1257,
+    Windows1258,
+    Koi8R,
+    Koi8U,
+    ShiftJis,
+    EucJp,
+    Iso2022Jp,
+    /// Gbk decodes GBK and GB2312. Four byte sequences of GB18030 are not supported.
+    Gbk,
+    Big5,
+    EucKr,
+}
+
+impl Charset {
+    pub const ALL: [Charset; 33] = [
+        Charset::Utf8,
+        Charset::Iso8859_1, Charset::Iso8859_2, Charset::Iso8859_3, Charset::Iso8859_4, Charset::Iso8859_5,
+        Charset::Iso8859_6, Charset::Iso8859_7, Charset::Iso8859_8, Charset::Iso8859_9, Charset::Iso8859_10,
+        Charset::Iso8859_11, Charset::Iso8859_13, Charset::Iso8859_14, Charset::Iso8859_15, Charset::Iso8859_16,
+        Charset::Windows1250, Charset::Windows1251, Charset::Windows1252, Charset::Windows1253, Charset::Windows1254,
+        Charset::Windows1255, Charset::Windows1256, Charset::Windows1257, Charset::Windows1258,
+        Charset::Koi8R, Charset::Koi8U,
+        Charset::ShiftJis, Charset::EucJp, Charset::Iso2022Jp, Charset::Gbk, Charset::Big5, Charset::EucKr,
+    ];
+
+    /// for_label finds charset for name used in MIME `charset` parameter or RFC 2047 encoded word.
+    /// Name is compared case insensitive and common aliases are recognised.
+    ///
+    /// Like browsers do, `us-ascii` is treated as `windows-1252` since mislabeled messages are common.
+    pub fn for_label(label: &str) -> Option<Self> {
+        let label = label.trim().to_ascii_lowercase();
+        let label = label.as_str();
+        let res = match label {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" | "x-unicode20utf8" => Charset::Utf8,
+            "us-ascii" | "ascii" | "us" | "ansi_x3.4-1968" | "iso646-us" | "cp367" | "ibm367" | "csascii" => Charset::Windows1252,
+            "latin1" | "l1" | "cp819" | "ibm819" | "csisolatin1" | "iso-ir-100" => Charset::Iso8859_1,
+            "latin2" | "l2" | "csisolatin2" | "iso-ir-101" => Charset::Iso8859_2,
+            "latin3" | "l3" | "csisolatin3" | "iso-ir-109" => Charset::Iso8859_3,
+            "latin4" | "l4" | "csisolatin4" | "iso-ir-110" => Charset::Iso8859_4,
+            "cyrillic" | "csisolatincyrillic" | "iso-ir-144" => Charset::Iso8859_5,
+            "arabic" | "csisolatinarabic" | "iso-ir-127" | "asmo-708" | "ecma-114" => Charset::Iso8859_6,
+            "greek" | "greek8" | "csisolatingreek" | "iso-ir-126" | "ecma-118" | "elot_928" => Charset::Iso8859_7,
+            "hebrew" | "csisolatinhebrew" | "iso-ir-138" | "visual" | "logical" => Charset::Iso8859_8,
+            "latin5" | "l5" | "csisolatin5" | "iso-ir-148" => Charset::Iso8859_9,
+            "latin6" | "l6" | "csisolatin6" | "iso-ir-157" => Charset::Iso8859_10,
+            "tis-620" | "tis620" | "windows-874" | "cp874" => Charset::Iso8859_11,
+            "latin7" | "l7" => Charset::Iso8859_13,
+            "latin8" | "l8" => Charset::Iso8859_14,
+            "latin9" | "l9" | "csisolatin9" => Charset::Iso8859_15,
+            "latin10" | "l10" => Charset::Iso8859_16,
+            "koi8-r" | "koi8r" | "koi8" | "koi" | "cskoi8r" => Charset::Koi8R,
+            "koi8-u" | "koi8-ru" => Charset::Koi8U,
+            "shift_jis" | "shift-jis" | "sjis" | "x-sjis" | "ms_kanji" | "csshiftjis" | "windows-31j" | "cp932" => Charset::ShiftJis,
+            "euc-jp" | "x-euc-jp" | "cseucpkdfmtjapanese" => Charset::EucJp,
+            "iso-2022-jp" | "csiso2022jp" => Charset::Iso2022Jp,
+            "gbk" | "x-gbk" | "gb2312" | "gb_2312" | "gb_2312-80" | "csgb2312" | "euc-cn" | "cp936" | "windows-936"
+            | "chinese" | "iso-ir-58" | "csiso58gb231280" => Charset::Gbk,
+            "big5" | "big5-hkscs" | "cn-big5" | "csbig5" | "x-x-big5" | "cp950" => Charset::Big5,
+            "euc-kr" | "cseuckr" | "ks_c_5601-1987" | "ks_c_5601-1989" | "ksc5601" | "ksc_5601" | "csksc56011987"
+            | "iso-ir-149" | "korean" | "windows-949" | "cp949" => Charset::EucKr,
+            _ => return Self::for_numbered_label(label),
+        };
+        Some(res)
+    }
+
+    /// for_numbered_label handles labels like `iso_8859-2:1987` or `cp1250`
+    fn for_numbered_label(label: &str) -> Option<Self> {
+        let iso = ["iso-8859-", "iso8859-", "iso_8859-", "iso8859"].iter()
+            .find(|p| label.starts_with(*p))
+            .map(|p| &label[p.len()..]);
+        if let Some(number) = iso {
+            let number = number.split(':').next().unwrap_or(number);
+            return Some(match number {
+                "1" => Charset::Iso8859_1,
+                "2" => Charset::Iso8859_2,
+                "3" => Charset::Iso8859_3,
+                "4" => Charset::Iso8859_4,
+                "5" => Charset::Iso8859_5,
+                "6" | "6-e" | "6-i" => Charset::Iso8859_6,
+                "7" => Charset::Iso8859_7,
+                "8" | "8-e" | "8-i" => Charset::Iso8859_8,
+                "9" => Charset::Iso8859_9,
+                "10" => Charset::Iso8859_10,
+                "11" => Charset::Iso8859_11,
+                "13" => Charset::Iso8859_13,
+                "14" => Charset::Iso8859_14,
+                "15" => Charset::Iso8859_15,
+                "16" => Charset::Iso8859_16,
+                _ => return None,
+            });
+        }
+
+        let windows = ["windows-", "x-cp", "cp"].iter()
+            .find(|p| label.starts_with(*p))
+            .map(|p| &label[p.len()..]);
+        Some(match windows? {
+            "1250" => Charset::Windows1250,
+            "1251" => Charset::Windows1251,
+            "1252" => Charset::Windows1252,
+            "1253" => Charset::Windows1253,
+            "1254" => Charset::Windows1254,
+            "1255" => Charset::Windows1255,
+            "1256" => Charset::Windows1256,
+            "1257" => Charset::Windows1257,
+            "1258" => Charset::Windows1258,
+            _ => return None,
+        })
+    }
+
+    /// name returns preferred MIME name of charset
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Iso8859_1 => "ISO-8859-1",
+            Charset::Iso8859_2 => "ISO-8859-2",
+            Charset::Iso8859_3 => "ISO-8859-3",
+            Charset::Iso8859_4 => "ISO-8859-4",
+            Charset::Iso8859_5 => "ISO-8859-5",
+            Charset::Iso8859_6 => "ISO-8859-6",
+            Charset::Iso8859_7 => "ISO-8859-7",
+            Charset::Iso8859_8 => "ISO-8859-8",
+            Charset::Iso8859_9 => "ISO-8859-9",
+            Charset::Iso8859_10 => "ISO-8859-10",
+            Charset::Iso8859_11 => "TIS-620",
+            Charset::Iso8859_13 => "ISO-8859-13",
+            Charset::Iso8859_14 => "ISO-8859-14",
+            Charset::Iso8859_15 => "ISO-8859-15",
+            Charset::Iso8859_16 => "ISO-8859-16",
+            Charset::Windows1250 => "windows-1250",
+            Charset::Windows1251 => "windows-1251",
+            Charset::Windows1252 => "windows-1252",
+            Charset::Windows1253 => "windows-1253",
+            Charset::Windows1254 => "windows-1254",
+            Charset::Windows1255 => "windows-1255",
+            Charset::Windows1256 => "windows-1256",
+            Charset::Windows1257 => "windows-1257",
+            Charset::Windows1258 => "windows-1258",
+            Charset::Koi8R => "KOI8-R",
+            Charset::Koi8U => "KOI8-U",
+            Charset::ShiftJis => "Shift_JIS",
+            Charset::EucJp => "EUC-JP",
+            Charset::Iso2022Jp => "ISO-2022-JP",
+            Charset::Gbk => "GBK",
+            Charset::Big5 => "Big5",
+            Charset::EucKr => "EUC-KR",
+        }
+    }
+
+    /// decode converts text to string. Invalid sequences are replaced with U+FFFD.
+    pub fn decode(self, data: &[u8]) -> Cow<'_, str> {
+        self.decode_with_errors(data).0
+    }
+
+    /// decode_strict converts text to string. It fails if text contains any invalid sequence.
+    pub fn decode_strict(self, data: &[u8]) -> Result<Cow<'_, str>, CharsetDecodeError> {
+        match self.decode_with_errors(data) {
+            (res, false) => Ok(res),
+            (_, true) => Err(CharsetDecodeError),
+        }
+    }
+
+    /// decode_with_errors converts text to string replacing invalid sequences with U+FFFD.
+    /// It also returns flag which is true if any replacement was made.
+    pub fn decode_with_errors(self, data: &[u8]) -> (Cow<'_, str>, bool) {
+        let table = match self {
+            Charset::Utf8 => {
+                let res = String::from_utf8_lossy(data);
+                let had_errors = match res {
+                    Cow::Owned(_) => true,
+                    Cow::Borrowed(_) => false,
+                };
+                return (res, had_errors);
+            }
+            Charset::ShiftJis => return decode_shift_jis(data),
+            Charset::EucJp => return decode_euc_jp(data),
+            Charset::Iso2022Jp => return decode_iso_2022_jp(data),
+            Charset::Gbk => return decode_gbk(data),
+            Charset::Big5 => return decode_big5(data),
+            Charset::EucKr => return decode_euc_kr(data),
+            Charset::Iso8859_1 => &tables::ISO_8859_1,
+            Charset::Iso8859_2 => &tables::ISO_8859_2,
+            Charset::Iso8859_3 => &tables::ISO_8859_3,
+            Charset::Iso8859_4 => &tables::ISO_8859_4,
+            Charset::Iso8859_5 => &tables::ISO_8859_5,
+            Charset::Iso8859_6 => &tables::ISO_8859_6,
+            Charset::Iso8859_7 => &tables::ISO_8859_7,
+            Charset::Iso8859_8 => &tables::ISO_8859_8,
+            Charset::Iso8859_9 => &tables::ISO_8859_9,
+            Charset::Iso8859_10 => &tables::ISO_8859_10,
+            Charset::Iso8859_11 => &tables::ISO_8859_11,
+            Charset::Iso8859_13 => &tables::ISO_8859_13,
+            Charset::Iso8859_14 => &tables::ISO_8859_14,
+            Charset::Iso8859_15 => &tables::ISO_8859_15,
+            Charset::Iso8859_16 => &tables::ISO_8859_16,
+            Charset::Windows1250 => &tables::WINDOWS_1250,
+            Charset::Windows1251 => &tables::WINDOWS_1251,
+            Charset::Windows1252 => &tables::WINDOWS_1252,
+            Charset::Windows1253 => &tables::WINDOWS_1253,
+            Charset::Windows1254 => &tables::WINDOWS_1254,
+            Charset::Windows1255 => &tables::WINDOWS_1255,
+            Charset::Windows1256 => &tables::WINDOWS_1256,
+            Charset::Windows1257 => &tables::WINDOWS_1257,
+            Charset::Windows1258 => &tables::WINDOWS_1258,
+            Charset::Koi8R => &tables::KOI8_R,
+            Charset::Koi8U => &tables::KOI8_U,
+        };
+        decode_single_byte(data, table)
+    }
+}
+
+/// decode_with_label decodes text in charset with given name, replacing invalid sequences with U+FFFD.
+/// When charset is not known text is decoded as UTF-8.
+pub fn decode_with_label<'a>(label: &str, data: &'a [u8]) -> Cow<'a, str> {
+    Charset::for_label(label).unwrap_or(Charset::Utf8).decode(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_find_charset_for_label() {
+        for (i, o) in [
+            ("UTF-8", Some(Charset::Utf8)),
+            (" utf8 ", Some(Charset::Utf8)),
+            ("us-ascii", Some(Charset::Windows1252)),
+            ("ISO-8859-1", Some(Charset::Iso8859_1)),
+            ("iso_8859-2:1987", Some(Charset::Iso8859_2)),
+            ("ISO8859-15", Some(Charset::Iso8859_15)),
+            ("latin2", Some(Charset::Iso8859_2)),
+            ("iso-8859-12", None),
+            ("windows-1251", Some(Charset::Windows1251)),
+            ("CP1250", Some(Charset::Windows1250)),
+            ("cp1259", None),
+            ("koi8-r", Some(Charset::Koi8R)),
+            ("Shift_JIS", Some(Charset::ShiftJis)),
+            ("GB2312", Some(Charset::Gbk)),
+            ("ks_c_5601-1987", Some(Charset::EucKr)),
+            ("x-unknown", None),
+            ("", None),
+        ].iter() {
+            assert_eq!(Charset::for_label(i), *o);
+        }
+    }
+
+    //noinspection SpellCheckingInspection
+    #[test]
+    fn test_can_decode() {
+        for (c, i, o) in [
+            (Charset::Utf8, &b"za\xC5\xBC\xC3\xB3\xC5\x82\xC4\x87"[..], "zażółć"),
+            (Charset::Iso8859_1, b"caf\xE9 \x80", "café \u{80}"),
+            (Charset::Iso8859_2, b"\xBFa\xBF\xF3\xB3\xE6", "żażółć"),
+            (Charset::Iso8859_3, b"\xA5", "\u{FFFD}"),
+            (Charset::Iso8859_5, b"\xBF\xE0\xD8\xD2\xD5\xE2", "Привет"),
+            (Charset::Iso8859_7, b"\xE1\xE2\xE3", "αβγ"),
+            (Charset::Iso8859_15, b"\xA4", "€"),
+            (Charset::Windows1250, b"\x8A\x9A", "Šš"),
+            (Charset::Windows1251, b"\xCF\xF0\xE8\xE2\xE5\xF2", "Привет"),
+            (Charset::Windows1252, b"\x80 \x93quoted\x94", "€ \u{201C}quoted\u{201D}"),
+            (Charset::Koi8R, b"\xF0\xD2\xC9\xD7\xC5\xD4", "Привет"),
+            (Charset::ShiftJis, b"\x93\xFA\x96\x7B\x8C\xEA \xB1", "日本語 ｱ"),
+            (Charset::ShiftJis, b"\x93", "\u{FFFD}"),
+            (Charset::ShiftJis, b"\x93 a", "\u{FFFD} a"),
+            (Charset::EucJp, b"\xC6\xFC\xCB\xDC\xB8\xEC \x8E\xB1", "日本語 ｱ"),
+            (Charset::Iso2022Jp, b"a\x1B$B;d\x1B(B b", "a私 b"),
+            (Charset::Iso2022Jp, b"\x1B(I1\x1B(J\\~", "ｱ¥‾"),
+            (Charset::Iso2022Jp, b"\x1B$Xa", "\u{FFFD}$Xa"),
+            (Charset::Gbk, b"\xD6\xD0\xCE\xC4 \x80", "中文 €"),
+            (Charset::Big5, b"\xA4\xA4\xA4\xE5", "中文"),
+            (Charset::EucKr, b"\xC7\xD1\xB1\xB9\xBE\xEE", "한국어"),
+        ].iter() {
+            assert_eq!(c.decode(i), *o);
+        }
+    }
+
+    #[test]
+    fn test_decode_strict_rejects_invalid_sequences() {
+        for (c, i, o) in [
+            (Charset::Utf8, &b"abc"[..], true),
+            (Charset::Utf8, b"\xFF", false),
+            (Charset::Iso8859_2, b"\xFF", true),
+            (Charset::Windows1252, b"\x81", false),
+            (Charset::Gbk, b"\xD6\xD0", true),
+            (Charset::Gbk, b"\xD6", false),
+            (Charset::Gbk, b"\x81\x30\x81\x30", false),
+        ].iter() {
+            assert_eq!(c.decode_strict(i).is_ok(), *o);
+        }
+    }
+
+    #[test]
+    fn test_name_is_valid_label() {
+        for c in Charset::ALL.iter() {
+            assert_eq!(Charset::for_label(c.name()), Some(*c));
+        }
+    }
+
+    #[test]
+    fn test_ascii_text_is_borrowed() {
+        for c in [Charset::Utf8, Charset::Iso8859_2, Charset::ShiftJis, Charset::Gbk].iter() {
+            match c.decode(b"plain text") {
+                Cow::Borrowed("plain text") => {}
+                r => panic!("Unexpected result: {:?}", r),
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::borrow::Cow;
+
+use super::tables::{BIG5, EUC_KR, GBK, JIS0208};
+
+/// JIS0208_PUA_RANGE is range of Shift_JIS pointers which are mapped to Unicode private use area
+const JIS0208_PUA_RANGE: std::ops::Range<usize> = 8836..10716;
+
+/// Output collects decoded text and remembers if any invalid sequence was found
+struct Output {
+    res: String,
+    had_errors: bool,
+}
+
+impl Output {
+    fn new(capacity: usize) -> Self {
+        Self {
+            res: String::with_capacity(capacity),
+            had_errors: false,
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, c: u32) {
+        self.res.push(std::char::from_u32(c).unwrap_or(std::char::REPLACEMENT_CHARACTER));
+    }
+
+    #[inline]
+    fn error(&mut self) {
+        self.had_errors = true;
+        self.res.push(std::char::REPLACEMENT_CHARACTER);
+    }
+
+    /// push_pointer pushes char from table or replacement char if pointer is not valid or not mapped
+    fn push_pointer(&mut self, table: &[u16], pointer: Option<usize>) {
+        match pointer.and_then(|p| table.get(p)).cloned().unwrap_or(0) {
+            0 => self.error(),
+            c => self.push(u32::from(c)),
+        }
+    }
+
+    fn finish(self) -> (Cow<'static, str>, bool) {
+        (Cow::Owned(self.res), self.had_errors)
+    }
+}
+
+/// borrow_ascii returns data as str if it's ASCII only
+fn borrow_ascii(data: &[u8]) -> Option<Cow<'_, str>> {
+    if data.is_ascii() {
+        std::str::from_utf8(data).ok().map(Cow::Borrowed)
+    } else {
+        None
+    }
+}
+
+/// read_pair computes table pointer for lead byte at the beginning of data and following trail byte.
+/// It returns pointer, which is `None` for invalid sequence, and number of bytes consumed.
+/// Invalid trail is not consumed if it's ASCII, so it's decoded on it's own.
+fn read_pair(data: &[u8], pointer: impl Fn(u8, u8) -> Option<usize>) -> (Option<usize>, usize) {
+    match data.get(1).cloned() {
+        Some(trail) => match pointer(data[0], trail) {
+            Some(p) => (Some(p), 2),
+            None if trail < 0x80 => (None, 1),
+            None => (None, 2),
+        },
+        None => (None, 1),
+    }
+}
+
+/// push_jis0208 pushes char for Shift_JIS pointer. Pointers of user defined area map to private use area.
+fn push_jis0208(out: &mut Output, pointer: Option<usize>) {
+    match pointer {
+        Some(p) if JIS0208_PUA_RANGE.contains(&p) && JIS0208[p] == 0 => {
+            out.push(0xE000 + (p - JIS0208_PUA_RANGE.start) as u32);
+        }
+        p => out.push_pointer(&JIS0208, p),
+    }
+}
+
+pub(crate) fn decode_shift_jis(data: &[u8]) -> (Cow<'_, str>, bool) {
+    if let Some(res) = borrow_ascii(data) {
+        return (res, false);
+    }
+    let mut out = Output::new(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        i += match b {
+            0x00..=0x80 => {
+                out.push(u32::from(b));
+                1
+            }
+            // half width katakana
+            0xA1..=0xDF => {
+                out.push(0xFF61 + u32::from(b - 0xA1));
+                1
+            }
+            0x81..=0x9F | 0xE0..=0xFC => {
+                let (p, n) = read_pair(&data[i..], |lead, trail| {
+                    let lead_offset = if lead < 0xA0 { 0x81 } else { 0xC1 };
+                    let trail_offset = match trail {
+                        0x40..=0x7E => 0x40,
+                        0x80..=0xFC => 0x41,
+                        _ => return None,
+                    };
+                    Some(usize::from(lead - lead_offset) * 188 + usize::from(trail - trail_offset))
+                });
+                push_jis0208(&mut out, p);
+                n
+            }
+            _ => {
+                out.error();
+                1
+            }
+        };
+    }
+    out.finish()
+}
+
+pub(crate) fn decode_euc_jp(data: &[u8]) -> (Cow<'_, str>, bool) {
+    if let Some(res) = borrow_ascii(data) {
+        return (res, false);
+    }
+    let mut out = Output::new(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        i += match b {
+            0x00..=0x7F => {
+                out.push(u32::from(b));
+                1
+            }
+            // half width katakana
+            0x8E => {
+                let (p, n) = read_pair(&data[i..], |_, trail| match trail {
+                    0xA1..=0xDF => Some(usize::from(trail - 0xA1)),
+                    _ => None,
+                });
+                match p {
+                    Some(p) => out.push(0xFF61 + p as u32),
+                    None => out.error(),
+                }
+                n
+            }
+            // JIS X 0212 is not supported
+            0x8F => {
+                out.error();
+                1 + data[i + 1..].iter().take(2).take_while(|b| (0xA1..=0xFE).contains(*b)).count()
+            }
+            0xA1..=0xFE => {
+                let (p, n) = read_pair(&data[i..], |lead, trail| match trail {
+                    0xA1..=0xFE => Some(usize::from(lead - 0xA1) * 94 + usize::from(trail - 0xA1)),
+                    _ => None,
+                });
+                push_jis0208(&mut out, p);
+                n
+            }
+            _ => {
+                out.error();
+                1
+            }
+        };
+    }
+    out.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Iso2022JpMode {
+    Ascii,
+    Roman,
+    Katakana,
+    Jis0208,
+}
+
+/// decode_iso_2022_jp decodes ISO-2022-JP. See RFC 1468
+pub(crate) fn decode_iso_2022_jp(data: &[u8]) -> (Cow<'_, str>, bool) {
+    if !data.contains(&0x1B) {
+        if let Some(res) = borrow_ascii(data) {
+            return (res, false);
+        }
+    }
+    let mut out = Output::new(data.len());
+    let mut mode = Iso2022JpMode::Ascii;
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        if b == 0x1B {
+            let next_mode = match data.get(i + 1..i + 3) {
+                Some(b"(B") => Some(Iso2022JpMode::Ascii),
+                Some(b"(J") => Some(Iso2022JpMode::Roman),
+                Some(b"(I") => Some(Iso2022JpMode::Katakana),
+                Some(b"$@") | Some(b"$B") => Some(Iso2022JpMode::Jis0208),
+                _ => None,
+            };
+            match next_mode {
+                Some(m) => {
+                    mode = m;
+                    i += 3;
+                }
+                None => {
+                    out.error();
+                    i += 1;
+                }
+            }
+            continue;
+        }
+        // line breaks are allowed in any mode
+        if b == b'\r' || b == b'\n' {
+            out.push(u32::from(b));
+            i += 1;
+            continue;
+        }
+        i += match mode {
+            Iso2022JpMode::Ascii if b < 0x80 => {
+                out.push(u32::from(b));
+                1
+            }
+            Iso2022JpMode::Roman if b < 0x80 => {
+                out.push(match b {
+                    b'\\' => 0xA5,
+                    b'~' => 0x203E,
+                    b => u32::from(b),
+                });
+                1
+            }
+            Iso2022JpMode::Katakana if (0x21..=0x5F).contains(&b) => {
+                out.push(0xFF61 + u32::from(b - 0x21));
+                1
+            }
+            Iso2022JpMode::Jis0208 if (0x21..=0x7E).contains(&b) => {
+                let (p, n) = read_pair(&data[i..], |lead, trail| match trail {
+                    0x21..=0x7E => Some(usize::from(lead - 0x21) * 94 + usize::from(trail - 0x21)),
+                    _ => None,
+                });
+                out.push_pointer(&JIS0208, p);
+                n
+            }
+            _ => {
+                out.error();
+                1
+            }
+        };
+    }
+    out.finish()
+}
+
+pub(crate) fn decode_gbk(data: &[u8]) -> (Cow<'_, str>, bool) {
+    if let Some(res) = borrow_ascii(data) {
+        return (res, false);
+    }
+    let mut out = Output::new(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        i += match b {
+            0x00..=0x7F => {
+                out.push(u32::from(b));
+                1
+            }
+            0x80 => {
+                out.push(0x20AC);
+                1
+            }
+            // four byte GB18030 sequence
+            0x81..=0xFE if data.get(i + 1).is_some_and(|t| t.is_ascii_digit()) => {
+                out.error();
+                data.len().min(i + 4) - i
+            }
+            0x81..=0xFE => {
+                let (p, n) = read_pair(&data[i..], |lead, trail| {
+                    let trail_offset = match trail {
+                        0x40..=0x7E => 0x40,
+                        0x80..=0xFE => 0x41,
+                        _ => return None,
+                    };
+                    Some(usize::from(lead - 0x81) * 190 + usize::from(trail - trail_offset))
+                });
+                out.push_pointer(&GBK, p);
+                n
+            }
+            _ => {
+                out.error();
+                1
+            }
+        };
+    }
+    out.finish()
+}
+
+pub(crate) fn decode_big5(data: &[u8]) -> (Cow<'_, str>, bool) {
+    if let Some(res) = borrow_ascii(data) {
+        return (res, false);
+    }
+    let mut out = Output::new(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        i += match b {
+            0x00..=0x7F => {
+                out.push(u32::from(b));
+                1
+            }
+            0x81..=0xFE => {
+                let (p, n) = read_pair(&data[i..], |lead, trail| {
+                    let trail_offset = match trail {
+                        0x40..=0x7E => 0x40,
+                        0xA1..=0xFE => 0x62,
+                        _ => return None,
+                    };
+                    Some(usize::from(lead - 0x81) * 157 + usize::from(trail - trail_offset))
+                });
+                out.push_pointer(&BIG5, p);
+                n
+            }
+            _ => {
+                out.error();
+                1
+            }
+        };
+    }
+    out.finish()
+}
+
+pub(crate) fn decode_euc_kr(data: &[u8]) -> (Cow<'_, str>, bool) {
+    if let Some(res) = borrow_ascii(data) {
+        return (res, false);
+    }
+    let mut out = Output::new(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        i += match b {
+            0x00..=0x7F => {
+                out.push(u32::from(b));
+                1
+            }
+            0x81..=0xFE => {
+                let (p, n) = read_pair(&data[i..], |lead, trail| match trail {
+                    0x41..=0xFE => Some(usize::from(lead - 0x81) * 190 + usize::from(trail - 0x41)),
+                    _ => None,
+                });
+                out.push_pointer(&EUC_KR, p);
+                n
+            }
+            _ => {
+                out.error();
+                1
+            }
+        };
+    }
+    out.finish()
+}
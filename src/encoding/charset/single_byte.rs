@@ -0,0 +1,25 @@
+use std::borrow::Cow;
+
+/// decode_single_byte decodes charset which is ASCII in lower half and uses given table for upper half
+pub(crate) fn decode_single_byte<'a>(data: &'a [u8], table: &[u16; 128]) -> (Cow<'a, str>, bool) {
+    if data.is_ascii() {
+        // ascii is valid utf-8
+        return (Cow::Borrowed(std::str::from_utf8(data).unwrap()), false);
+    }
+    let mut res = String::with_capacity(data.len() + data.len() / 2);
+    let mut had_errors = false;
+    for b in data.iter().cloned() {
+        if b < 0x80 {
+            res.push(b as char);
+            continue;
+        }
+        match table[(b - 0x80) as usize] {
+            0 => {
+                had_errors = true;
+                res.push(std::char::REPLACEMENT_CHARACTER);
+            }
+            c => res.push(std::char::from_u32(u32::from(c)).unwrap_or(std::char::REPLACEMENT_CHARACTER)),
+        }
+    }
+    (Cow::Owned(res), had_errors)
+}
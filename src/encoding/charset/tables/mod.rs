@@ -0,0 +1,14 @@
+//! Lookup tables used by charset decoders.
+//! They are generated by `tools/generate_charset_tables.py`, zero means that pointer is not mapped.
+
+pub(crate) use big5::*;
+pub(crate) use euc_kr::*;
+pub(crate) use gbk::*;
+pub(crate) use jis0208::*;
+pub(crate) use single_byte::*;
+
+mod big5;
+mod euc_kr;
+mod gbk;
+mod jis0208;
+mod single_byte;
@@ -0,0 +1,363 @@
+//! Idna module converts internationalized domain names between U-labels and A-labels.
+//! Labels are encoded with punycode. See RFC 3492 and RFC 5891
+//!
+//! Only lowercasing and mapping of ideographic full stops to dots is done,
+//! domains are not validated against IDNA tables.
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+use crate::error::ErrorPosition;
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 0x80;
+
+/// ACE_PREFIX is prefix of labels encoded with punycode
+pub const ACE_PREFIX: &str = "xn--";
+
+/// MAX_LABEL_LENGTH is maximum length of A-label in octets. See RFC 1034 section 3.1
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+/// IdnaError is returned when domain or label can't be converted
+#[derive(Debug)]
+pub enum IdnaError {
+    /// InvalidPunycode is returned when label is not valid punycode
+    InvalidPunycode(ErrorPosition),
+    /// LabelTooLong is returned when A-label is longer than `MAX_LABEL_LENGTH`
+    LabelTooLong(ErrorPosition),
+    /// Overflow is returned when label is too long to be encoded or decoded
+    Overflow,
+    /// NonAsciiLocalPart is returned when address with UTF-8 local part is converted to ASCII.
+    /// Such address can be only sent with SMTPUTF8. See RFC 6530
+    NonAsciiLocalPart,
+}
+
+impl IdnaError {
+    /// position returns place in input where error was found if it's known
+    pub fn position(&self) -> Option<&ErrorPosition> {
+        match self {
+            IdnaError::InvalidPunycode(p) |
+            IdnaError::LabelTooLong(p) => Some(p),
+            IdnaError::Overflow |
+            IdnaError::NonAsciiLocalPart => None,
+        }
+    }
+
+    fn shift(self, offset: usize) -> Self {
+        match self {
+            IdnaError::InvalidPunycode(p) => IdnaError::InvalidPunycode(p.shift(offset)),
+            IdnaError::LabelTooLong(p) => IdnaError::LabelTooLong(p.shift(offset)),
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for IdnaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdnaError::InvalidPunycode(p) => write!(f, "invalid punycode: {}", p),
+            IdnaError::LabelTooLong(p) => write!(f, "label is too long: {}", p),
+            IdnaError::Overflow => write!(f, "label is too long to be converted"),
+            IdnaError::NonAsciiLocalPart => write!(f, "local part of address is not ASCII"),
+        }
+    }
+}
+
+impl error::Error for IdnaError {}
+
+#[inline]
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        T_MIN
+    } else if k >= bias + T_MAX {
+        T_MAX
+    } else {
+        k - bias
+    }
+}
+
+fn adapt(delta: u32, points: u32, is_first: bool) -> u32 {
+    let mut delta = if is_first { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+#[inline]
+fn encode_digit(d: u32) -> char {
+    debug_assert!(d < BASE);
+    if d < 26 {
+        (b'a' + d as u8) as char
+    } else {
+        (b'0' + (d - 26) as u8) as char
+    }
+}
+
+#[inline]
+fn decode_digit(b: u8) -> Option<u32> {
+    match b {
+        b'a'..=b'z' => Some(u32::from(b - b'a')),
+        b'A'..=b'Z' => Some(u32::from(b - b'A')),
+        b'0'..=b'9' => Some(u32::from(b - b'0') + 26),
+        _ => None,
+    }
+}
+
+/// encode_punycode encodes label with punycode. Prefix is not added.
+///
+/// # Example
+/// ```rust
+///# use smtpc::encoding::idna::{decode_punycode, encode_punycode};
+///assert_eq!(encode_punycode("bücher").unwrap(), "bcher-kva");
+///assert_eq!(decode_punycode("bcher-kva").unwrap(), "bücher");
+/// ```
+pub fn encode_punycode(label: &str) -> Result<String, IdnaError> {
+    let chars: Vec<u32> = label.chars().map(u32::from).collect();
+    let mut res: String = label.chars().filter(char::is_ascii).collect();
+    let basic = res.len() as u32;
+    if basic > 0 {
+        res.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut handled = basic;
+    while (handled as usize) < chars.len() {
+        let m = chars.iter().cloned().filter(|c| *c >= n).min().expect("Not handled char exists");
+        delta = (m - n).checked_mul(handled + 1)
+            .and_then(|d| delta.checked_add(d))
+            .ok_or(IdnaError::Overflow)?;
+        n = m;
+        for c in chars.iter().cloned() {
+            if c < n {
+                delta = delta.checked_add(1).ok_or(IdnaError::Overflow)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    res.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                res.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta = delta.checked_add(1).ok_or(IdnaError::Overflow)?;
+        n += 1;
+    }
+    Ok(res)
+}
+
+/// decode_punycode decodes label encoded with punycode. Label has to be given without prefix.
+pub fn decode_punycode(label: &str) -> Result<String, IdnaError> {
+    let (basic, encoded, offset) = match label.rfind('-') {
+        Some(idx) => (&label[..idx], &label[idx + 1..], idx + 1),
+        None => ("", label, 0),
+    };
+    if let Some(idx) = basic.find(|c: char| !c.is_ascii()) {
+        return Err(IdnaError::InvalidPunycode(ErrorPosition::new(label, idx, "ASCII char")));
+    }
+    let mut res: Vec<char> = basic.chars().collect();
+
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let data = encoded.as_bytes();
+    let mut pos = 0;
+    while pos < data.len() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = data.get(pos).cloned().and_then(decode_digit).ok_or_else(|| {
+                IdnaError::InvalidPunycode(ErrorPosition::new(label, offset + pos, "punycode digit"))
+            })?;
+            pos += 1;
+            i = digit.checked_mul(w)
+                .and_then(|d| i.checked_add(d))
+                .ok_or(IdnaError::Overflow)?;
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t).ok_or(IdnaError::Overflow)?;
+            k += BASE;
+        }
+        let len = res.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len).ok_or(IdnaError::Overflow)?;
+        i %= len;
+        // basic code points may appear in basic portion only. See RFC 3492 section 6.2
+        let c = std::char::from_u32(n).filter(|c| !c.is_ascii()).ok_or_else(|| {
+            IdnaError::InvalidPunycode(ErrorPosition::new(label, offset + pos, "valid non-ASCII code point"))
+        })?;
+        res.insert(i as usize, c);
+        i += 1;
+    }
+    Ok(res.into_iter().collect())
+}
+
+/// is_label_separator checks if char separates labels. Ideographic full stops are treated as dots. See RFC 3490 section 3.1
+#[inline]
+fn is_label_separator(c: char) -> bool {
+    matches!(c, '.' | '\u{3002}' | '\u{FF0E}' | '\u{FF61}')
+}
+
+/// split_labels returns labels of domain along with their byte offsets
+fn split_labels(domain: &str) -> impl Iterator<Item=(usize, &str)> {
+    let mut offset = 0;
+    domain.split(is_label_separator).map(move |label| {
+        let res = (offset, label);
+        // separator follows label, it's length is not known here so it's found in text
+        offset += label.len();
+        offset += domain[offset..].chars().next().map(char::len_utf8).unwrap_or(0);
+        res
+    })
+}
+
+/// domain_to_ascii converts U-labels of domain to A-labels. ASCII labels are kept as they are.
+///
+/// # Example
+/// ```rust
+///# use smtpc::encoding::idna::{domain_to_ascii, domain_to_unicode};
+///assert_eq!(domain_to_ascii("Bücher.example").unwrap(), "xn--bcher-kva.example");
+///assert_eq!(domain_to_unicode("xn--bcher-kva.example").unwrap(), "bücher.example");
+/// ```
+pub fn domain_to_ascii(domain: &str) -> Result<Cow<'_, str>, IdnaError> {
+    if domain.is_ascii() {
+        return Ok(Cow::Borrowed(domain));
+    }
+    let mut res = String::with_capacity(domain.len() * 2);
+    for (i, (offset, label)) in split_labels(domain).enumerate() {
+        if i > 0 {
+            res.push('.');
+        }
+        let start = res.len();
+        if label.is_ascii() {
+            res.push_str(label);
+        } else {
+            res.push_str(ACE_PREFIX);
+            res.push_str(&encode_punycode(&label.to_lowercase()).map_err(|e| e.shift(offset))?);
+        }
+        if res.len() - start > MAX_LABEL_LENGTH {
+            return Err(IdnaError::LabelTooLong(ErrorPosition::new(domain, offset, "shorter label")));
+        }
+    }
+    Ok(Cow::Owned(res))
+}
+
+/// domain_to_unicode converts A-labels of domain to U-labels. Other labels are kept as they are.
+pub fn domain_to_unicode(domain: &str) -> Result<Cow<'_, str>, IdnaError> {
+    let is_ace = |label: &str| label.get(..ACE_PREFIX.len())
+        .map(|p| p.eq_ignore_ascii_case(ACE_PREFIX))
+        .unwrap_or(false);
+    if !split_labels(domain).any(|(_, label)| is_ace(label)) {
+        return Ok(Cow::Borrowed(domain));
+    }
+    let mut res = String::with_capacity(domain.len());
+    for (i, (offset, label)) in split_labels(domain).enumerate() {
+        if i > 0 {
+            res.push('.');
+        }
+        if is_ace(label) {
+            let decoded = decode_punycode(&label[ACE_PREFIX.len()..])
+                .map_err(|e| e.shift(offset + ACE_PREFIX.len()))?;
+            // A-label has to encode at least one non-ASCII char, otherwise label would be written as it is
+            if decoded.is_ascii() {
+                return Err(IdnaError::InvalidPunycode(ErrorPosition::new(domain, offset, "label with non-ASCII chars")));
+            }
+            res.push_str(&decoded);
+        } else {
+            res.push_str(label);
+        }
+    }
+    Ok(Cow::Owned(res))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_encode_and_decode_punycode() {
+        for (i, o) in [
+            ("", ""),
+            ("abc", "abc-"),
+            ("bücher", "bcher-kva"),
+            ("münchen", "mnchen-3ya"),
+            ("例子", "fsqu00a"),
+            // samples from RFC 3492 section 7.1
+            ("他们为什么不说中文", "ihqwcrb4cv8a8dqg056pqjye"),
+            ("3年B組金八先生", "3B-ww4c5e180e575a65lsy2b"),
+            ("안녕하세요", "o70b841ad5c9qb763b"),
+        ].iter() {
+            assert_eq!(encode_punycode(i).unwrap(), *o, "{}", i);
+            assert_eq!(decode_punycode(o).unwrap(), *i, "{}", o);
+        }
+    }
+
+    #[test]
+    fn test_invalid_punycode_is_rejected() {
+        for (i, offset) in [
+            ("bcher-kv!", 8),
+            ("ü-abc", 0),
+            ("bcher-k", 7),
+        ].iter() {
+            match decode_punycode(i) {
+                Err(IdnaError::InvalidPunycode(p)) => assert_eq!(p.offset, *offset, "{}", i),
+                r => panic!("Unexpected result for {}: {:?}", i, r),
+            }
+        }
+        match decode_punycode("99999999999") {
+            Err(IdnaError::Overflow) => {}
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_can_convert_domain() {
+        for (u, a) in [
+            ("example.com", "example.com"),
+            ("bücher.example", "xn--bcher-kva.example"),
+            ("例子.广告", "xn--fsqu00a.xn--4rr70v"),
+            ("exämple.de", "xn--exmple-cua.de"),
+        ].iter() {
+            assert_eq!(domain_to_ascii(u).unwrap(), *a);
+            assert_eq!(domain_to_unicode(a).unwrap(), *u);
+        }
+        assert_eq!(domain_to_ascii("BÜCHER。example").unwrap(), "xn--bcher-kva.example");
+        assert_eq!(domain_to_unicode("XN--bcher-kva.example").unwrap(), "bücher.example");
+
+        for (i, offset) in [
+            ("a.xn--bcher-k!", 13),
+            ("a.xn--abc-.example", 2),
+        ].iter() {
+            match domain_to_unicode(i) {
+                Err(IdnaError::InvalidPunycode(p)) => assert_eq!(p.offset, *offset, "{}", i),
+                r => panic!("Unexpected result for {}: {:?}", i, r),
+            }
+        }
+        match domain_to_ascii(&format!("a.{}", "ü".repeat(60))) {
+            Err(IdnaError::LabelTooLong(p)) => assert_eq!(p.offset, 2),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
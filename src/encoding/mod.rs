@@ -1,9 +1,15 @@
 //! Encoding module implements support for various encodings in streaming manner
 //! right now `QuotedPrintable` and `Base64` are supported
 //!
-//! For multipart there is `PartReader`
+//! For multipart there is `PartReader` which reads single part and `MultipartReader` which reads all of them
+//!
+//! Text in charsets other than UTF-8 can be decoded with `charset::Charset`
+//!
+//! Internationalized domain names can be converted between U-labels and A-labels with `idna`
 
 pub mod multipart;
 pub mod quoted_printable;
 pub mod base64;
-pub mod spaceless;
\ No newline at end of file
+pub mod spaceless;
+pub mod charset;
+pub mod idna;
\ No newline at end of file
@@ -1,9 +1,12 @@
 use std::borrow::Cow;
 use std::cmp::max;
 use std::collections::VecDeque;
-use std::io::{Error, ErrorKind, Read};
+use std::error;
+use std::fmt;
+use std::io::{self, Error, ErrorKind, Read};
 
-use crate::utils::{BoundaryDetector, BoundaryDetectorResult};
+use crate::mail::header::{MailHeaderReader, RawHeaderBag, RawHeaderParseOptions, RawMailHeaderParseError};
+use crate::utils::{BoundaryDetector, BoundaryDetectorResult, PrefixedReader};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PartReaderState {
@@ -68,6 +71,20 @@ impl<R> PartReader<R> {
             middle_bd: BoundaryDetector::new(Cow::from(middle_boundary)),
         }
     }
+
+    /// state returns which kind of boundary(if any) has been found so far.
+    /// Once it's not `LookingForBoundary` reader returns `Ok(0)` on every read.
+    #[inline]
+    pub fn state(&self) -> PartReaderState {
+        self.state
+    }
+
+    /// into_inner returns underlying reader.
+    /// Once boundary has been found it's positioned right after the boundary line.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 }
 
 impl<R> Read for PartReader<R>
@@ -129,6 +146,10 @@ impl<R> Read for PartReader<R>
                         }
                     }
                     if let PartReaderState::LookingForBoundary = self.state {
+                        // return data read so far. Error will be returned on next read
+                        if last_buffer_len != buf.len() {
+                            break;
+                        }
                         return Err(Error::new(ErrorKind::UnexpectedEof, "Reader is done but multipart end was not found"));
                     }
                     break;
@@ -264,6 +285,237 @@ impl<R> Read for PartReader<R>
     }
 }
 
+/// MAX_HEADER_SIZE limits size of headers section of single part when reading it from stream.
+pub const MAX_HEADER_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, From)]
+pub enum PartHeadersError {
+    HeaderParseError(RawMailHeaderParseError),
+    IOError(io::Error),
+    HeaderTooLong,
+}
+
+impl fmt::Display for PartHeadersError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PartHeadersError::HeaderParseError(e) => e.fmt(f),
+            PartHeadersError::IOError(e) => e.fmt(f),
+            PartHeadersError::HeaderTooLong => write!(f, "headers section is too long"),
+        }
+    }
+}
+
+impl error::Error for PartHeadersError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PartHeadersError::HeaderParseError(e) => e.source(),
+            PartHeadersError::IOError(e) => e.source(),
+            PartHeadersError::HeaderTooLong => None,
+        }
+    }
+}
+
+/// read_part_headers reads headers section of part from stream using `MailHeaderReader`.
+/// Once it's done reader is positioned at the first byte of body.
+/// Offsets of headers are relative to the beginning of part.
+pub(crate) fn read_part_headers<R: Read>(reader: R, options: RawHeaderParseOptions) -> Result<(RawHeaderBag<'static>, R), PartHeadersError> {
+    // part with no headers starts with empty line so fake new line before it
+    // in order to make `MailHeaderReader` find it
+    let mut hr = MailHeaderReader::new(PrefixedReader::new(b"\r\n", reader), false);
+    let mut data = Vec::new();
+    (&mut hr).take(MAX_HEADER_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_HEADER_SIZE {
+        return Err(PartHeadersError::HeaderTooLong);
+    }
+    // fake new line is not part of headers, so it's removed in order to keep offsets valid
+    if data.starts_with(b"\r\n") {
+        data.drain(..2);
+    }
+    // reader consumes line terminator of last header along with empty line, so it's restored
+    if !data.is_empty() && hr.is_terminated() {
+        data.extend_from_slice(b"\r\n");
+    }
+    let headers = RawHeaderBag::parse_with_options(&data, options)?.into_owned();
+    Ok((headers, hr.into_inner().into_inner()))
+}
+
+
+/// MultipartSegment describes which piece of multipart body is read by `MultipartReader`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MultipartSegment {
+    /// Preamble is data before first boundary
+    Preamble,
+    /// Part is body of part, which headers were returned by `MultipartReader::next_part`
+    Part,
+    /// Epilogue is data after final boundary
+    Epilogue,
+}
+
+enum MultipartReaderInner<R> {
+    Part(PartReader<PrefixedReader<R>>),
+    Raw(PrefixedReader<R>),
+    Taken,
+}
+
+/// MultipartReader reads entire multipart body part by part.
+///
+/// It implements `Read` which reads current segment of body: preamble, body of current part or epilogue.
+/// `next_part` skips what is left of current segment and reads headers of next part.
+/// Once there are no more parts `next_part` returns `None` and reader reads epilogue.
+///
+/// Nested multipart may be read with another `MultipartReader` on top of this one.
+/// Missing final boundary is tolerated, part ends where data ends then.
+///
+/// # Example
+/// ```rust
+///# use std::io::{Cursor, Read};
+///# use smtpc::encoding::multipart::MultipartReader;
+///const BODY: &str = "\
+///preamble\r\n\
+///--some-boundary\r\n\
+///Content-Type: text/plain\r\n\
+///\r\n\
+///first\r\n\
+///--some-boundary\r\n\
+///\r\n\
+///second\r\n\
+///--some-boundary--\r\n\
+///epilogue";
+///let mut r = MultipartReader::new(Cursor::new(BODY.as_bytes()), b"some-boundary");
+///let mut preamble = String::new();
+///r.read_to_string(&mut preamble).unwrap();
+///assert_eq!(preamble, "preamble");
+///
+///let mut bodies = Vec::new();
+///while let Some(_headers) = r.next_part().unwrap() {
+///    let mut body = String::new();
+///    r.read_to_string(&mut body).unwrap();
+///    bodies.push(body);
+///}
+///assert_eq!(bodies, vec!["first", "second"]);
+///
+///let mut epilogue = String::new();
+///r.read_to_string(&mut epilogue).unwrap();
+///assert_eq!(epilogue, "epilogue");
+/// ```
+pub struct MultipartReader<R> {
+    inner: MultipartReaderInner<R>,
+    boundary: Vec<u8>,
+    segment: MultipartSegment,
+    is_truncated: bool,
+    // count of faked new line bytes which were not skipped yet
+    prefix_skip: usize,
+    options: RawHeaderParseOptions,
+}
+
+impl<R> MultipartReader<R> {
+    pub fn new(reader: R, boundary: &[u8]) -> Self {
+        Self::with_options(reader, boundary, RawHeaderParseOptions::default())
+    }
+
+    /// with_options creates reader which parses headers of parts using given options
+    pub fn with_options(reader: R, boundary: &[u8], options: RawHeaderParseOptions) -> Self {
+        // first boundary is not preceded with new line when there is no preamble
+        // so fake one
+        let reader = PrefixedReader::new(b"\r\n", reader);
+        Self {
+            inner: MultipartReaderInner::Part(PartReader::new(reader, boundary, false)),
+            boundary: boundary.to_vec(),
+            segment: MultipartSegment::Preamble,
+            is_truncated: false,
+            prefix_skip: 2,
+            options,
+        }
+    }
+
+    #[inline]
+    pub fn boundary(&self) -> &[u8] {
+        &self.boundary
+    }
+
+    /// segment returns which piece of multipart body is read right now
+    #[inline]
+    pub fn segment(&self) -> MultipartSegment {
+        self.segment
+    }
+
+    /// into_inner returns underlying reader.
+    /// When it's called before epilogue it's positioned somewhere in the middle of multipart body.
+    ///
+    /// # Panics
+    /// It panics when `next_part` has failed, since underlying reader is lost then.
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            MultipartReaderInner::Part(pr) => pr.into_inner().into_inner(),
+            MultipartReaderInner::Raw(r) => r.into_inner(),
+            MultipartReaderInner::Taken => panic!("Underlying reader was lost when reading part headers failed"),
+        }
+    }
+}
+
+impl<R> MultipartReader<R> where R: Read {
+    /// next_part skips rest of current segment and reads headers of next part.
+    /// `None` is returned when there are no more parts.
+    pub fn next_part(&mut self) -> Result<Option<RawHeaderBag<'static>>, PartHeadersError> {
+        if self.segment == MultipartSegment::Epilogue {
+            return Ok(None);
+        }
+        io::copy(self, &mut io::sink())?;
+        self.prefix_skip = 0;
+
+        let pr = match std::mem::replace(&mut self.inner, MultipartReaderInner::Taken) {
+            MultipartReaderInner::Part(pr) => pr,
+            _ => unreachable!("Part reader is used before epilogue"),
+        };
+        if self.is_truncated || pr.state() != PartReaderState::FoundMiddleBoundary {
+            self.inner = MultipartReaderInner::Raw(pr.into_inner());
+            self.segment = MultipartSegment::Epilogue;
+            return Ok(None);
+        }
+
+        let pr = PartReader::new(pr.into_inner(), &self.boundary, false);
+        let (headers, pr) = read_part_headers(pr, self.options)?;
+        self.inner = MultipartReaderInner::Part(pr);
+        self.segment = MultipartSegment::Part;
+        Ok(Some(headers))
+    }
+}
+
+impl<R> MultipartReader<R> where R: Read {
+    fn read_segment(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match &mut self.inner {
+            MultipartReaderInner::Part(_) if self.is_truncated => Ok(0),
+            MultipartReaderInner::Part(pr) => match pr.read(buf) {
+                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    self.is_truncated = true;
+                    Ok(0)
+                }
+                res => res,
+            },
+            MultipartReaderInner::Raw(r) => r.read(buf),
+            MultipartReaderInner::Taken => Err(Error::other("Multipart reader failed to read part headers")),
+        }
+    }
+}
+
+impl<R> Read for MultipartReader<R> where R: Read {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // non empty preamble always starts with faked new line
+        while self.prefix_skip > 0 {
+            let mut skip_buf = [0u8; 2];
+            let sz = self.read_segment(&mut skip_buf[..self.prefix_skip])?;
+            if sz == 0 {
+                break;
+            }
+            self.prefix_skip -= sz;
+        }
+        self.read_segment(buf)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::io::Cursor;
@@ -335,4 +587,90 @@ mod test {
             }
         }
     }
-}
\ No newline at end of file
+
+    fn read_all_parts<R: Read>(r: &mut MultipartReader<R>) -> Vec<(Option<String>, String)> {
+        let mut res = Vec::new();
+        while let Some(headers) = r.next_part().unwrap() {
+            let mut body = String::new();
+            r.read_to_string(&mut body).unwrap();
+            res.push((headers.container().get("Content-Type").map(|v| String::from_utf8_lossy(&v[0]).into_owned()), body));
+        }
+        assert_eq!(r.segment(), MultipartSegment::Epilogue);
+        res
+    }
+
+    #[test]
+    fn test_multipart_reader_can_read_parts() {
+        for (i, preamble, parts, epilogue) in vec![
+            ("--b\r\n\r\nfirst\r\n--b--", "", vec![(None, "first")], ""),
+            ("--b--\r\nepilogue", "", vec![], "epilogue"),
+            ("no boundary at all", "no boundary at all", vec![], ""),
+            (
+                "pre\r\n--b\r\nContent-Type: text/plain\r\n\r\nfirst\r\n--b\r\n\r\nsecond\r\n--b--\r\nepi",
+                "pre",
+                vec![(Some("text/plain"), "first"), (None, "second")],
+                "epi",
+            ),
+            // missing final boundary
+            ("--b\r\n\r\nfirst\r\n--b\r\n\r\nsecond", "", vec![(None, "first"), (None, "second")], ""),
+        ].into_iter() {
+            let mut r = MultipartReader::new(Cursor::new(i.as_bytes()), b"b");
+            let mut res = String::new();
+            r.read_to_string(&mut res).unwrap();
+            assert_eq!(res, preamble);
+
+            let given_parts = read_all_parts(&mut r);
+            let parts: Vec<_> = parts.iter()
+                .map(|(h, b)| (h.map(|h| h.to_string()), b.to_string()))
+                .collect();
+            assert_eq!(given_parts, parts);
+
+            let mut res = String::new();
+            r.read_to_string(&mut res).unwrap();
+            assert_eq!(res, epilogue);
+        }
+    }
+
+    #[test]
+    fn test_multipart_reader_can_read_nested_multipart() {
+        const BODY: &str = concat!(
+        "--outer\r\n",
+        "Content-Type: multipart/alternative; boundary=inner\r\n",
+        "\r\n",
+        "--inner\r\n",
+        "\r\n",
+        "first\r\n",
+        "--inner--\r\n",
+        "inner epilogue\r\n",
+        "--outer\r\n",
+        "\r\n",
+        "second\r\n",
+        "--outer--",
+        );
+        let mut r = MultipartReader::new(Cursor::new(BODY.as_bytes()), b"outer");
+        r.next_part().unwrap().unwrap();
+        {
+            let mut nested = MultipartReader::new(&mut r, b"inner");
+            assert_eq!(read_all_parts(&mut nested), vec![(None, "first".to_string())]);
+        }
+        assert_eq!(read_all_parts(&mut r), vec![(None, "second".to_string())]);
+    }
+
+    #[test]
+    fn test_can_read_part_headers() {
+        for (i, o, b) in [
+            ("Subject: Test\r\nTo: a@example.com\r\n\r\nbody", vec![(0, "Subject: Test\r\n"), (15, "To: a@example.com\r\n")], "body"),
+            ("Subject: Test", vec![(0, "Subject: Test")], ""),
+            ("\r\nbody", vec![], "body"),
+        ].iter() {
+            let (headers, mut rest) = read_part_headers(i.as_bytes(), RawHeaderParseOptions::default()).unwrap();
+            let headers: Vec<_> = headers.headers().iter()
+                .map(|h| (h.offset, String::from_utf8(h.raw.to_vec()).unwrap()))
+                .collect();
+            assert_eq!(headers, o.iter().map(|(o, r)| (*o, r.to_string())).collect::<Vec<_>>());
+            let mut body = Vec::new();
+            rest.read_to_end(&mut body).unwrap();
+            assert_eq!(&body[..], b.as_bytes());
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::error;
+use std::fmt;
 use std::io::{self, Error, ErrorKind, Read, Write};
 use std::num::ParseIntError;
 
@@ -20,6 +22,24 @@ pub enum QuotedPrintableDecodingError {
     InvalidEnd,
 }
 
+impl fmt::Display for QuotedPrintableDecodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotedPrintableDecodingError::ParseIntError(e) => write!(f, "invalid escaped byte: {}", e),
+            QuotedPrintableDecodingError::InvalidEnd => write!(f, "quoted printable text ends with incomplete escape"),
+        }
+    }
+}
+
+impl error::Error for QuotedPrintableDecodingError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            QuotedPrintableDecodingError::ParseIntError(e) => e.source(),
+            QuotedPrintableDecodingError::InvalidEnd => None,
+        }
+    }
+}
+
 // TODO(teawithsand) rewrite to use streaming version on buffer
 /// encode_quoted_printable encodes given string as quited printable 7 bit ascii
 /// # Note
@@ -174,6 +194,12 @@ impl<R> QuotedPrintableReader<R> {
             is_strict: false,
         }
     }
+
+    /// into_inner returns underlying reader. Any data buffered by decoder is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.stream
+    }
 }
 
 impl<R> Read for QuotedPrintableReader<R>
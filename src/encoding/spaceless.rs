@@ -15,6 +15,11 @@ impl<R> SpacelessReader<R> {
             reader,
         }
     }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 }
 
 impl<R> Read for SpacelessReader<R>
@@ -0,0 +1,223 @@
+//! Error module contains `ErrorPosition`, which points at place in input where parsing failed,
+//! and `Error`, which is sum of all errors returned by this crate.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+pub use crate::utils::quoted::QuotedStringError;
+
+use crate::encoding::charset::CharsetDecodeError;
+use crate::encoding::idna::IdnaError;
+use crate::encoding::multipart::PartHeadersError;
+use crate::encoding::quoted_printable::QuotedPrintableDecodingError;
+use crate::mail::address::EmailAddressParseError;
+use crate::mail::builder::MessageBuildError;
+use crate::mail::date::DateParseError;
+use crate::mail::header::{
+    ExtendedValueParseError,
+    HeaderError,
+    MailHeaderParseError,
+    MessageIDParseError,
+    ParamsParseError,
+    RawMailHeaderParseError,
+};
+use crate::mail::message::MessageParseError;
+use crate::smtp::{EnhancedStatusCodeParseError, ReplyParseError, SmtpClientError};
+
+/// SNIPPET_LENGTH is maximum number of chars of input kept in `ErrorPosition`
+pub const SNIPPET_LENGTH: usize = 24;
+
+/// ErrorPosition points at place in input where parsing failed.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::address::{EmailAddress, EmailAddressParseError};
+///match EmailAddress::parse_single("John Doe <john@example.com") {
+///    Err(EmailAddressParseError::InvalidAddressSpec(p)) => {
+///        assert_eq!(p.offset, 26);
+///        assert_eq!(p.expected, "`>`");
+///        assert_eq!(p.to_string(), "expected `>` at byte 26, found end of input");
+///    }
+///    r => panic!("Unexpected result: {:?}", r),
+///}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorPosition {
+    /// offset is byte offset of place where parsing failed in parsed input
+    pub offset: usize,
+    /// snippet is part of input starting at offset. It's at most `SNIPPET_LENGTH` chars long.
+    pub snippet: String,
+    /// expected describes token which was expected at offset
+    pub expected: &'static str,
+}
+
+impl ErrorPosition {
+    /// new creates position in given text. Offset past the end of text points at it's end.
+    pub fn new(text: &str, offset: usize, expected: &'static str) -> Self {
+        Self::from_bytes(text.as_bytes(), offset, expected)
+    }
+
+    /// from_bytes creates position in given data. Snippet is decoded lossy.
+    pub fn from_bytes(data: &[u8], offset: usize, expected: &'static str) -> Self {
+        let rest = data.get(offset..).unwrap_or(&[]);
+        let snippet = String::from_utf8_lossy(rest).chars().take(SNIPPET_LENGTH).collect();
+        Self {
+            offset,
+            snippet,
+            expected,
+        }
+    }
+
+    /// shift moves position by given number of bytes.
+    /// It's used when parsed input is part of larger text.
+    #[inline]
+    pub fn shift(mut self, offset: usize) -> Self {
+        self.offset += offset;
+        self
+    }
+}
+
+impl fmt::Display for ErrorPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} at byte {}, ", self.expected, self.offset)?;
+        if self.snippet.is_empty() {
+            write!(f, "found end of input")
+        } else {
+            write!(f, "found {:?}", self.snippet)
+        }
+    }
+}
+
+/// offset_in returns offset of part in data. Part has to be slice of data.
+#[inline]
+pub(crate) fn offset_in(data: &[u8], part: &[u8]) -> usize {
+    let offset = part.as_ptr() as usize - data.as_ptr() as usize;
+    debug_assert!(offset + part.len() <= data.len(), "Part is not slice of data");
+    offset
+}
+
+/// Error is any error returned by this crate
+#[derive(Debug, From)]
+pub enum Error {
+    IOError(io::Error),
+    EmailAddressParseError(EmailAddressParseError),
+    RawMailHeaderParseError(RawMailHeaderParseError),
+    MailHeaderParseError(MailHeaderParseError),
+    HeaderError(HeaderError),
+    QuotedStringError(QuotedStringError),
+    MessageIDParseError(MessageIDParseError),
+    ParamsParseError(ParamsParseError),
+    ExtendedValueParseError(ExtendedValueParseError),
+    DateParseError(DateParseError),
+    MessageParseError(MessageParseError),
+    PartHeadersError(PartHeadersError),
+    MessageBuildError(MessageBuildError),
+    QuotedPrintableDecodingError(QuotedPrintableDecodingError),
+    CharsetDecodeError(CharsetDecodeError),
+    IdnaError(IdnaError),
+    ReplyParseError(ReplyParseError),
+    EnhancedStatusCodeParseError(EnhancedStatusCodeParseError),
+    SmtpClientError(SmtpClientError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IOError(e) => e.fmt(f),
+            Error::EmailAddressParseError(e) => e.fmt(f),
+            Error::RawMailHeaderParseError(e) => e.fmt(f),
+            Error::MailHeaderParseError(e) => e.fmt(f),
+            Error::HeaderError(e) => e.fmt(f),
+            Error::QuotedStringError(e) => e.fmt(f),
+            Error::MessageIDParseError(e) => e.fmt(f),
+            Error::ParamsParseError(e) => e.fmt(f),
+            Error::ExtendedValueParseError(e) => e.fmt(f),
+            Error::DateParseError(e) => e.fmt(f),
+            Error::MessageParseError(e) => e.fmt(f),
+            Error::PartHeadersError(e) => e.fmt(f),
+            Error::MessageBuildError(e) => e.fmt(f),
+            Error::QuotedPrintableDecodingError(e) => e.fmt(f),
+            Error::CharsetDecodeError(e) => e.fmt(f),
+            Error::IdnaError(e) => e.fmt(f),
+            Error::ReplyParseError(e) => e.fmt(f),
+            Error::EnhancedStatusCodeParseError(e) => e.fmt(f),
+            Error::SmtpClientError(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    /// source returns source of wrapped error. Errors of this crate include message of wrapped error
+    /// in their own one, so returning wrapped error itself would make it reported twice in error chain.
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::IOError(e) => e.source(),
+            Error::EmailAddressParseError(e) => e.source(),
+            Error::RawMailHeaderParseError(e) => e.source(),
+            Error::MailHeaderParseError(e) => e.source(),
+            Error::HeaderError(e) => e.source(),
+            Error::QuotedStringError(e) => e.source(),
+            Error::MessageIDParseError(e) => e.source(),
+            Error::ParamsParseError(e) => e.source(),
+            Error::ExtendedValueParseError(e) => e.source(),
+            Error::DateParseError(e) => e.source(),
+            Error::MessageParseError(e) => e.source(),
+            Error::PartHeadersError(e) => e.source(),
+            Error::MessageBuildError(e) => e.source(),
+            Error::QuotedPrintableDecodingError(e) => e.source(),
+            Error::CharsetDecodeError(e) => e.source(),
+            Error::IdnaError(e) => e.source(),
+            Error::ReplyParseError(e) => e.source(),
+            Error::EnhancedStatusCodeParseError(e) => e.source(),
+            Error::SmtpClientError(e) => e.source(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_display_error_position() {
+        for (text, offset, o) in [
+            ("a@b", 1, "expected atom at byte 1, found \"@b\""),
+            ("a@b", 3, "expected atom at byte 3, found end of input"),
+            ("a@b", 10, "expected atom at byte 10, found end of input"),
+            ("ąęść", 2, "expected atom at byte 2, found \"ęść\""),
+            ("0123456789012345678901234567890123456789", 0, "expected atom at byte 0, found \"012345678901234567890123\""),
+        ].iter() {
+            assert_eq!(ErrorPosition::new(text, *offset, "atom").to_string(), *o);
+        }
+    }
+
+    #[test]
+    fn test_snippet_of_bytes_is_lossy() {
+        let p = ErrorPosition::from_bytes(b"To: \xFF\r\n", 4, "value");
+        assert_eq!(p.snippet, "\u{FFFD}\r\n");
+        assert_eq!(p.shift(10).offset, 14);
+    }
+
+    #[test]
+    fn test_error_chain_does_not_repeat_messages() {
+        use std::error::Error as _;
+
+        for (err, o) in [
+            (Error::from(EmailAddressParseError::InputEmpty), "address is empty"),
+            (Error::from(MailHeaderParseError::from(QuotedStringError::UnexpectedEof)), "unexpected end of input"),
+            (
+                Error::from(MessageParseError::from(io::Error::new(io::ErrorKind::UnexpectedEof, "closed"))),
+                "closed",
+            ),
+        ].iter() {
+            let mut messages = vec![err.to_string()];
+            let mut source = err.source();
+            while let Some(e) = source {
+                messages.push(e.to_string());
+                source = e.source();
+            }
+            assert_eq!(messages, vec![o.to_string()]);
+        }
+    }
+}
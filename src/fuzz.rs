@@ -1,14 +1,20 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::io::{Cursor, Read};
 use std::io;
 
 use crate::encoding::base64::Base64Reader;
-use crate::encoding::multipart::PartReader;
+use crate::encoding::charset::Charset;
+use crate::encoding::idna::{decode_punycode, domain_to_ascii, domain_to_unicode, encode_punycode};
+use crate::encoding::multipart::{MultipartReader, PartReader};
 use crate::encoding::quoted_printable::QuotedPrintableReader;
-use crate::mail::address::EmailAddress;
-use crate::mail::date::parse_date;
-use crate::mail::header::{ParsedMailHeader, RawHeaderBag};
-use crate::utils::quoted::parse_rfc_2047;
+use crate::mail::address::{AddressSpec, EmailAddress};
+use crate::mail::date::{Date, parse_date};
+use crate::mail::header::{decode_rfc_2231_params, parse_params, ParsedHeaderBag, ParsedMailHeader, RawHeaderBag, RawHeaderParseOptions, UnfoldingMode};
+use crate::mail::event::MimeEventReader;
+use crate::mail::message::Message;
+use crate::smtp::{ClientSession, Command, ServerEvent, ServerSession};
+use crate::utils::quoted::{decode_rfc_2047, decode_rfc_2047_strict, parse_rfc_2047};
 
 fn drain_reader(r: &mut impl io::Read) {
     loop {
@@ -31,6 +37,14 @@ pub fn fuzz_multipart(data: &[u8]) {
     }
 }
 
+pub fn fuzz_multipart_reader(data: &[u8]) {
+    let mut reader = MultipartReader::new(Cursor::new(data), b"b");
+    drain_reader(&mut reader);
+    while let Ok(Some(_)) = reader.next_part() {
+        drain_reader(&mut reader);
+    }
+}
+
 pub fn fuzz_base64_decoder(data: &[u8]) {
     let mut reader = Cursor::new(data);
     {
@@ -50,25 +64,78 @@ pub fn fuzz_quoted_printable_decoder(data: &[u8]) {
 pub fn fuzz_parse_address(data: &[u8]) {
     if let Ok(text) = std::str::from_utf8(data) {
         let _ = EmailAddress::parse_group(text);
+        let _ = AddressSpec::parse(text);
+    }
+}
+
+pub fn fuzz_idna(data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = domain_to_unicode(text);
+        let _ = domain_to_ascii(text);
+        if let Ok(decoded) = decode_punycode(text) {
+            let _ = encode_punycode(&decoded);
+        }
     }
 }
 
 pub fn fuzz_parse_date(data: &[u8]) {
     if let Ok(text) = std::str::from_utf8(data) {
         let _ = parse_date(text);
+        if let Ok(date) = Date::parse(text) {
+            assert_eq!(Date::parse(&date.to_string()), Ok(date));
+        }
+        if let Ok((date, _)) = Date::parse_lenient(text) {
+            assert_eq!(Date::parse(&date.to_string()), Ok(date));
+        }
+    }
+}
+
+pub fn fuzz_charset_decode(data: &[u8]) {
+    for c in Charset::ALL.iter() {
+        let (res, had_errors) = c.decode_with_errors(data);
+        assert_eq!(c.decode_strict(data).is_err(), had_errors);
+        sink(res);
     }
 }
 
 pub fn fuzz_rfc_2047(data: &[u8]) {
     if let Ok(text) = std::str::from_utf8(data) {
         let _ = parse_rfc_2047(text);
+        let res = decode_rfc_2047(text);
+        if let Ok(strict) = decode_rfc_2047_strict(text) {
+            assert_eq!(strict, res);
+        }
     }
 }
 
-pub fn fuzz_mail_raw_mail_header_bag(data: &[u8]) {
+pub fn fuzz_parse_params(data: &[u8]) {
     if let Ok(text) = std::str::from_utf8(data) {
-        let _ = RawHeaderBag::parse(text);
+        if let Ok((_, params)) = parse_params(text) {
+            sink(decode_rfc_2231_params(params.into_iter().map(|(n, v)| (Cow::Borrowed(n), v))));
+        }
+    }
+}
+
+pub fn fuzz_mail_raw_mail_header_bag(data: &[u8]) {
+    if let Ok(bag) = RawHeaderBag::parse(data) {
+        let _ = ParsedHeaderBag::parse_raw_bag(&bag);
     }
+    let _ = RawHeaderBag::parse_with_options(data, RawHeaderParseOptions {
+        unfolding: UnfoldingMode::Legacy,
+    });
+}
+
+pub fn fuzz_parse_message(data: &[u8]) {
+    if let Ok(m) = Message::parse(data) {
+        for p in m.parts() {
+            let _ = p.decode_body();
+        }
+    }
+}
+
+pub fn fuzz_mime_event_reader(data: &[u8]) {
+    let mut r = MimeEventReader::new(Cursor::new(data));
+    while let Ok(Some(_)) = r.next_event() {}
 }
 
 pub fn fuzz_parse_mail_header(data: &[u8]) {
@@ -150,4 +217,29 @@ pub fn fuzz_base64_decoder_gives_same_result(data: &[u8]) {
     if let Ok(ok_res) = ok_res {
         assert_eq!(ok_res, res);
     }
-}
\ No newline at end of file
+}
+pub fn fuzz_smtp_server_session(data: &[u8]) {
+    let mut session = ServerSession::new("localhost");
+    for chunk in data.chunks(7) {
+        session.feed(chunk);
+        while let Some(event) = session.next_event() {
+            match event {
+                ServerEvent::MailFrom { .. } |
+                ServerEvent::RcptTo { .. } |
+                ServerEvent::Verify(_) |
+                ServerEvent::DataEnd => session.accept(),
+                _ => {}
+            }
+        }
+        session.take_output();
+    }
+}
+
+pub fn fuzz_smtp_client_session(data: &[u8]) {
+    let mut session = ClientSession::new();
+    for _ in 0..data.len() {
+        session.send_command(&Command::Noop).unwrap();
+    }
+    session.feed(data);
+    while let Ok(Some(_)) = session.next_reply() {}
+}
@@ -1,6 +1,7 @@
 //! SMTPC crate provides utilities required to:
 //! - parse messages sent over SMTP encoded with quoted-printable, base64
 //! - has support for reading multipart messages
+//! - implement SMTP protocol in sans-IO manner
 //!
 //! Take a look at examples to see how to use it.
 
@@ -28,5 +29,6 @@ pub(crate) mod utils;
 pub mod fuzz;
 
 pub mod encoding;
+pub mod error;
 pub mod mail;
-// pub mod smtp;
\ No newline at end of file
+pub mod smtp;
@@ -1,10 +1,17 @@
 use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
-use crate::utils::cc::is_atext;
+use crate::encoding::idna::{domain_to_ascii, domain_to_unicode, IdnaError};
+use crate::error::{ErrorPosition, offset_in};
+use crate::utils::cc::{is_atext, is_vchar, is_white_space};
 use crate::utils::quoted::{
+    encode_phrase,
+    fold_header,
     parse_maybe_rfc_2047,
-    parse_maybe_rfc_2047_is_encoded,
+    quote_string,
     unquote_string,
 };
 use std::cmp::Ordering;
@@ -37,29 +44,441 @@ impl<'a> EmailAddress<'a> {
             address: Cow::Owned(self.address.into_owned()),
         }
     }
+
+    /// spec splits address into local part and domain
+    pub fn spec(&self) -> Result<AddressSpec<'_>, EmailAddressParseError> {
+        AddressSpec::parse(&self.address)
+    }
+
+    /// to_header_value formats address so it can be put in header.
+    /// Display name is quoted if it contains specials and encoded with RFC 2047 if it's not ASCII.
+    /// Line breaks are not checked here, `MessageBuilder` rejects addresses containing them before writing message.
+    ///
+    /// # Example
+    /// ```rust
+    ///# use smtpc::mail::address::EmailAddress;
+    ///let address = EmailAddress {
+    ///    name: "Doe, John".into(),
+    ///    address: "john@example.com".into(),
+    ///};
+    ///assert_eq!(address.to_header_value(), "\"Doe, John\" <john@example.com>");
+    ///assert_eq!(address.to_string(), address.to_header_value());
+    /// ```
+    pub fn to_header_value(&self) -> String {
+        if self.name.is_empty() {
+            return self.address.to_string();
+        }
+        format!("{} <{}>", encode_phrase(&self.name), self.address)
+    }
+
+    /// list_to_header_value formats comma separated list of addresses. Result is not folded.
+    pub fn list_to_header_value(addresses: &[EmailAddress]) -> String {
+        addresses.iter()
+            .map(EmailAddress::to_header_value)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl<'a> fmt::Display for EmailAddress<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_header_value())
+    }
+}
+
+/// Address is single entry of address list.
+/// It's either mailbox or named group of mailboxes, which may be empty.
+/// See RFC 5322 section 3.4
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Address<'a> {
+    Mailbox(EmailAddress<'a>),
+    Group {
+        name: Cow<'a, str>,
+        members: Vec<EmailAddress<'a>>,
+    },
+}
+
+impl<'a> Address<'a> {
+    /// into_owned makes `Address` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> Address<'static> {
+        match self {
+            Address::Mailbox(m) => Address::Mailbox(m.into_owned()),
+            Address::Group { name, members } => Address::Group {
+                name: Cow::Owned(name.into_owned()),
+                members: members.into_iter().map(EmailAddress::into_owned).collect(),
+            },
+        }
+    }
+
+    /// mailboxes returns all mailboxes of this address.
+    /// Mailbox returns only itself and group returns it's members.
+    pub fn mailboxes(&self) -> &[EmailAddress<'a>] {
+        match self {
+            Address::Mailbox(m) => std::slice::from_ref(m),
+            Address::Group { members, .. } => &members[..],
+        }
+    }
+
+    /// parse_list parses comma separated list of mailboxes and groups.
+    /// Unlike `EmailAddress::parse_group` it keeps groups, including empty ones.
+    ///
+    /// # Example
+    /// ```rust
+    ///# use smtpc::mail::address::Address;
+    ///let list = Address::parse_list("undisclosed-recipients:;, John Doe <john@example.com>").unwrap();
+    ///assert_eq!(list.len(), 2);
+    ///match &list[0] {
+    ///    Address::Group { name, members } => {
+    ///        assert_eq!(name, "undisclosed-recipients");
+    ///        assert!(members.is_empty());
+    ///    }
+    ///    a => panic!("Unexpected address: {:?}", a),
+    ///}
+    ///assert_eq!(list[1].mailboxes()[0].address, "john@example.com");
+    /// ```
+    pub fn parse_list(addresses: &'a str) -> Result<Vec<Address<'a>>, EmailAddressParseError> {
+        Self::parse_list_with_options(addresses, AddressParseOptions::default())
+    }
+
+    /// parse_list_with_options parses address list like `parse_list` using given options
+    pub fn parse_list_with_options(addresses: &'a str, options: AddressParseOptions) -> Result<Vec<Address<'a>>, EmailAddressParseError> {
+        let mut pa = AddressParser::with_options(addresses, options);
+        pa.take_address_list()
+    }
+
+    /// to_header_value formats address so it can be put in header.
+    /// Group is formatted as `name: a, b;`, which for empty group is `name:;`.
+    pub fn to_header_value(&self) -> String {
+        match self {
+            Address::Mailbox(m) => m.to_header_value(),
+            Address::Group { name, members } if members.is_empty() => format!("{}:;", encode_phrase(name)),
+            Address::Group { name, members } => format!("{}: {};", encode_phrase(name), EmailAddress::list_to_header_value(members)),
+        }
+    }
+
+    /// list_to_header_value formats comma separated list of addresses. Result is not folded.
+    pub fn list_to_header_value(addresses: &[Address]) -> String {
+        addresses.iter()
+            .map(Address::to_header_value)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// list_to_header creates header line of given name and addresses, ending with CRLF.
+    /// Line is folded so it's not longer than 78 chars if possible.
+    ///
+    /// # Example
+    /// ```rust
+    ///# use smtpc::mail::address::Address;
+    ///let list = Address::parse_list("Zażółć <a@example.com>, Team: b@example.com;").unwrap();
+    ///assert_eq!(
+    ///    Address::list_to_header("To", &list),
+    ///    "To: =?UTF-8?B?WmHFvMOzxYLEhw==?= <a@example.com>, Team: b@example.com;\r\n",
+    ///);
+    /// ```
+    pub fn list_to_header(name: &str, addresses: &[Address]) -> String {
+        fold_header(name, &Self::list_to_header_value(addresses))
+    }
+
+    /// flatten returns all mailboxes of given addresses, in order
+    pub fn flatten(addresses: Vec<Address<'a>>) -> Vec<EmailAddress<'a>> {
+        let mut res = Vec::new();
+        for a in addresses {
+            match a {
+                Address::Mailbox(m) => res.push(m),
+                Address::Group { members, .. } => res.extend(members),
+            }
+        }
+        res
+    }
+}
+
+impl<'a> fmt::Display for Address<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_header_value())
+    }
+}
+
+impl<'a> From<EmailAddress<'a>> for Address<'a> {
+    fn from(m: EmailAddress<'a>) -> Self {
+        Address::Mailbox(m)
+    }
+}
+
+/// AddressMode selects which chars are accepted in address specs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum AddressMode {
+    /// Eai accepts UTF-8 in local parts and domains. See RFC 6532
+    #[default]
+    Eai,
+
+    /// Ascii accepts only ASCII address specs as RFC 5322 does.
+    /// Display names are not affected.
+    Ascii,
+}
+
+/// AddressParseOptions configures parsing of addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct AddressParseOptions {
+    pub mode: AddressMode,
+}
+
+/// Domain is domain part of address spec
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Domain<'a> {
+    /// Name is dot-atom domain like `example.com`
+    Name(Cow<'a, str>),
+    /// Literal is domain literal without brackets like `192.0.2.1` or `IPv6:2001:db8::1`
+    Literal(Cow<'a, str>),
+}
+
+impl<'a> Domain<'a> {
+    /// into_owned makes `Domain` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> Domain<'static> {
+        match self {
+            Domain::Name(n) => Domain::Name(Cow::Owned(n.into_owned())),
+            Domain::Literal(l) => Domain::Literal(Cow::Owned(l.into_owned())),
+        }
+    }
+
+    /// is_ascii checks if domain contains only ASCII chars
+    pub fn is_ascii(&self) -> bool {
+        match self {
+            Domain::Name(n) => n.is_ascii(),
+            Domain::Literal(l) => l.is_ascii(),
+        }
+    }
+
+    /// to_ascii converts U-labels of domain name to A-labels. Literals are kept as they are.
+    pub fn to_ascii(&self) -> Result<Domain<'_>, IdnaError> {
+        match self {
+            Domain::Name(n) => Ok(Domain::Name(domain_to_ascii(n)?)),
+            Domain::Literal(l) => Ok(Domain::Literal(Cow::Borrowed(l))),
+        }
+    }
+
+    /// to_unicode converts A-labels of domain name to U-labels. Literals are kept as they are.
+    pub fn to_unicode(&self) -> Result<Domain<'_>, IdnaError> {
+        match self {
+            Domain::Name(n) => Ok(Domain::Name(domain_to_unicode(n)?)),
+            Domain::Literal(l) => Ok(Domain::Literal(Cow::Borrowed(l))),
+        }
+    }
+
+    /// ip returns address of domain literal if it's IPv4 or IPv6 address literal. See RFC 5321 section 4.1.3
+    pub fn ip(&self) -> Option<IpAddr> {
+        let literal = match self {
+            Domain::Name(_) => return None,
+            Domain::Literal(l) => l,
+        };
+        match literal.get(..5) {
+            Some(tag) if tag.eq_ignore_ascii_case("IPv6:") => literal[5..].parse::<Ipv6Addr>().ok().map(IpAddr::V6),
+            _ => literal.parse::<Ipv4Addr>().ok().map(IpAddr::V4),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Domain<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Domain::Name(n) => write!(f, "{}", n),
+            Domain::Literal(l) => write!(f, "[{}]", l),
+        }
+    }
+}
+
+/// AddressSpec is address split into local part and domain.
+/// Local part is kept unquoted, so `"john doe"@example.com` has local part `john doe`.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::address::{AddressSpec, Domain, EmailAddress};
+///let address = EmailAddress::parse_single("John <\"john@home\"@[192.0.2.1]>").unwrap();
+///assert_eq!(address.address, "\"john@home\"@[192.0.2.1]");
+///
+///let spec = address.spec().unwrap();
+///assert_eq!(spec.local_part, "john@home");
+///assert_eq!(spec.domain, Domain::Literal("192.0.2.1".into()));
+///assert!(spec.domain.ip().is_some());
+///assert_eq!(spec, AddressSpec::parse("\"john@home\" @ (comment) [192.0.2.1]").unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct AddressSpec<'a> {
+    pub local_part: Cow<'a, str>,
+    pub domain: Domain<'a>,
+}
+
+impl<'a> AddressSpec<'a> {
+    /// parse parses address spec like `john@example.com`. Whole text has to be single address spec.
+    pub fn parse(text: &'a str) -> Result<Self, EmailAddressParseError> {
+        Self::parse_with_options(text, AddressParseOptions::default())
+    }
+
+    /// parse_with_options parses address spec like `parse` using given options
+    pub fn parse_with_options(text: &'a str, options: AddressParseOptions) -> Result<Self, EmailAddressParseError> {
+        let mut pa = AddressParser::with_options(text, options);
+        if pa.is_empty() {
+            return Err(EmailAddressParseError::InputEmpty);
+        }
+        let start = pa.address;
+        let spec = pa.take_address_spec()?;
+        pa.check_mode(start)?;
+        pa.skip_cfws()?;
+        if !pa.is_empty() {
+            return Err(EmailAddressParseError::InvalidAddressSpec(pa.position("end of address")));
+        }
+        Ok(spec)
+    }
+
+    /// into_owned makes `AddressSpec` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> AddressSpec<'static> {
+        AddressSpec {
+            local_part: Cow::Owned(self.local_part.into_owned()),
+            domain: self.domain.into_owned(),
+        }
+    }
+
+    /// is_ascii checks if address can be sent without SMTPUTF8 extension
+    pub fn is_ascii(&self) -> bool {
+        self.local_part.is_ascii() && self.domain.is_ascii()
+    }
+
+    /// to_ascii downgrades address so it can be sent without SMTPUTF8 extension.
+    /// Domain is converted to A-labels. Local part can't be converted, so address with UTF-8 local part is rejected.
+    ///
+    /// # Example
+    /// ```rust
+    ///# use smtpc::mail::address::AddressSpec;
+    ///let spec = AddressSpec::parse("info@bücher.example").unwrap();
+    ///assert_eq!(spec.to_ascii().unwrap().to_string(), "info@xn--bcher-kva.example");
+    ///assert!(AddressSpec::parse("josé@example.com").unwrap().to_ascii().is_err());
+    /// ```
+    pub fn to_ascii(&self) -> Result<AddressSpec<'_>, IdnaError> {
+        if !self.local_part.is_ascii() {
+            return Err(IdnaError::NonAsciiLocalPart);
+        }
+        Ok(AddressSpec {
+            local_part: Cow::Borrowed(&self.local_part),
+            domain: self.domain.to_ascii()?,
+        })
+    }
+
+    /// to_unicode converts A-labels of domain to U-labels
+    pub fn to_unicode(&self) -> Result<AddressSpec<'_>, IdnaError> {
+        Ok(AddressSpec {
+            local_part: Cow::Borrowed(&self.local_part),
+            domain: self.domain.to_unicode()?,
+        })
+    }
 }
 
+/// is_dot_atom checks if text can be put in address without quoting
+fn is_dot_atom(text: &str) -> bool {
+    !text.is_empty() &&
+        !text.starts_with('.') &&
+        !text.ends_with('.') &&
+        !text.contains("..") &&
+        text.chars().all(|c| is_atext(c, true, false))
+}
+
+impl<'a> fmt::Display for AddressSpec<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if is_dot_atom(&self.local_part) {
+            write!(f, "{}@{}", self.local_part, self.domain)
+        } else {
+            write!(f, "{}@{}", quote_string(&self.local_part, true), self.domain)
+        }
+    }
+}
+
+/// EmailAddressParseError is returned when address can't be parsed.
+/// Positions are relative to the beginning of parsed text.
 #[derive(Debug, From)]
 pub enum EmailAddressParseError {
     InputEmpty,
-    InvalidAtom,
-    InvalidAddressSpec,
-    InvalidQuotedString,
-    InvalidComment,
-    InvalidGroup,
+    InvalidAtom(ErrorPosition),
+    InvalidAddressSpec(ErrorPosition),
+    InvalidQuotedString(ErrorPosition),
+    InvalidComment(ErrorPosition),
+    InvalidGroup(ErrorPosition),
 }
 
+impl EmailAddressParseError {
+    /// position returns place in input where error was found if it's known
+    pub fn position(&self) -> Option<&ErrorPosition> {
+        match self {
+            EmailAddressParseError::InputEmpty => None,
+            EmailAddressParseError::InvalidAtom(p) |
+            EmailAddressParseError::InvalidAddressSpec(p) |
+            EmailAddressParseError::InvalidQuotedString(p) |
+            EmailAddressParseError::InvalidComment(p) |
+            EmailAddressParseError::InvalidGroup(p) => Some(p),
+        }
+    }
+}
+
+impl fmt::Display for EmailAddressParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmailAddressParseError::InputEmpty => write!(f, "address is empty"),
+            EmailAddressParseError::InvalidAtom(p) => write!(f, "invalid atom: {}", p),
+            EmailAddressParseError::InvalidAddressSpec(p) => write!(f, "invalid address: {}", p),
+            EmailAddressParseError::InvalidQuotedString(p) => write!(f, "invalid quoted string: {}", p),
+            EmailAddressParseError::InvalidComment(p) => write!(f, "invalid comment: {}", p),
+            EmailAddressParseError::InvalidGroup(p) => write!(f, "invalid address list: {}", p),
+        }
+    }
+}
+
+impl error::Error for EmailAddressParseError {}
+
 struct AddressParser<'a> {
+    input: &'a str,
     address: &'a str,
+    options: AddressParseOptions,
 }
 
 impl<'a> AddressParser<'a> {
     pub fn new(address: &'a str) -> Self {
+        Self::with_options(address, AddressParseOptions::default())
+    }
+
+    pub fn with_options(address: &'a str, options: AddressParseOptions) -> Self {
         Self {
+            input: address,
             address: address.trim(),
+            options,
         }
     }
 
+    /// position_at returns position of given offset in remaining text
+    fn position_at(&self, offset: usize, expected: &'static str) -> ErrorPosition {
+        let base = offset_in(self.input.as_bytes(), self.address.as_bytes());
+        ErrorPosition::new(self.input, base + offset, expected)
+    }
+
+    /// position returns current position of parser
+    #[inline]
+    fn position(&self, expected: &'static str) -> ErrorPosition {
+        self.position_at(0, expected)
+    }
+
+    /// decode_atoms joins run of atoms with single space, decodes encoded words in them and clears the run.
+    /// `None` is returned when there are no atoms.
+    fn decode_atoms(&self, atoms: &mut Vec<&'a str>) -> Option<String> {
+        if atoms.is_empty() {
+            return None;
+        }
+        let res = parse_maybe_rfc_2047(&atoms.join(" ")).into_owned();
+        atoms.clear();
+        Some(res)
+    }
+
     pub fn take_quoted_string(&mut self, take_quotes: bool) -> Result<String, EmailAddressParseError> {
         let mut byte_offset = 0;
         let mut state = if take_quotes { 0 } else { 1 };
@@ -70,7 +489,7 @@ impl<'a> AddressParser<'a> {
                     // eprintln!("First char is quote!");
                     state = 1;
                 } else {
-                    return Err(EmailAddressParseError::InvalidQuotedString);
+                    return Err(EmailAddressParseError::InvalidQuotedString(self.position("`\"`")));
                 }
             } else if state == 1 {
                 if c == '\\' {
@@ -87,17 +506,22 @@ impl<'a> AddressParser<'a> {
             byte_offset += c.len_utf8();
         }
         if !found_end_quote && take_quotes {
-            return Err(EmailAddressParseError::InvalidQuotedString);
+            return Err(EmailAddressParseError::InvalidQuotedString(self.position_at(byte_offset, "closing `\"`")));
         }
         debug_assert!(byte_offset <= self.address.len());
         // eprintln!("Unquoting: {:?}", &self.address[1..byte_offset]);
-        let res = match unquote_string(&self.address[if take_quotes { 1 } else { 0 }..byte_offset], false) {
+        let start = if take_quotes { 1 } else { 0 };
+        let res = match unquote_string(&self.address[start..byte_offset], false) {
             Ok(v) => {
                 // panic!("Unquoting succeed! {:?}", v);
                 v
             }
-            Err(_) => {
-                return Err(EmailAddressParseError::InvalidQuotedString);
+            Err(e) => {
+                let position = match e.position() {
+                    Some(p) => self.position_at(start + p.offset, p.expected),
+                    None => self.position("quoted string"),
+                };
+                return Err(EmailAddressParseError::InvalidQuotedString(position));
             }
         };
         self.address = &self.address[if take_quotes { 1 } else { 0 } + byte_offset..];
@@ -124,6 +548,15 @@ impl<'a> AddressParser<'a> {
         }
     }
 
+    /// skip_cfws takes white space and comments. It fails if comment is not valid.
+    fn skip_cfws(&mut self) -> Result<(), EmailAddressParseError> {
+        if self.take_cfws() {
+            Ok(())
+        } else {
+            Err(EmailAddressParseError::InvalidComment(self.position("comment")))
+        }
+    }
+
     pub fn take_char(&mut self) -> Option<char> {
         if let Some(chr) = self.peek_char() {
             self.address = &self.address[chr.len_utf8()..];
@@ -169,14 +602,21 @@ impl<'a> AddressParser<'a> {
         }
         let atom = &self.address[..byte_offset];
         if byte_offset == 0 {
-            return Err(EmailAddressParseError::InvalidAtom);
+            return Err(EmailAddressParseError::InvalidAtom(self.position("atom")));
         }
-        if !permissive && (
-            atom.starts_with('.') ||
-                atom.contains("..") ||
-                atom.ends_with('.')
-        ) {
-            return Err(EmailAddressParseError::InvalidAtom);
+        if !permissive {
+            let misplaced_dot = if atom.starts_with('.') {
+                Some(0)
+            } else if let Some(idx) = atom.find("..") {
+                Some(idx + 1)
+            } else if atom.ends_with('.') {
+                Some(atom.len() - 1)
+            } else {
+                None
+            };
+            if let Some(idx) = misplaced_dot {
+                return Err(EmailAddressParseError::InvalidAtom(self.position_at(idx, "atom text around dot")));
+            }
         }
 
         self.address = &self.address[byte_offset..];
@@ -185,47 +625,43 @@ impl<'a> AddressParser<'a> {
 
     pub fn take_phrase(&mut self) -> Result<String, EmailAddressParseError> {
         let mut words = Vec::new();
-        let mut is_prev_encoded = false;
+        // adjacent atoms are decoded together, so white space between encoded words is dropped
+        // and chars split between them are joined
+        let mut atoms = Vec::new();
         let mut err = Ok(());
         loop {
             self.take_white_chars();
-            let (word, is_encoded) = match self.peek_char() {
+            let word = match self.peek_char() {
                 None => break,
                 Some('"') => {
                     match self.take_quoted_string(true) {
-                        Ok(v) => {
-                            // eprintln!("Took quoted string: {:?}", v);
-                            (v, false)
-                        }
+                        Ok(v) => v,
                         Err(e) => {
-                            // eprintln!("Err take quoted string!: {:?}", ap.address);
                             err = Err(e);
                             break;
                         }
                     }
                 }
                 Some(_) => {
-                    match self.take_atom(true, true)
-                        .map(|v| parse_maybe_rfc_2047_is_encoded(v)) {
-                        Ok(Ok((s, b))) => {
-                            // eprintln!("Taken atom: {:?} Parsed: {:?}", s.as_ref(), b);
-                            (s.to_string(), b)
+                    match self.take_atom(true, true) {
+                        Ok(v) => {
+                            atoms.push(v);
+                            continue;
                         }
-                        _ => {
-                            // eprintln!("Field to take an atom: {:?}", ap.address);
-                            err = Err(EmailAddressParseError::InvalidAtom);
+                        Err(e) => {
+                            err = Err(e);
                             break;
                         }
                     }
                 }
             };
-            if is_prev_encoded && is_encoded {
-                let words_sz = words.len();
-                words[words_sz - 1] = format!("{}{}", words[words.len() - 1], word);
-            } else {
-                words.push(word);
+            if let Some(v) = self.decode_atoms(&mut atoms) {
+                words.push(v);
             }
-            is_prev_encoded = is_encoded;
+            words.push(word);
+        }
+        if let Some(v) = self.decode_atoms(&mut atoms) {
+            words.push(v);
         }
 
         if words.is_empty() && err.is_err() {
@@ -235,7 +671,7 @@ impl<'a> AddressParser<'a> {
         Ok(words.join(" "))
     }
 
-    pub fn take_address_spec_may_rollback(&mut self) -> Result<String, EmailAddressParseError> {
+    pub fn take_address_spec_may_rollback(&mut self) -> Result<AddressSpec<'a>, EmailAddressParseError> {
         let ea = self.address;
         match self.take_address_spec() {
             Err(e) => {
@@ -248,7 +684,7 @@ impl<'a> AddressParser<'a> {
         }
     }
 
-    pub fn take_address_spec(&mut self) -> Result<String, EmailAddressParseError> {
+    pub fn take_address_spec(&mut self) -> Result<AddressSpec<'a>, EmailAddressParseError> {
         let local_part = match self.peek_char() {
             Some('"') => {
                 Cow::Owned(self.take_quoted_string(true)?)
@@ -257,46 +693,97 @@ impl<'a> AddressParser<'a> {
                 Cow::Borrowed(self.take_atom(true, false)?)
             }
         };
-        if self.is_empty() {
-            return Err(EmailAddressParseError::InvalidAddressSpec);
+        self.skip_cfws()?;
+        if !self.consume_char('@') {
+            return Err(EmailAddressParseError::InvalidAddressSpec(self.position("`@`")));
         }
-        match self.take_char() {
-            Some('@') => {
-                // ok
-            }
-            None | Some(_) => {
-                return Err(EmailAddressParseError::InvalidAddressSpec);
+        self.skip_cfws()?;
+        if self.address.is_empty() {
+            return Err(EmailAddressParseError::InvalidAddressSpec(self.position("domain")));
+        }
+        let domain = self.take_domain()?;
+        Ok(AddressSpec {
+            local_part,
+            domain,
+        })
+    }
+
+    /// check_mode checks if text taken since start is allowed in selected `AddressMode`
+    fn check_mode(&self, start: &'a str) -> Result<(), EmailAddressParseError> {
+        if self.options.mode == AddressMode::Eai {
+            return Ok(());
+        }
+        let taken = &start[..start.len() - self.address.len()];
+        match taken.find(|c: char| !c.is_ascii()) {
+            Some(idx) => Err(EmailAddressParseError::InvalidAddressSpec(ErrorPosition::new(
+                self.input,
+                offset_in(self.input.as_bytes(), start.as_bytes()) + idx,
+                "ASCII char",
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// take_domain takes dot-atom domain or domain literal in square brackets
+    pub fn take_domain(&mut self) -> Result<Domain<'a>, EmailAddressParseError> {
+        if !self.consume_char('[') {
+            return Ok(Domain::Name(Cow::Borrowed(self.take_atom(true, false)?)));
+        }
+        let mut byte_offset = 0;
+        for c in self.address.chars() {
+            match c {
+                ']' => {
+                    let literal = self.address[..byte_offset].trim();
+                    self.address = &self.address[byte_offset + 1..];
+                    return Ok(Domain::Literal(Cow::Borrowed(literal)));
+                }
+                '[' | '\\' => break,
+                c if is_vchar(c) || is_white_space(c) => {}
+                _ => break,
             }
+            byte_offset += c.len_utf8();
         }
-        self.take_white_chars();
-        if self.address.is_empty() {
-            return Err(EmailAddressParseError::InvalidAddressSpec);
+        let expected = if byte_offset == self.address.len() { "`]`" } else { "domain literal text" };
+        Err(EmailAddressParseError::InvalidAddressSpec(self.position_at(byte_offset, expected)))
+    }
+
+    /// take_obs_route takes obsolete source route like `@a,@b:` in front of address spec if there is one.
+    /// Route is ignored. See RFC 5322 section 4.4
+    pub fn take_obs_route(&mut self) -> Result<(), EmailAddressParseError> {
+        if self.peek_char() != Some('@') {
+            return Ok(());
+        }
+        loop {
+            if !self.consume_char('@') {
+                return Err(EmailAddressParseError::InvalidAddressSpec(self.position("`@`")));
+            }
+            self.skip_cfws()?;
+            let start = self.address;
+            self.take_domain()?;
+            self.check_mode(start)?;
+            self.skip_cfws()?;
+            if self.consume_char(':') {
+                return Ok(());
+            }
+            if !self.consume_char(',') {
+                return Err(EmailAddressParseError::InvalidAddressSpec(self.position("`,` or `:`")));
+            }
+            // obsolete domain list may contain empty elements
+            loop {
+                self.skip_cfws()?;
+                if !self.consume_char(',') {
+                    break;
+                }
+            }
         }
-        let domain = self.take_atom(true, false).map(Ok)
-            .unwrap_or(Err(EmailAddressParseError::InvalidAtom))?;
-        let mut res = String::new();
-        res.push_str(&local_part);
-        res.push('@');
-        res.push_str(&domain);
-        Ok(res)
     }
 
     pub fn take_display_name_comment(&mut self) -> Result<String, EmailAddressParseError> {
-        match self.take_char() {
-            Some('(') => {}
-            None | Some(_) => {
-                return Err(EmailAddressParseError::InvalidComment);
-            }
+        if !self.consume_char('(') {
+            return Err(EmailAddressParseError::InvalidComment(self.position("`(`")));
         }
         let comment = self.take_comment(true)?;
-        let splits: Result<Vec<_>, _> = comment.split(' ')
-            .flat_map(|s| s.split('\t'))
-            .map(|r| parse_maybe_rfc_2047(r))
-            .collect();
-        let splits = splits.map_err(|_| EmailAddressParseError::InvalidComment)?;
-
-        let res = splits.join(" ");
-        Ok(res)
+        Ok(parse_maybe_rfc_2047(&comment).into_owned())
     }
 
     pub fn take_comment(&mut self, is_first_consumed: bool) -> Result<String, EmailAddressParseError> {
@@ -313,8 +800,9 @@ impl<'a> AddressParser<'a> {
                 } else if chr == ')' {
                     depth -= 1;
                 } else if chr == '\\' {
-                    self.take_char().map(Ok)
-                        .unwrap_or(Err(EmailAddressParseError::InvalidComment))?;
+                    if self.take_char().is_none() {
+                        return Err(EmailAddressParseError::InvalidComment(self.position("escaped char")));
+                    }
                     continue;
                 }
 
@@ -322,38 +810,41 @@ impl<'a> AddressParser<'a> {
                     res.push(chr);
                 }
             } else {
-                return Err(EmailAddressParseError::InvalidComment);
+                return Err(EmailAddressParseError::InvalidComment(self.position("`)`")));
             }
         }
         if depth != 0 {
-            return Err(EmailAddressParseError::InvalidComment);
+            return Err(EmailAddressParseError::InvalidComment(self.position("`)`")));
         }
         Ok(res)
     }
 
-    pub fn take_address(&mut self, allow_many: bool) -> Result<Vec<EmailAddress<'a>>, EmailAddressParseError> {
+    /// take_address takes single mailbox or, if `allow_group` is set, group of mailboxes
+    pub fn take_address(&mut self, allow_group: bool) -> Result<Address<'a>, EmailAddressParseError> {
         if self.is_empty() {
             return Err(EmailAddressParseError::InputEmpty);
         }
         self.take_white_chars();
 
+        let start = self.address;
         if let Ok(spec) = self.take_address_spec_may_rollback() {
+            self.check_mode(start)?;
             self.take_white_chars();
             let dn = if self.peek_char() == Some('(') {
                 self.take_display_name_comment()?
             } else {
                 String::new()
             };
-            return Ok(vec![EmailAddress {
+            return Ok(Address::Mailbox(EmailAddress {
                 name: Cow::Owned(dn),
-                address: Cow::Owned(spec),
-            }]);
+                address: Cow::Owned(spec.to_string()),
+            }));
         }
         // not an addr-spec address.
 
         let dn = match self.peek_char() {
             None => {
-                return Err(EmailAddressParseError::InvalidComment);
+                return Err(EmailAddressParseError::InvalidAddressSpec(self.position("address")));
             }
             Some('<') => {
                 String::new()
@@ -364,42 +855,41 @@ impl<'a> AddressParser<'a> {
         };
         self.take_white_chars();
 
-        if allow_many && self.consume_char(':') {
-            return self.take_group_list();
+        if allow_group && self.consume_char(':') {
+            return Ok(Address::Group {
+                name: Cow::Owned(dn),
+                members: self.take_group_list()?,
+            });
         }
 
-        match self.peek_char() {
-            None => {
-                return Err(EmailAddressParseError::InvalidAtom);
-            }
-            Some('<') => {
-                self.take_char().expect("Peek succeed. Take can't fail.");
-            }
-            Some(_) => {
-                if !dn.chars().all(|c| is_atext(c, true, false)) {
-                    return Err(EmailAddressParseError::InvalidAtom); // TODO(teawithsand) proper error
-                }
-                return Err(EmailAddressParseError::InvalidAtom); // TODO(teawithsand) proper error
-            }
-        };
+        // display name has to be followed by address in angle brackets
+        if !self.consume_char('<') {
+            return Err(EmailAddressParseError::InvalidAddressSpec(self.position("`<`")));
+        }
 
+        self.skip_cfws()?;
+        self.take_obs_route()?;
+        self.skip_cfws()?;
+        let start = self.address;
         let spec = self.take_address_spec()?;
-        match self.peek_char() {
-            None => {
-                return Err(EmailAddressParseError::InvalidAddressSpec);
-            }
-            Some('>') => {
-                self.take_char().expect("Peek succeed. Take can't fail.");
-            }
-            Some(_c) => {
-                return Err(EmailAddressParseError::InvalidAddressSpec);
-            }
-        };
+        self.check_mode(start)?;
+        self.skip_cfws()?;
+        if !self.consume_char('>') {
+            return Err(EmailAddressParseError::InvalidAddressSpec(self.position("`>`")));
+        }
 
-        Ok(vec![EmailAddress {
+        Ok(Address::Mailbox(EmailAddress {
             name: Cow::Owned(dn),
-            address: Cow::Owned(spec),
-        }])
+            address: Cow::Owned(spec.to_string()),
+        }))
+    }
+
+    /// take_mailbox takes single address, which can't be a group
+    pub fn take_mailbox(&mut self) -> Result<EmailAddress<'a>, EmailAddressParseError> {
+        match self.take_address(false)? {
+            Address::Mailbox(m) => Ok(m),
+            Address::Group { .. } => unreachable!("Take address with group not allowed returns mailbox or error"),
+        }
     }
 
     pub fn take_group_list(&mut self) -> Result<Vec<EmailAddress<'a>>, EmailAddressParseError> {
@@ -411,12 +901,10 @@ impl<'a> AddressParser<'a> {
         let mut res = Vec::new();
         loop {
             self.take_white_chars();
-            let address = self.take_address(false)?;
-            debug_assert_eq!(address.len(), 1, "Take address with multiple false returns either one address or error");
-            res.extend_from_slice(&address[..]);
+            res.push(self.take_mailbox()?);
 
             if !self.take_cfws() {
-                return Err(EmailAddressParseError::InvalidGroup);
+                return Err(EmailAddressParseError::InvalidGroup(self.position("comment")));
             }
 
             if self.consume_char(';') {
@@ -426,22 +914,20 @@ impl<'a> AddressParser<'a> {
             }
 
             if !self.consume_char(',') {
-                return Err(EmailAddressParseError::InvalidGroup);
+                return Err(EmailAddressParseError::InvalidGroup(self.position("`,` or `;`")));
             }
         }
         Ok(res)
     }
 
-    pub fn take_address_list(&mut self) -> Result<Vec<EmailAddress<'a>>, EmailAddressParseError> {
+    pub fn take_address_list(&mut self) -> Result<Vec<Address<'a>>, EmailAddressParseError> {
         let mut res = Vec::new();
         loop {
             self.take_white_chars();
-            let address = self.take_address(true)?;
-            // debug_assert_eq!(address.len(), 1, "Take address with multiple false returns either one address or error");
-            res.extend_from_slice(&address[..]);
+            res.push(self.take_address(true)?);
 
             if !self.take_cfws() {
-                return Err(EmailAddressParseError::InvalidGroup);
+                return Err(EmailAddressParseError::InvalidGroup(self.position("comment")));
             }
 
             if self.is_empty() {
@@ -449,7 +935,7 @@ impl<'a> AddressParser<'a> {
             }
 
             if !self.consume_char(',') {
-                return Err(EmailAddressParseError::InvalidGroup);
+                return Err(EmailAddressParseError::InvalidGroup(self.position("`,`")));
             }
         }
         Ok(res)
@@ -459,15 +945,19 @@ impl<'a> AddressParser<'a> {
 impl<'a> EmailAddress<'a> {
     // in fact it's static right now BUT it may change in future
     pub fn parse_single(address: &'a str) -> Result<EmailAddress<'a>, EmailAddressParseError> {
-        let mut pa = AddressParser::new(address);
-        let mut res = pa.take_address(false)?;
-        assert_eq!(res.len(), 1, "Take address with multiple false returns either one address or error");
-        Ok(res.remove(0))
+        Self::parse_single_with_options(address, AddressParseOptions::default())
+    }
+
+    /// parse_single_with_options parses single address like `parse_single` using given options
+    pub fn parse_single_with_options(address: &'a str, options: AddressParseOptions) -> Result<EmailAddress<'a>, EmailAddressParseError> {
+        let mut pa = AddressParser::with_options(address, options);
+        pa.take_mailbox()
     }
 
+    /// parse_group parses address list and returns all mailboxes in it.
+    /// Use `Address::parse_list` in order to keep groups.
     pub fn parse_group(addresses: &'a str) -> Result<Vec<EmailAddress<'a>>, EmailAddressParseError> {
-        let mut pa = AddressParser::new(addresses);
-        pa.take_address_list()
+        Address::parse_list(addresses).map(Address::flatten)
     }
 }
 
@@ -483,14 +973,11 @@ impl FromStr for EmailAddress<'static> {
 // TODO(teawithsand) remove below fns
 pub fn parse_address(address: &str) -> Result<EmailAddress, EmailAddressParseError> {
     let mut pa = AddressParser::new(address);
-    let mut res = pa.take_address(false)?;
-    assert_eq!(res.len(), 1, "Take address with multiple false returns either one address or error");
-    Ok(res.remove(0))
+    pa.take_mailbox()
 }
 
 pub fn parse_address_group(address: &str) -> Result<Vec<EmailAddress>, EmailAddressParseError> {
-    let mut pa = AddressParser::new(address);
-    pa.take_address_list()
+    EmailAddress::parse_group(address)
 }
 
 #[cfg(test)]
@@ -542,6 +1029,18 @@ mod test {
                 name: Cow::Borrowed("John Doe"),
                 address: Cow::Borrowed("asdf@example.com"),
             })),
+            ("=?UTF-8?B?xQ==?= =?UTF-8?B?gQ==?=ukasz \"Q\" =?ISO-8859-2?Q?=A3?= <asdf@example.com>", Some(EmailAddress {
+                name: Cow::Borrowed("Łukasz Q Ł"),
+                address: Cow::Borrowed("asdf@example.com"),
+            })),
+            ("=?UTF-8?Q?J=C3=B3zef?= =?UTF-8?Q?=ZZ?= <asdf@example.com>", Some(EmailAddress {
+                name: Cow::Borrowed("Józef =?UTF-8?Q?=ZZ?="),
+                address: Cow::Borrowed("asdf@example.com"),
+            })),
+            ("asdf@example.com (=?UTF-8?Q?J=C3=B3zef?= =?UTF-8?Q?_Doe?=)", Some(EmailAddress {
+                name: Cow::Borrowed("Józef Doe"),
+                address: Cow::Borrowed("asdf@example.com"),
+            })),
             ("<asdf@example.com> (CFWS (cfws))  (another comment)", Some(EmailAddress {
                 name: Cow::Borrowed(""),
                 address: Cow::Borrowed("asdf@example.com"),
@@ -590,4 +1089,234 @@ mod test {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_can_parse_address_list_with_groups() {
+        let mailbox = |name: &'static str, address: &'static str| EmailAddress {
+            name: Cow::Borrowed(name),
+            address: Cow::Borrowed(address),
+        };
+        for (i, o) in [
+            ("undisclosed-recipients:;", Some(vec![
+                Address::Group {
+                    name: Cow::Borrowed("undisclosed-recipients"),
+                    members: vec![],
+                },
+            ])),
+            ("a@example.com, Team: John Doe <john@example.com>, jane@example.com;, Empty: ;", Some(vec![
+                Address::Mailbox(mailbox("", "a@example.com")),
+                Address::Group {
+                    name: Cow::Borrowed("Team"),
+                    members: vec![
+                        mailbox("John Doe", "john@example.com"),
+                        mailbox("", "jane@example.com"),
+                    ],
+                },
+                Address::Group {
+                    name: Cow::Borrowed("Empty"),
+                    members: vec![],
+                },
+            ])),
+            ("\"The Team\": a@example.com; (comment)", Some(vec![
+                Address::Group {
+                    name: Cow::Borrowed("The Team"),
+                    members: vec![mailbox("", "a@example.com")],
+                },
+            ])),
+            ("Team: a@example.com", None),
+            ("Team: Inner: a@example.com;;", None),
+        ].iter() {
+            if let Some(o) = o {
+                let list = Address::parse_list(i).unwrap();
+                assert_eq!(o, &list, "{}", i);
+
+                let flat: Vec<_> = o.iter().flat_map(|a| a.mailboxes().iter().cloned()).collect();
+                assert_eq!(EmailAddress::parse_group(i).unwrap(), flat);
+            } else {
+                Address::parse_list(i).unwrap_err();
+            }
+        }
+    }
+
+    #[test]
+    fn test_can_split_address_spec() {
+        for (i, o) in [
+            ("john@example.com", Some(("john", Domain::Name("example.com".into()), "john@example.com"))),
+            ("john.j.doe @ (comment) example.com", Some(("john.j.doe", Domain::Name("example.com".into()), "john.j.doe@example.com"))),
+            ("\"john doe\"@example.com", Some(("john doe", Domain::Name("example.com".into()), "\"john doe\"@example.com"))),
+            ("\"john@home\"@example.com", Some(("john@home", Domain::Name("example.com".into()), "\"john@home\"@example.com"))),
+            ("\"a\\\"b\"@example.com", Some(("a\"b", Domain::Name("example.com".into()), "\"a\\\"b\"@example.com"))),
+            ("\"john\"@example.com", Some(("john", Domain::Name("example.com".into()), "john@example.com"))),
+            ("user@[192.0.2.1]", Some(("user", Domain::Literal("192.0.2.1".into()), "user@[192.0.2.1]"))),
+            ("user@[ IPv6:2001:db8::1 ]", Some(("user", Domain::Literal("IPv6:2001:db8::1".into()), "user@[IPv6:2001:db8::1]"))),
+            ("user@[192.0.2.1", None),
+            ("user@[a[b]", None),
+            ("user@example.com trailing", None),
+            ("\"john doe\"", None),
+            ("", None),
+        ].iter() {
+            match o {
+                Some((local_part, domain, canonical)) => {
+                    let spec = AddressSpec::parse(i).unwrap();
+                    assert_eq!(&spec.local_part, local_part, "{}", i);
+                    assert_eq!(&spec.domain, domain, "{}", i);
+                    assert_eq!(&spec.to_string(), canonical, "{}", i);
+                    assert_eq!(AddressSpec::parse(canonical).unwrap(), spec);
+                }
+                None => {
+                    AddressSpec::parse(i).unwrap_err();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_can_parse_address_with_route_and_literal() {
+        for (i, o) in [
+            ("<@a.example,@b.example:user@c.example>", Some("user@c.example")),
+            ("John <@a.example, ,@b.example : \"j d\"@c.example>", Some("\"j d\"@c.example")),
+            ("< (comment) user@[IPv6:2001:db8::1] (comment) >", Some("user@[IPv6:2001:db8::1]")),
+            ("<@a.example user@c.example>", None),
+            ("<@:user@c.example>", None),
+        ].iter() {
+            match o {
+                Some(o) => assert_eq!(EmailAddress::parse_single(i).unwrap().address, *o, "{}", i),
+                None => {
+                    EmailAddress::parse_single(i).unwrap_err();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_domain_literal_can_be_ip() {
+        for (i, o) in [
+            (Domain::Literal("192.0.2.1".into()), Some("192.0.2.1")),
+            (Domain::Literal("ipv6:2001:db8::1".into()), Some("2001:db8::1")),
+            (Domain::Literal("IPv6:192.0.2.1".into()), None),
+            (Domain::Literal("x-tag:abc".into()), None),
+            (Domain::Name("192.0.2.1".into()), None),
+        ].iter() {
+            assert_eq!(i.ip().map(|ip| ip.to_string()), o.map(String::from), "{}", i);
+        }
+    }
+
+    #[test]
+    fn test_utf8_addresses_depend_on_mode() {
+        let ascii = AddressParseOptions {
+            mode: AddressMode::Ascii,
+        };
+        for (i, o, ascii_offset) in [
+            ("用户@例子.广告", "用户@例子.广告", Some(0)),
+            ("José <josé@exämple.de>", "josé@exämple.de", Some(10)),
+            ("\"josé d\"@example.de", "\"josé d\"@example.de", Some(4)),
+            ("user@[IPv6:2001:db8::1]", "user@[IPv6:2001:db8::1]", None),
+            ("Józef <user@example.com>", "user@example.com", None),
+            ("<@relay.example,@exämple.de:user@example.com>", "user@example.com", Some(19)),
+            ("<@relay.example:user@example.com>", "user@example.com", None),
+        ].iter() {
+            assert_eq!(EmailAddress::parse_single(i).unwrap().address, *o, "{}", i);
+            match (EmailAddress::parse_single_with_options(i, ascii), ascii_offset) {
+                (Ok(a), None) => assert_eq!(a.address, *o),
+                (Err(EmailAddressParseError::InvalidAddressSpec(p)), Some(offset)) => {
+                    assert_eq!((p.offset, p.expected), (*offset, "ASCII char"), "{}", i);
+                }
+                (r, _) => panic!("Unexpected result for {}: {:?}", i, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_can_convert_address_spec_between_labels() {
+        for (u, a) in [
+            ("用户@例子.广告", None),
+            ("info@例子.广告", Some("info@xn--fsqu00a.xn--4rr70v")),
+            ("\"a b\"@exämple.de", Some("\"a b\"@xn--exmple-cua.de")),
+            ("user@[192.0.2.1]", Some("user@[192.0.2.1]")),
+        ].iter() {
+            let spec = AddressSpec::parse(u).unwrap();
+            match a {
+                Some(a) => {
+                    let downgraded = spec.to_ascii().unwrap();
+                    assert!(downgraded.is_ascii());
+                    assert_eq!(downgraded.to_string(), *a);
+                    assert_eq!(AddressSpec::parse(a).unwrap().to_unicode().unwrap(), spec);
+                }
+                None => match spec.to_ascii() {
+                    Err(IdnaError::NonAsciiLocalPart) => {}
+                    r => panic!("Unexpected result for {}: {:?}", u, r),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_can_format_address() {
+        for (name, address, o) in [
+            ("", "john@example.com", "john@example.com"),
+            ("John Doe", "john@example.com", "John Doe <john@example.com>"),
+            ("Doe, John", "john@example.com", "\"Doe, John\" <john@example.com>"),
+            ("John \"JD\" Doe", "john@example.com", "\"John \\\"JD\\\" Doe\" <john@example.com>"),
+            ("John J. Doe", "\"john doe\"@example.com", "\"John J. Doe\" <\"john doe\"@example.com>"),
+            ("Józef", "jozef@example.com", "=?UTF-8?B?SsOzemVm?= <jozef@example.com>"),
+        ].iter() {
+            let address = EmailAddress {
+                name: Cow::Borrowed(name),
+                address: Cow::Borrowed(address),
+            };
+            assert_eq!(address.to_string(), *o);
+            assert_eq!(EmailAddress::parse_single(o).unwrap(), address, "{}", o);
+        }
+    }
+
+    #[test]
+    fn test_can_format_address_list() {
+        for (i, o) in [
+            ("undisclosed-recipients:;", "undisclosed-recipients:;"),
+            ("a@example.com , \"Doe, John\"<john@example.com>", "a@example.com, \"Doe, John\" <john@example.com>"),
+            ("\"The Team\":Józef <a@example.com>,b@example.com;, c@example.com", "The Team: =?UTF-8?B?SsOzemVm?= <a@example.com>, b@example.com;, c@example.com"),
+        ].iter() {
+            let list = Address::parse_list(i).unwrap();
+            assert_eq!(Address::list_to_header_value(&list), *o);
+            assert_eq!(list.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", "), *o);
+            assert_eq!(Address::parse_list(o).unwrap(), list);
+        }
+    }
+
+    #[test]
+    fn test_long_address_list_is_folded() {
+        let list: Vec<_> = (0..10)
+            .map(|i| Address::Mailbox(EmailAddress {
+                name: Cow::Owned(format!("Recipient Number {}", i)),
+                address: Cow::Owned(format!("recipient{}@example.com", i)),
+            }))
+            .collect();
+        let header = Address::list_to_header("To", &list);
+        assert!(header.ends_with("\r\n"));
+        let lines: Vec<_> = header.trim_end().split("\r\n").collect();
+        assert!(lines.len() > 1);
+        for line in lines.iter() {
+            assert!(line.len() <= 78, "{:?}", line);
+        }
+
+        let value = header.trim_end().replace("\r\n", "");
+        let value = value.strip_prefix("To:").unwrap();
+        assert_eq!(Address::parse_list(value).unwrap(), list);
+    }
+
+    #[test]
+    fn test_address_errors_name_invalid_part() {
+        for (i, o) in [
+            ("", "address is empty"),
+            ("John Doe <john@example.com", "invalid address: expected `>` at byte 26, found end of input"),
+            ("John Doe john@example.com", "invalid address: expected `<` at byte 25, found end of input"),
+            ("\"John <john@example.com>", "invalid quoted string: expected closing `\"` at byte 24, found end of input"),
+            ("\"a\\\u{1}\"@example.com", "invalid quoted string: expected quoted text at byte 3, found \"\\u{1}\\\"@example.com\""),
+            ("john@example.com (Doe", "invalid comment: expected `)` at byte 21, found end of input"),
+            ("a@example.com, b@example.com c", "invalid address list: expected `,` at byte 29, found \"c\""),
+        ].iter() {
+            let err = parse_address_group(i).unwrap_err();
+            assert_eq!(err.to_string(), *o, "{:?}", i);
+        }
+    }
+}
@@ -0,0 +1,669 @@
+use std::collections::hash_map::RandomState;
+use std::error;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::mail::address::EmailAddress;
+use crate::mail::date::format_date;
+use crate::mail::header::encode_param;
+use crate::utils::cc::{is_ftext, is_white_space};
+use crate::utils::hex::encode_hex_char;
+use crate::utils::quoted::{encode_header, fold_header};
+
+/// MessageBuildError is returned when message can't be written
+#[derive(Debug)]
+pub enum MessageBuildError {
+    /// InvalidHeaderName is returned when name of header is not valid RFC 5322 field name
+    InvalidHeaderName(String),
+    /// InvalidHeaderValue is returned when value of header, which is written as is, contains line break.
+    /// It contains name of header.
+    InvalidHeaderValue(String),
+    IOError(io::Error),
+}
+
+impl From<io::Error> for MessageBuildError {
+    fn from(e: io::Error) -> Self {
+        MessageBuildError::IOError(e)
+    }
+}
+
+impl fmt::Display for MessageBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageBuildError::InvalidHeaderName(n) => write!(f, "invalid header name: {:?}", n),
+            MessageBuildError::InvalidHeaderValue(n) => write!(f, "value of header `{}` contains line break", n),
+            MessageBuildError::IOError(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for MessageBuildError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            MessageBuildError::IOError(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+/// is_header_name checks if text is valid header field name. See RFC 5322 3.6.8
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_ftext)
+}
+
+/// check_header_value checks that value, which is written as is, can't break header into many ones
+fn check_header_value(name: &str, value: &str) -> Result<(), MessageBuildError> {
+    if value.contains(['\r', '\n']) {
+        return Err(MessageBuildError::InvalidHeaderValue(name.to_string()));
+    }
+    Ok(())
+}
+
+/// MAX_HARD_LINE_LENGTH is length of line which must not be exceeded. See RFC 5322 2.1.1
+const MAX_HARD_LINE_LENGTH: usize = 998;
+
+/// MAX_BASE64_LINE_LENGTH is length of base64 line. See RFC 2045 6.8
+const MAX_BASE64_LINE_LENGTH: usize = 76;
+
+static UNIQUE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// unique_token returns string which is unique for each call.
+/// It's not cryptographically secure, it's only supposed not to collide.
+fn unique_token() -> String {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    // RandomState is seeded randomly so it's good enough source of entropy
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(time.as_nanos());
+    hasher.write_u32(process::id());
+    hasher.write_usize(UNIQUE_COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:016x}{:08x}", hasher.finish(), time.as_secs() as u32)
+}
+
+/// generate_boundary generates multipart boundary.
+/// It contains `=_` sequence, which never occurs in base64 or quoted printable encoded data.
+pub fn generate_boundary() -> String {
+    format!("=_{}", unique_token())
+}
+
+/// generate_message_id generates unique message id(without angle brackets) for given domain
+pub fn generate_message_id(domain: &str) -> String {
+    format!("{}@{}", unique_token(), domain)
+}
+
+/// normalize_new_lines converts any new line to `\r\n`
+fn normalize_new_lines(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            res.push_str("\r\n");
+        }
+        res.push_str(line.strip_suffix('\r').unwrap_or(line));
+    }
+    res
+}
+
+/// encode_quoted_printable_text encodes text with quoted printable encoding.
+/// Line breaks are preserved and long lines are broken with soft line breaks.
+fn encode_quoted_printable_text(text: &str) -> Vec<u8> {
+    let mut res = Vec::with_capacity(text.len());
+    for (i, line) in text.split("\r\n").enumerate() {
+        if i > 0 {
+            res.extend_from_slice(b"\r\n");
+        }
+        let mut line_len = 0;
+        let bytes = line.as_bytes();
+        for (j, b) in bytes.iter().cloned().enumerate() {
+            let is_last = j + 1 == bytes.len();
+            let is_literal = match b {
+                b'=' => false,
+                // white space at the end of line has to be encoded. See RFC 2045 6.7 rule 3
+                b' ' | b'\t' => !is_last,
+                b'!'..=b'~' => true,
+                _ => false,
+            };
+            let len = if is_literal { 1 } else { 3 };
+            // line may have 76 chars including soft line break `=`
+            if line_len + len > 75 && !(is_last && line_len + len <= 76) {
+                res.extend_from_slice(b"=\r\n");
+                line_len = 0;
+            }
+            if is_literal {
+                res.push(b);
+            } else {
+                res.push(b'=');
+                res.extend_from_slice(&encode_hex_char(b));
+            }
+            line_len += len;
+        }
+    }
+    res
+}
+
+fn encode_base64_lines(data: &[u8]) -> Vec<u8> {
+    let encoded = base64::encode(data);
+    let mut res = Vec::with_capacity(encoded.len() + encoded.len() / MAX_BASE64_LINE_LENGTH * 2);
+    for (i, line) in encoded.as_bytes().chunks(MAX_BASE64_LINE_LENGTH).enumerate() {
+        if i > 0 {
+            res.extend_from_slice(b"\r\n");
+        }
+        res.extend_from_slice(line);
+    }
+    res
+}
+
+/// encode_text picks transfer encoding for text and encodes it with it
+fn encode_text(text: &str) -> (&'static str, Vec<u8>) {
+    let text = normalize_new_lines(text);
+    let non_ascii_count = text.bytes().filter(|b| !b.is_ascii()).count();
+    let is_7bit = non_ascii_count == 0 &&
+        !text.bytes().any(|b| b == 0) &&
+        text.split("\r\n").all(|l| l.len() <= MAX_HARD_LINE_LENGTH && !l.ends_with(is_white_space));
+    if is_7bit {
+        ("7bit", text.into_bytes())
+    } else if non_ascii_count * 3 > text.len() {
+        // quoted printable would triple size of most of text
+        ("base64", encode_base64_lines(text.as_bytes()))
+    } else {
+        ("quoted-printable", encode_quoted_printable_text(&text))
+    }
+}
+
+/// Attachment is file attached to message.
+/// It's either listed as attachment or displayed inline when it has content id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    content_type: String,
+    data: Vec<u8>,
+    filename: Option<String>,
+    content_id: Option<String>,
+}
+
+impl Attachment {
+    pub fn new(content_type: &str, data: Vec<u8>) -> Self {
+        Self {
+            content_type: content_type.to_string(),
+            data,
+            filename: None,
+            content_id: None,
+        }
+    }
+
+    pub fn filename(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_string());
+        self
+    }
+
+    /// content_id sets id(without angle brackets) which html body may refer to with `cid:` url
+    pub fn content_id(mut self, content_id: &str) -> Self {
+        self.content_id = Some(content_id.to_string());
+        self
+    }
+
+    /// check checks that values of headers of attachment are safe to be written
+    fn check(&self) -> Result<(), MessageBuildError> {
+        check_header_value("Content-Type", &self.content_type)?;
+        if let Some(content_id) = &self.content_id {
+            check_header_value("Content-ID", content_id)?;
+        }
+        Ok(())
+    }
+
+    fn to_node(&self) -> Node {
+        let disposition = if self.content_id.is_some() { "inline" } else { "attachment" };
+        let mut content_type = self.content_type.clone();
+        let mut disposition = disposition.to_string();
+        if let Some(filename) = &self.filename {
+            content_type = format!("{}; {}", content_type, encode_param("name", filename));
+            disposition = format!("{}; {}", disposition, encode_param("filename", filename));
+        }
+        let mut headers = vec![
+            ("Content-Type", content_type),
+            ("Content-Transfer-Encoding", "base64".to_string()),
+            ("Content-Disposition", disposition),
+        ];
+        if let Some(content_id) = &self.content_id {
+            headers.push(("Content-ID", format!("<{}>", content_id)));
+        }
+        Node::Leaf {
+            headers,
+            body: encode_base64_lines(&self.data),
+        }
+    }
+}
+
+enum Node {
+    Leaf {
+        headers: Vec<(&'static str, String)>,
+        body: Vec<u8>,
+    },
+    Multipart {
+        subtype: &'static str,
+        children: Vec<Node>,
+    },
+}
+
+impl Node {
+    fn text(subtype: &str, text: &str) -> Self {
+        let (cte, body) = encode_text(text);
+        Node::Leaf {
+            headers: vec![
+                ("Content-Type", format!("text/{}; charset=utf-8", subtype)),
+                ("Content-Transfer-Encoding", cte.to_string()),
+            ],
+            body,
+        }
+    }
+
+    fn multipart(subtype: &'static str, mut children: Vec<Node>) -> Self {
+        if children.len() == 1 {
+            children.remove(0)
+        } else {
+            Node::Multipart {
+                subtype,
+                children,
+            }
+        }
+    }
+
+    /// write_to writes headers of node and it's body
+    fn write_to<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        match self {
+            Node::Leaf { headers, body } => {
+                for (name, value) in headers {
+                    w.write_all(fold_header(name, value).as_bytes())?;
+                }
+                w.write_all(b"\r\n")?;
+                w.write_all(body)?;
+            }
+            Node::Multipart { subtype, children } => {
+                let boundary = generate_boundary();
+                let content_type = format!("multipart/{}; boundary=\"{}\"", subtype, boundary);
+                w.write_all(fold_header("Content-Type", &content_type).as_bytes())?;
+                w.write_all(b"\r\n")?;
+                for child in children {
+                    w.write_all(b"\r\n--")?;
+                    w.write_all(boundary.as_bytes())?;
+                    w.write_all(b"\r\n")?;
+                    child.write_to(w)?;
+                }
+                w.write_all(b"\r\n--")?;
+                w.write_all(boundary.as_bytes())?;
+                w.write_all(b"--\r\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// MessageBuilder composes MIME message and writes it in RFC 5322 format.
+///
+/// Structure of message depends on what was added to it:
+/// - text and html bodies are put in `multipart/alternative`
+/// - html body with inline attachments is put in `multipart/related`
+/// - attachments are put along with body in `multipart/mixed`
+///
+/// Parts which contain single part are not created.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::address::EmailAddress;
+///# use smtpc::mail::builder::{Attachment, MessageBuilder};
+///# use smtpc::mail::message::Message;
+///let data = MessageBuilder::new()
+///    .from("Bob <bob@example.com>".parse().unwrap())
+///    .to("alice@example.com".parse().unwrap())
+///    .subject("Hello")
+///    .text("Hello Alice!")
+///    .html("<b>Hello Alice!</b>")
+///    .attachment(Attachment::new("application/pdf", vec![1, 2, 3]).filename("doc.pdf"))
+///    .to_bytes()
+///    .unwrap();
+///
+///let message = Message::parse(&data).unwrap();
+///assert_eq!(message.root().mime_subtype(), "mixed");
+///assert_eq!(message.root().children()[1].decode_body().unwrap(), vec![1, 2, 3]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MessageBuilder<'a> {
+    from: Option<EmailAddress<'a>>,
+    reply_to: Option<EmailAddress<'a>>,
+    to: Vec<EmailAddress<'a>>,
+    cc: Vec<EmailAddress<'a>>,
+    bcc: Vec<EmailAddress<'a>>,
+    subject: Option<String>,
+    date: Option<u64>,
+    message_id: Option<String>,
+    headers: Vec<(String, String)>,
+
+    text: Option<String>,
+    html: Option<String>,
+    attachments: Vec<Attachment>,
+}
+
+impl<'a> MessageBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from(mut self, address: EmailAddress<'a>) -> Self {
+        self.from = Some(address);
+        self
+    }
+
+    pub fn reply_to(mut self, address: EmailAddress<'a>) -> Self {
+        self.reply_to = Some(address);
+        self
+    }
+
+    pub fn to(mut self, address: EmailAddress<'a>) -> Self {
+        self.to.push(address);
+        self
+    }
+
+    pub fn cc(mut self, address: EmailAddress<'a>) -> Self {
+        self.cc.push(address);
+        self
+    }
+
+    /// bcc adds recipient which is not written into message.
+    /// It's listed by `recipients` though.
+    pub fn bcc(mut self, address: EmailAddress<'a>) -> Self {
+        self.bcc.push(address);
+        self
+    }
+
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// date sets date of message as unix timestamp. When it's not set current time is used.
+    pub fn date(mut self, unix_timestamp: u64) -> Self {
+        self.date = Some(unix_timestamp);
+        self
+    }
+
+    /// message_id sets message id(without angle brackets).
+    /// When it's not set new one is generated each time message is written.
+    pub fn message_id(mut self, message_id: &str) -> Self {
+        self.message_id = Some(message_id.to_string());
+        self
+    }
+
+    /// header adds any other header. Non ASCII value is RFC 2047 encoded.
+    /// Name has to be valid RFC 5322 field name, otherwise writing message fails.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    pub fn html(mut self, html: &str) -> Self {
+        self.html = Some(html.to_string());
+        self
+    }
+
+    /// attachment adds attachment to message.
+    /// Attachments with content id are displayed inline along with html body.
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// recipients returns all recipients of message: to, cc and bcc ones
+    pub fn recipients(&self) -> impl Iterator<Item=&EmailAddress<'a>> {
+        self.to.iter().chain(self.cc.iter()).chain(self.bcc.iter())
+    }
+
+    fn body_node(&self) -> Node {
+        let mut alternatives = Vec::new();
+        if let Some(text) = &self.text {
+            alternatives.push(Node::text("plain", text));
+        }
+        if let Some(html) = &self.html {
+            let inline = self.attachments.iter()
+                .filter(|a| a.content_id.is_some())
+                .map(Attachment::to_node);
+            let related = std::iter::once(Node::text("html", html)).chain(inline).collect();
+            alternatives.push(Node::multipart("related", related));
+        }
+        if alternatives.is_empty() {
+            alternatives.push(Node::text("plain", ""));
+        }
+        Node::multipart("alternative", alternatives)
+    }
+
+    /// check checks that message can be written without producing headers which were not set
+    fn check(&self) -> Result<(), MessageBuildError> {
+        for (name, _) in &self.headers {
+            if !is_header_name(name) {
+                return Err(MessageBuildError::InvalidHeaderName(name.clone()));
+            }
+        }
+        let mailboxes = self.from.iter().map(|a| ("From", a))
+            .chain(self.reply_to.iter().map(|a| ("Reply-To", a)))
+            .chain(self.to.iter().map(|a| ("To", a)))
+            .chain(self.cc.iter().map(|a| ("Cc", a)));
+        for (name, address) in mailboxes {
+            // encoded display name would not break header, but line break would be part of decoded name
+            check_header_value(name, &address.name)?;
+            check_header_value(name, &address.address)?;
+        }
+        if let Some(id) = &self.message_id {
+            check_header_value("Message-ID", id)?;
+        }
+        for a in &self.attachments {
+            a.check()?;
+        }
+        Ok(())
+    }
+
+    /// write_to writes entire message.
+    /// Message is checked before anything is written, so nothing is written when it's invalid.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), MessageBuildError> {
+        self.check()?;
+        let date = self.date.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+        });
+        w.write_all(fold_header("Date", &format_date(date)).as_bytes())?;
+        if let Some(from) = &self.from {
+            w.write_all(fold_header("From", &from.to_header_value()).as_bytes())?;
+        }
+        if let Some(reply_to) = &self.reply_to {
+            w.write_all(fold_header("Reply-To", &reply_to.to_header_value()).as_bytes())?;
+        }
+        if !self.to.is_empty() {
+            w.write_all(fold_header("To", &EmailAddress::list_to_header_value(&self.to)).as_bytes())?;
+        }
+        if !self.cc.is_empty() {
+            w.write_all(fold_header("Cc", &EmailAddress::list_to_header_value(&self.cc)).as_bytes())?;
+        }
+        if let Some(subject) = &self.subject {
+            w.write_all(encode_header("Subject", subject).as_bytes())?;
+        }
+        let message_id = match &self.message_id {
+            Some(id) => id.clone(),
+            None => {
+                let domain = self.from.as_ref()
+                    .and_then(|f| f.spec().ok())
+                    .map(|spec| spec.domain.to_string())
+                    .unwrap_or_else(|| "localhost".to_string());
+                generate_message_id(&domain)
+            }
+        };
+        w.write_all(fold_header("Message-ID", &format!("<{}>", message_id)).as_bytes())?;
+        for (name, value) in &self.headers {
+            w.write_all(encode_header(name, value).as_bytes())?;
+        }
+        w.write_all(b"MIME-Version: 1.0\r\n")?;
+
+        let mut parts = vec![self.body_node()];
+        parts.extend(self.attachments.iter()
+            .filter(|a| a.content_id.is_none() || self.html.is_none())
+            .map(Attachment::to_node));
+        Ok(Node::multipart("mixed", parts).write_to(w)?)
+    }
+
+    /// to_bytes writes entire message to vector
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MessageBuildError> {
+        let mut res = Vec::new();
+        self.write_to(&mut res)?;
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mail::address::Address;
+    use crate::mail::date::Date;
+    use crate::mail::header::{ParsedHeaderBag, ParsedMailHeader};
+    use crate::mail::message::Message;
+    use crate::utils::quoted::MAX_HEADER_LINE_LENGTH;
+
+    use super::*;
+
+    #[test]
+    fn test_can_fold_header() {
+        for (n, v, o) in [
+            ("Subject", "Hello", "Subject: Hello\r\n"),
+            (
+                "To",
+                "aaaaaaaaaaaaaaaaaaaa@example.com, bbbbbbbbbbbbbbbbbbbb@example.com, cccccccccccccccccccc@example.com",
+                "To: aaaaaaaaaaaaaaaaaaaa@example.com, bbbbbbbbbbbbbbbbbbbb@example.com,\r\n cccccccccccccccccccc@example.com\r\n",
+            ),
+        ].iter() {
+            assert_eq!(fold_header(n, v), *o);
+        }
+    }
+
+    #[test]
+    fn test_can_encode_quoted_printable_text() {
+        for (i, o) in [
+            ("abc", "abc"),
+            ("a=b", "a=3Db"),
+            ("Ł\r\nb ", "=C5=81\r\nb=20"),
+            (&"a".repeat(77), &format!("{}=\r\naa", "a".repeat(75))),
+            (&"a".repeat(76), &"a".repeat(76)),
+        ].iter() {
+            assert_eq!(String::from_utf8(encode_quoted_printable_text(i)).unwrap(), *o);
+        }
+    }
+
+    #[test]
+    fn test_built_message_can_be_parsed() {
+        let data = MessageBuilder::new()
+            .from("Bob Źdźbło <bob@example.com>".parse().unwrap())
+            .to("alice@example.com".parse().unwrap())
+            .to("\"Doe, John\" <john@example.com>".parse().unwrap())
+            .bcc("hidden@example.com".parse().unwrap())
+            .subject("Zażółć gęślą jaźń. Zażółć gęślą jaźń. Zażółć gęślą jaźń.")
+            .date(0)
+            .message_id("some-id@example.com")
+            .text("Line one\nLine two ąę\n")
+            .html("<img src=\"cid:logo\">")
+            .attachment(Attachment::new("image/png", vec![0, 1, 2]).content_id("logo"))
+            .attachment(Attachment::new("application/octet-stream", vec![255; 100]).filename("dane.bin"))
+            .to_bytes()
+            .unwrap();
+
+        for line in data.split(|b| *b == b'\n') {
+            assert!(line.len() <= MAX_HEADER_LINE_LENGTH + 1);
+        }
+
+        let message = Message::parse(&data).unwrap();
+        let root = message.root();
+        let headers: ParsedHeaderBag = root.parsed_headers();
+        let headers = headers.container();
+        assert_eq!(headers["Date"][0], ParsedMailHeader::Date(Date::new(0, 0)));
+        assert_eq!(headers["Message-Id"][0], ParsedMailHeader::MessageID("some-id@example.com".into()));
+        assert_eq!(headers["From"][0], ParsedMailHeader::From(EmailAddress {
+            name: "Bob Źdźbło".into(),
+            address: "bob@example.com".into(),
+        }));
+        assert_eq!(headers["To"][0], ParsedMailHeader::To(vec![
+            Address::Mailbox(EmailAddress {
+                name: "".into(),
+                address: "alice@example.com".into(),
+            }),
+            Address::Mailbox(EmailAddress {
+                name: "Doe, John".into(),
+                address: "john@example.com".into(),
+            }),
+        ]));
+        assert!(!headers.contains_key("Bcc"));
+
+        assert_eq!(root.mime_subtype(), "mixed");
+        let body = &root.children()[0];
+        assert_eq!(body.mime_subtype(), "alternative");
+        let text = &body.children()[0];
+        assert_eq!(text.decode_body().unwrap(), "Line one\r\nLine two ąę\r\n".as_bytes());
+        let related = &body.children()[1];
+        assert_eq!(related.mime_subtype(), "related");
+        assert_eq!(related.children()[0].decode_body().unwrap(), b"<img src=\"cid:logo\">");
+        assert_eq!(related.children()[1].decode_body().unwrap(), vec![0, 1, 2]);
+
+        let attachment = &root.children()[1];
+        assert_eq!(attachment.decode_body().unwrap(), vec![255; 100]);
+    }
+
+    #[test]
+    fn test_built_simple_message_has_single_part() {
+        let data = MessageBuilder::new()
+            .text("Hello")
+            .to_bytes()
+            .unwrap();
+        let message = Message::parse(&data).unwrap();
+        assert!(message.root().children().is_empty());
+        assert_eq!(message.root().decode_body().unwrap(), b"Hello");
+    }
+
+    #[test]
+    fn test_rejects_header_injection() {
+        let builder = MessageBuilder::new().text("Hello");
+        for (b, o) in [
+            (builder.clone().header("X-Custom", "a\r\nBcc: x@example.com"), None),
+            (builder.clone().header("X-Custom: a\r\nBcc", "x@example.com"), Some("invalid header name: \"X-Custom: a\\r\\nBcc\"")),
+            (builder.clone().header("X Custom", "a"), Some("invalid header name: \"X Custom\"")),
+            (builder.clone().header("", "a"), Some("invalid header name: \"\"")),
+            (
+                builder.clone().attachment(Attachment::new("text/plain\r\nBcc: x@example.com", vec![])),
+                Some("value of header `Content-Type` contains line break"),
+            ),
+            (
+                builder.clone().attachment(Attachment::new("image/png", vec![]).content_id("a>\nBcc: <x@example.com")),
+                Some("value of header `Content-ID` contains line break"),
+            ),
+            (
+                builder.clone().from(EmailAddress { name: "John\r\nBcc: x@example.com".into(), address: "john@example.com".into() }),
+                Some("value of header `From` contains line break"),
+            ),
+            (
+                builder.clone().cc(EmailAddress { name: "".into(), address: "john@example.com\nBcc: x@example.com".into() }),
+                Some("value of header `Cc` contains line break"),
+            ),
+            (
+                builder.clone().message_id("x>\r\nBcc: victim@example.com"),
+                Some("value of header `Message-ID` contains line break"),
+            ),
+        ].iter() {
+            let res = b.to_bytes();
+            match o {
+                Some(o) => assert_eq!(res.unwrap_err().to_string(), *o),
+                None => {
+                    // value is encoded, so it can't create another header
+                    let data = res.unwrap();
+                    let message = Message::parse(&data).unwrap();
+                    assert!(message.headers().headers().iter().all(|h| h.name != "Bcc"));
+                }
+            }
+        }
+    }
+}
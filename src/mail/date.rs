@@ -1,4 +1,10 @@
+use std::borrow::Cow;
 use std::convert::TryInto;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{ErrorPosition, offset_in};
 
 enum DateParseState {
     Date,
@@ -29,11 +35,16 @@ fn days_in_month(month: u64, year: u64) -> u64 {
     }
 }
 
-fn seconds_to_date(year: u64, month: u64, day: u64) -> Result<u64, &'static str> {
-    assert!(year >= 1970, "Invalid year: {}", year);
-    assert!(month >= 1 && month <= 12, "Invalid month: {}", month);
-    assert!(day >= 1, "Invalid day: {}", day);
-    assert!(day <= days_in_month(month, year), "Invalid day in month: {}", day);
+fn seconds_to_date(year: u64, month: u64, day: u64) -> Result<u64, DateParseError> {
+    if year < 1970 {
+        return Err(DateParseError::InvalidYear(None));
+    }
+    if !(1..=12).contains(&month) {
+        return Err(DateParseError::InvalidMonth(None));
+    }
+    if day < 1 || day > days_in_month(month, year) {
+        return Err(DateParseError::InvalidDay(None));
+    }
 
     // we assume that we operate on unix ts.
     // Unix ts does not care about leap seconds or stuff like that.
@@ -72,18 +83,22 @@ fn seconds_to_date(year: u64, month: u64, day: u64) -> Result<u64, &'static str>
         result += u64::from(86400 * days_in_month(m + 1, year));
     }
     result += u64::from(86400 * (day - 1));
-    let r: u64 = result.try_into().map_err(|_| "Target number does not fit in u64")?;
+    let r: u64 = result.try_into().map_err(|_| DateParseError::InvalidYear(None))?;
     Ok(r)
 }
 
 // shamelessly stolen(and modified) from:
 // https://github.com/staktrace/mailparse
 /// parse_date tries to parse date in some way which makes some sense
-pub fn parse_date(date: &str) -> Result<u64, &'static str> {
+///
+/// It returns unix timestamp only and can't handle dates before 1970. Use `Date` in order to keep timezone.
+pub fn parse_date(date: &str) -> Result<u64, DateParseError> {
     let mut result = 0;
     let mut month = 0u32;
     let mut day_of_month = 0;
+    let mut day_of_month_tok = &date[..0];
     let mut state = DateParseState::Date;
+    let error_at = |tok: &str, expected| Some(ErrorPosition::new(date, offset_in(date.as_bytes(), tok.as_bytes()), expected));
     for tok in date.split(|c| c == ' ' || c == ':') {
         if tok.is_empty() {
             continue;
@@ -92,6 +107,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
             DateParseState::Date => {
                 if let Ok(v) = tok.parse::<u8>() {
                     day_of_month = v;
+                    day_of_month_tok = tok;
                     state = DateParseState::Month;
                 };
                 continue;
@@ -110,7 +126,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
                     "OCT" | "OCTOBER" => 10,
                     "NOV" | "NOVEMBER" => 11,
                     "DEC" | "DECEMBER" => 12,
-                    _ => return Err("Unrecognized month"),
+                    _ => return Err(DateParseError::InvalidMonth(error_at(tok, "month name"))),
                 };
                 state = DateParseState::Year;
                 continue;
@@ -119,13 +135,13 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
                 let year = match tok.parse::<u16>() {
                     Ok(v) if v < 70 => 2000 + v,
                     Ok(v) if v < 100 => 1900 + v,
-                    Ok(v) if v < 1970 => return Err("Disallowed year(can't be expressed as unix timestamp"),
+                    Ok(v) if v < 1970 => return Err(DateParseError::InvalidYear(error_at(tok, "year"))),
                     Ok(v) => v,
-                    Err(_) => return Err("Invalid year"),
+                    Err(_) => return Err(DateParseError::InvalidYear(error_at(tok, "year"))),
                 };
                 // eprintln!("DIM: {} DOM: {}", days_in_month(u64::from(month), u64::from(year)), day_of_month);
                 if day_of_month < 1 || u64::from(day_of_month) > days_in_month(u64::from(month), u64::from(year)) {
-                    return Err("Invalid day of month");
+                    return Err(DateParseError::InvalidDay(error_at(day_of_month_tok, "day of month")));
                 }
                 result = seconds_to_date(u64::from(year), u64::from(month), u64::from(day_of_month))?;
                 state = DateParseState::Hour;
@@ -134,7 +150,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
             DateParseState::Hour => {
                 let hour = match tok.parse::<u8>() {
                     Ok(v) => v,
-                    Err(_) => return Err("Invalid hour"),
+                    Err(_) => return Err(DateParseError::InvalidTime(error_at(tok, "hour"))),
                 };
                 result += 3600 * u64::from(hour);
                 state = DateParseState::Minute;
@@ -143,7 +159,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
             DateParseState::Minute => {
                 let minute = match tok.parse::<u8>() {
                     Ok(v) => v,
-                    Err(_) => return Err("Invalid minute"),
+                    Err(_) => return Err(DateParseError::InvalidTime(error_at(tok, "minute"))),
                 };
                 result += 60 * u64::from(minute);
                 state = DateParseState::Second;
@@ -152,7 +168,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
             DateParseState::Second => {
                 let second = match tok.parse::<u8>() {
                     Ok(v) => v,
-                    Err(_) => return Err("Invalid second"),
+                    Err(_) => return Err(DateParseError::InvalidTime(error_at(tok, "second"))),
                 };
                 result += u64::from(second);
                 state = DateParseState::Timezone;
@@ -162,7 +178,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
                 let (tz, tz_sign) = match tok.parse::<i32>() {
                     Ok(v) if v < 0 => {
                         if v == std::i32::MIN {
-                            return Err("Invalid value for timezone");
+                            return Err(DateParseError::InvalidZone(error_at(tok, "zone")));
                         }
                         (-v, -1)
                     } // it's int overflow when v == std::i32::min
@@ -180,7 +196,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
                             "M" => (1200, -1),
                             "N" => (100, 1),
                             "Y" => (1200, 1),
-                            _ => return Err("Invalid timezone"),
+                            _ => return Err(DateParseError::InvalidZone(error_at(tok, "zone"))),
                         }
                     }
                 };
@@ -191,7 +207,7 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
                     result += tz_delta as u64;
                 } else {
                     if tz_delta as u64 > result {
-                        return Err("Invalid time and tz delta");
+                        return Err(DateParseError::InvalidZone(error_at(tok, "zone")));
                     }
                     result -= tz_delta as u64;
                 }
@@ -201,22 +217,813 @@ pub fn parse_date(date: &str) -> Result<u64, &'static str> {
     }
     Ok(result)
 }
-/*
-#[derive(Debug, Copy, Clone, PartialEq)]
+const WEEK_DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// format_date formats unix timestamp as RFC 5322 date in UTC, for instance `Thu, 01 Jan 1970 00:00:00 +0000`
+pub fn format_date(unix_timestamp: u64) -> String {
+    Date::new(unix_timestamp as i64, 0).to_string()
+}
+
+/// days_from_civil returns number of days since 1970-01-01 for given date in proleptic gregorian calendar.
+/// See: http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// civil_from_days is inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// DateParseError is returned when date can't be parsed.
+/// Positions are relative to the beginning of parsed text. There is no position if date was not parsed
+/// from text, like in case of `Date::from_components`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateParseError {
+    InvalidComment(Option<ErrorPosition>),
+    InvalidWeekDay(Option<ErrorPosition>),
+    /// WeekDayMismatch is returned when day of week does not match date. It points at day of week.
+    WeekDayMismatch(Option<ErrorPosition>),
+    InvalidDay(Option<ErrorPosition>),
+    InvalidMonth(Option<ErrorPosition>),
+    InvalidYear(Option<ErrorPosition>),
+    InvalidTime(Option<ErrorPosition>),
+    InvalidZone(Option<ErrorPosition>),
+    TrailingData(Option<ErrorPosition>),
+}
+
+impl DateParseError {
+    /// position returns place in input where error was found if it's known
+    pub fn position(&self) -> Option<&ErrorPosition> {
+        match self {
+            DateParseError::InvalidComment(p) |
+            DateParseError::InvalidWeekDay(p) |
+            DateParseError::WeekDayMismatch(p) |
+            DateParseError::InvalidDay(p) |
+            DateParseError::InvalidMonth(p) |
+            DateParseError::InvalidYear(p) |
+            DateParseError::InvalidTime(p) |
+            DateParseError::InvalidZone(p) |
+            DateParseError::TrailingData(p) => p.as_ref(),
+        }
+    }
+}
+
+impl fmt::Display for DateParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            DateParseError::InvalidComment(_) => "comment is not closed or opened",
+            DateParseError::InvalidWeekDay(_) => "invalid day of week",
+            DateParseError::WeekDayMismatch(_) => "day of week does not match date",
+            DateParseError::InvalidDay(_) => "invalid day of month",
+            DateParseError::InvalidMonth(_) => "invalid month",
+            DateParseError::InvalidYear(_) => "invalid year",
+            DateParseError::InvalidTime(_) => "invalid time",
+            DateParseError::InvalidZone(_) => "invalid zone",
+            DateParseError::TrailingData(_) => "unexpected data after date",
+        };
+        match self.position() {
+            Some(p) => write!(f, "{}: {}", description, p),
+            None => write!(f, "{}", description),
+        }
+    }
+}
+
+impl error::Error for DateParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// from_name parses three letter abbreviation of week day name, case insensitive
+    pub fn from_name(name: &str) -> Option<Self> {
+        WEEK_DAYS.iter()
+            .position(|d| d.eq_ignore_ascii_case(name))
+            .map(|i| Self::ALL[i])
+    }
+
+    /// name returns three letter abbreviation used in RFC 5322 dates
+    pub fn name(self) -> &'static str {
+        WEEK_DAYS[self as usize]
+    }
+}
+
+/// DateHeuristic describes deviation from RFC 5322 which was accepted by `Date::parse_lenient`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum DateHeuristic {
+    /// Iso8601 means date like `2008-06-03T11:05:30Z`
+    Iso8601,
+    /// Asctime means that month was before day, like in `Mon Jun  3 11:05:30 2008` produced by C `asctime`
+    Asctime,
+    /// DottedOrDashedDate means date like `03.06.2008` or `3-Jun-2008`
+    DottedOrDashedDate,
+    /// FullMonthName means that month name was not abbreviated, like `June`
+    FullMonthName,
+    /// GmtOffsetZone means zone like `GMT+0200` or `UTC-3`, which is treated as offset from UTC
+    GmtOffsetZone,
+    /// NonStandardZone means zone like `+02:00`, `+2` or unregistered name like `CEST`
+    NonStandardZone,
+    /// UnknownZone means that zone was not recognized so UTC was assumed
+    UnknownZone,
+    /// MissingZone means that there was no zone so UTC was assumed
+    MissingZone,
+    /// MissingTime means that there was no time so midnight was assumed
+    MissingTime,
+    /// WeekDayMismatch means that day of week did not match date and it was ignored
+    WeekDayMismatch,
+    /// InvalidComment means that some comment was not closed or opened
+    InvalidComment,
+    /// IgnoredData means that some word was not recognized and it was ignored
+    IgnoredData,
+}
+
+/// Date is point in time along with timezone shift it was written in. See RFC 5322 3.3
+///
+/// Dates are ordered by point in time they represent.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::date::{Date, Weekday};
+///let date: Date = "Tue, 1 Jul 2003 10:52:37 +0200".parse().unwrap();
+///assert_eq!(date.unix_timestamp(), 1057049557);
+///assert_eq!(date.timezone_shift(), 2 * 3600);
+///assert_eq!(date.weekday(), Weekday::Tuesday);
+///assert_eq!(date.hour(), 10);
+///assert_eq!(date.to_string(), "Tue, 01 Jul 2003 10:52:37 +0200");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Date {
-    unix_timestamp: u64,
+    unix_timestamp: i64,
+    /// timezone_shift is offset of local time from UTC in seconds
     timezone_shift: i32,
 }
 
+impl Date {
+    pub fn new(unix_timestamp: i64, timezone_shift: i32) -> Self {
+        Self {
+            unix_timestamp,
+            timezone_shift,
+        }
+    }
+
+    /// from_components creates date from local date and time.
+    /// Returned errors have no position.
+    pub fn from_components(
+        year: i64, month: u32, day: u32,
+        hour: u32, minute: u32, second: u32,
+        timezone_shift: i32,
+    ) -> Result<Self, DateParseError> {
+        if !(0..=9999).contains(&year) {
+            return Err(DateParseError::InvalidYear(None));
+        }
+        if !(1..=12).contains(&month) {
+            return Err(DateParseError::InvalidMonth(None));
+        }
+        if day < 1 || u64::from(day) > days_in_month(u64::from(month), year as u64) {
+            return Err(DateParseError::InvalidDay(None));
+        }
+        // 60th second is leap second
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(DateParseError::InvalidTime(None));
+        }
+        let local = days_from_civil(year, month, day) * 86400 +
+            i64::from(hour * 3600 + minute * 60 + second);
+        Ok(Self::new(local - i64::from(timezone_shift), timezone_shift))
+    }
+
+    /// parse parses RFC 5322 date including obsolete syntax: two or three digit years, zone names and comments
+    pub fn parse(text: &str) -> Result<Self, DateParseError> {
+        let error_at = |offset, expected| Some(ErrorPosition::new(text, offset, expected));
+        let (stripped, invalid_comment) = strip_comments(text);
+        if let Some(offset) = invalid_comment {
+            return Err(DateParseError::InvalidComment(error_at(offset, "matching parenthesis")));
+        }
+        let mut p = DateCursor::new(&stripped);
+
+        p.skip_white_space();
+        let weekday_offset = p.position();
+        let weekday = if p.peek().map(|c| c.is_ascii_alphabetic()).unwrap_or(false) {
+            let weekday = Weekday::from_name(p.take_while(|c| c.is_ascii_alphabetic()))
+                .ok_or_else(|| DateParseError::InvalidWeekDay(error_at(weekday_offset, "day of week")))?;
+            p.skip_white_space();
+            if !p.eat(',') {
+                return Err(DateParseError::InvalidWeekDay(error_at(p.position(), "comma")));
+            }
+            Some(weekday)
+        } else {
+            None
+        };
+
+        p.skip_white_space();
+        let day_offset = p.position();
+        let day = p.take_number(1, 2)
+            .ok_or_else(|| DateParseError::InvalidDay(error_at(day_offset, "day of month")))?;
+        p.skip_white_space();
+        let month_offset = p.position();
+        let month = p.take_while(|c| c.is_ascii_alphabetic());
+        let month = MONTHS.iter()
+            .position(|m| m.eq_ignore_ascii_case(month))
+            .ok_or_else(|| DateParseError::InvalidMonth(error_at(month_offset, "month name")))? as u32 + 1;
+        p.skip_white_space();
+        let year_offset = p.position();
+        let year_len = p.peek_digits();
+        let year = p.take_number(2, 4)
+            .ok_or_else(|| DateParseError::InvalidYear(error_at(year_offset, "year")))?;
+        let year = match year_len {
+            // See RFC 5322 4.3
+            2 if year < 50 => 2000 + year,
+            2 | 3 => 1900 + year,
+            _ => year,
+        };
+        if !p.skip_white_space() {
+            return Err(DateParseError::InvalidYear(error_at(p.position(), "white space")));
+        }
+
+        let time_offset = p.position();
+        let hour = p.take_number(2, 2)
+            .ok_or_else(|| DateParseError::InvalidTime(error_at(time_offset, "hour")))?;
+        p.skip_white_space();
+        if !p.eat(':') {
+            return Err(DateParseError::InvalidTime(error_at(p.position(), "colon")));
+        }
+        p.skip_white_space();
+        let minute = p.take_number(2, 2)
+            .ok_or_else(|| DateParseError::InvalidTime(error_at(p.position(), "minute")))?;
+        p.skip_white_space();
+        let second = if p.eat(':') {
+            p.skip_white_space();
+            p.take_number(2, 2)
+                .ok_or_else(|| DateParseError::InvalidTime(error_at(p.position(), "second")))?
+        } else {
+            0
+        };
+
+        p.skip_white_space();
+        let zone_offset = p.position();
+        let timezone_shift = p.take_zone()
+            .ok_or_else(|| DateParseError::InvalidZone(error_at(zone_offset, "zone")))?;
+        p.skip_white_space();
+        if !p.is_empty() {
+            return Err(DateParseError::TrailingData(error_at(p.position(), "end of date")));
+        }
+
+        let date = Self::from_components(
+            i64::from(year), month, day,
+            hour, minute, second,
+            timezone_shift,
+        ).map_err(|e| match e {
+            DateParseError::InvalidYear(_) => DateParseError::InvalidYear(error_at(year_offset, "valid year")),
+            DateParseError::InvalidDay(_) => DateParseError::InvalidDay(error_at(day_offset, "valid day of month")),
+            DateParseError::InvalidTime(_) => DateParseError::InvalidTime(error_at(time_offset, "valid time")),
+            e => e,
+        })?;
+        if weekday.map(|w| w != date.weekday()).unwrap_or(false) {
+            return Err(DateParseError::WeekDayMismatch(error_at(weekday_offset, "day of week matching date")));
+        }
+        Ok(date)
+    }
+
+    /// parse_lenient parses date the same way `parse` does, but if it fails it tries to recover from
+    /// defects found in real world messages, like ISO 8601 or asctime format, `GMT+0200` zones or missing zones.
+    ///
+    /// Applied heuristics are returned along with date. If date is valid RFC 5322 date there are none.
+    ///
+    /// # Example
+    /// ```rust
+    ///# use smtpc::mail::date::{Date, DateHeuristic};
+    ///let (date, heuristics) = Date::parse_lenient("Tue, 3 Jun 2008 11:05:30 GMT+0200").unwrap();
+    ///assert_eq!(date.to_string(), "Tue, 03 Jun 2008 11:05:30 +0200");
+    ///assert_eq!(heuristics, vec![DateHeuristic::GmtOffsetZone]);
+    ///
+    ///let (date, heuristics) = Date::parse_lenient("Mon Jun  3 11:05:30 2008").unwrap();
+    ///assert_eq!(date.to_string(), "Tue, 03 Jun 2008 11:05:30 +0000");
+    ///assert_eq!(heuristics, vec![DateHeuristic::Asctime, DateHeuristic::MissingZone, DateHeuristic::WeekDayMismatch]);
+    /// ```
+    pub fn parse_lenient(text: &str) -> Result<(Self, Vec<DateHeuristic>), DateParseError> {
+        let err = match Self::parse(text) {
+            Ok(date) => return Ok((date, Vec::new())),
+            Err(err) => err,
+        };
+        if let Some(date) = parse_iso_8601(text.trim()) {
+            return Ok((date, vec![DateHeuristic::Iso8601]));
+        }
+        // error of strict parser is usually more meaningful
+        parse_relaxed(text).map_err(|_| err)
+    }
+
+    pub fn unix_timestamp(&self) -> i64 {
+        self.unix_timestamp
+    }
+
+    pub fn timezone_shift(&self) -> i32 {
+        self.timezone_shift
+    }
+
+    /// with_timezone_shift returns same point in time in other timezone
+    pub fn with_timezone_shift(&self, timezone_shift: i32) -> Self {
+        Self::new(self.unix_timestamp, timezone_shift)
+    }
+
+    fn local_timestamp(&self) -> i64 {
+        self.unix_timestamp + i64::from(self.timezone_shift)
+    }
+
+    fn local_date(&self) -> (i64, u32, u32) {
+        civil_from_days(self.local_timestamp().div_euclid(86400))
+    }
+
+    fn local_seconds(&self) -> u32 {
+        self.local_timestamp().rem_euclid(86400) as u32
+    }
+
+    pub fn year(&self) -> i64 {
+        self.local_date().0
+    }
+
+    pub fn month(&self) -> u32 {
+        self.local_date().1
+    }
+
+    pub fn day(&self) -> u32 {
+        self.local_date().2
+    }
+
+    pub fn hour(&self) -> u32 {
+        self.local_seconds() / 3600
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.local_seconds() / 60 % 60
+    }
+
+    pub fn second(&self) -> u32 {
+        self.local_seconds() % 60
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        // 1970-01-01 was thursday
+        let days = self.local_timestamp().div_euclid(86400);
+        Weekday::ALL[(days + 3).rem_euclid(7) as usize]
+    }
+
+    /// to_rfc_5322 formats date as it should be written in `Date` header
+    pub fn to_rfc_5322(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl FromStr for Date {
-    type Err = &'static str;
+    type Err = DateParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
 
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.local_date();
+        let sign = if self.timezone_shift < 0 { '-' } else { '+' };
+        let shift_minutes = self.timezone_shift.abs() / 60;
+        write!(
+            f,
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} {}{:02}{:02}",
+            self.weekday().name(),
+            day,
+            MONTHS[(month - 1) as usize],
+            year,
+            self.hour(),
+            self.minute(),
+            self.second(),
+            sign,
+            shift_minutes / 60,
+            shift_minutes % 60,
+        )
     }
 }
-*/
 
+/// strip_comments replaces comments, which may be nested, with spaces, so offsets in returned text are the same
+/// as in given one. Unmatched parentheses are replaced as well, in which case offset of the first one is returned.
+fn strip_comments(text: &str) -> (Cow<'_, str>, Option<usize>) {
+    if !text.contains(['(', ')']) {
+        return (Cow::Borrowed(text), None);
+    }
+    let mut res = String::with_capacity(text.len());
+    let mut depth = 0;
+    let mut comment_start = 0;
+    let mut is_escaped = false;
+    let mut invalid_at = None;
+    for (i, c) in text.char_indices() {
+        if depth == 0 {
+            match c {
+                '(' => {
+                    depth += 1;
+                    comment_start = i;
+                }
+                ')' => {
+                    invalid_at.get_or_insert(i);
+                }
+                c => {
+                    res.push(c);
+                    continue;
+                }
+            }
+        } else if is_escaped {
+            is_escaped = false;
+        } else {
+            match c {
+                '\\' => is_escaped = true,
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+        for _ in 0..c.len_utf8() {
+            res.push(' ');
+        }
+    }
+    if depth > 0 {
+        invalid_at.get_or_insert(comment_start);
+    }
+    (Cow::Owned(res), invalid_at)
+}
+
+/// parse_zone parses numeric or obsolete zone. See RFC 5322 4.3
+fn parse_zone(zone: &str) -> Option<i32> {
+    let bytes = zone.as_bytes();
+    if bytes.len() == 5 && (bytes[0] == b'+' || bytes[0] == b'-') && bytes[1..].iter().all(|b| b.is_ascii_digit()) {
+        let value: i32 = zone[1..].parse().ok()?;
+        if value % 100 > 59 {
+            return None;
+        }
+        let shift = (value / 100) * 3600 + (value % 100) * 60;
+        return Some(if bytes[0] == b'-' { -shift } else { shift });
+    }
+    let hours = match zone.to_ascii_uppercase().as_str() {
+        "UT" | "GMT" => 0,
+        "EDT" => -4,
+        "EST" | "CDT" => -5,
+        "CST" | "MDT" => -6,
+        "MST" | "PDT" => -7,
+        "PST" => -8,
+        // military zones had wrong signs in RFC 822 so they are treated as unknown zone. See RFC 5322 4.3
+        z if z.len() == 1 && z != "J" && z.as_bytes()[0].is_ascii_alphabetic() => 0,
+        _ => return None,
+    };
+    Some(hours * 3600)
+}
+
+const FULL_WEEK_DAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+const FULL_MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+/// parse_month parses month name, which may be abbreviated, and returns it's number along with
+/// information if it was not RFC 5322 abbreviation
+fn parse_month(name: &str) -> Option<(u32, bool)> {
+    if let Some(i) = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(name)) {
+        return Some((i as u32 + 1, false));
+    }
+    if name.eq_ignore_ascii_case("Sept") {
+        return Some((9, true));
+    }
+    FULL_MONTHS.iter()
+        .position(|m| m.eq_ignore_ascii_case(name))
+        .map(|i| (i as u32 + 1, true))
+}
+
+fn parse_weekday(name: &str) -> Option<Weekday> {
+    Weekday::from_name(name).or_else(|| {
+        FULL_WEEK_DAYS.iter()
+            .position(|d| d.eq_ignore_ascii_case(name))
+            .map(|i| Weekday::ALL[i])
+    })
+}
+
+/// parse_offset parses zone like `+0200`, `+02:00`, `+02` or `+2` and returns it along with information if
+/// it was in other form than `+0200`
+fn parse_offset(text: &str) -> Option<(i32, bool)> {
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let mut p = DateCursor::new(&text[1..]);
+    let (hours, minutes, is_standard) = match p.peek_digits() {
+        4 => {
+            let value = p.take_number(4, 4)?;
+            (value / 100, value % 100, true)
+        }
+        1 | 2 => {
+            let hours = p.take_number(1, 2)?;
+            let minutes = if p.eat(':') { p.take_number(2, 2)? } else { 0 };
+            (hours, minutes, false)
+        }
+        _ => return None,
+    };
+    if !p.is_empty() || minutes > 59 {
+        return None;
+    }
+    Some((sign * (hours * 3600 + minutes * 60) as i32, is_standard))
+}
+
+/// parse_time parses time like `11:05`, `11:05:30` or `11:05:30.123`. Fraction of second is ignored
+fn parse_time(text: &str) -> Option<(u32, u32, u32)> {
+    let mut p = DateCursor::new(text);
+    let hour = p.take_number(1, 2)?;
+    if !p.eat(':') {
+        return None;
+    }
+    let minute = p.take_number(2, 2)?;
+    let second = if p.eat(':') { p.take_number(2, 2)? } else { 0 };
+    if p.eat('.') || p.eat(',') {
+        p.take_number(1, 9)?;
+    }
+    if !p.is_empty() {
+        return None;
+    }
+    Some((hour, minute, second))
+}
+
+/// parse_extra_zone parses zone names not defined in RFC 5322, which are commonly used
+fn parse_extra_zone(name: &str) -> Option<i32> {
+    let minutes = match name.to_ascii_uppercase().as_str() {
+        "WET" => 0,
+        "CET" | "MET" | "BST" | "WEST" => 60,
+        "CEST" | "MEST" | "EET" | "SAST" => 120,
+        "EEST" | "MSK" => 180,
+        "IST" => 330,
+        "HKT" | "AWST" => 480,
+        "JST" | "KST" => 540,
+        "ACST" => 570,
+        "AEST" => 600,
+        "AEDT" => 660,
+        "NZST" => 720,
+        "NZDT" => 780,
+        "HST" => -600,
+        "AKST" => -540,
+        "AKDT" => -480,
+        _ => return None,
+    };
+    Some(minutes * 60)
+}
+
+/// parse_iso_8601 parses date like `2008-06-03T11:05:30.123+02:00`. Missing zone means UTC
+fn parse_iso_8601(text: &str) -> Option<Date> {
+    let mut p = DateCursor::new(text);
+    let year = p.take_number(4, 4)?;
+    if !p.eat('-') {
+        return None;
+    }
+    let month = p.take_number(2, 2)?;
+    if !p.eat('-') {
+        return None;
+    }
+    let day = p.take_number(2, 2)?;
+    let (time, zone) = if p.is_empty() {
+        ("00:00", "")
+    } else {
+        if !(p.eat('T') || p.eat('t') || p.eat(' ')) {
+            return None;
+        }
+        let time = p.take_while(|c| c.is_ascii_digit() || c == ':' || c == '.' || c == ',');
+        (time, p.take_while(|_| true))
+    };
+    let (hour, minute, second) = parse_time(time)?;
+    let timezone_shift = match zone.trim_start() {
+        "" | "Z" | "z" => 0,
+        zone => parse_offset(zone)?.0,
+    };
+    Date::from_components(i64::from(year), month, day, hour, minute, second, timezone_shift).ok()
+}
+
+/// parse_relaxed recognizes parts of date one by one, ignoring their order where possible
+fn parse_relaxed(text: &str) -> Result<(Date, Vec<DateHeuristic>), DateParseError> {
+    let mut heuristics = Vec::new();
+    let error_at = |offset, expected| Some(ErrorPosition::new(text, offset, expected));
+    let (stripped, invalid_comment) = strip_comments(text);
+    if invalid_comment.is_some() {
+        heuristics.push(DateHeuristic::InvalidComment);
+    }
+
+    let mut weekday = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    let mut time = None;
+    let mut timezone_shift = None;
+    let mut is_month_first = false;
+    let mut push_heuristic = |heuristic| {
+        if !heuristics.contains(&heuristic) {
+            heuristics.push(heuristic);
+        }
+    };
+
+    let tokens = stripped.split(|c: char| c.is_whitespace() || c == ',')
+        .map(|t| t.trim_end_matches('.'))
+        .filter(|t| !t.is_empty());
+    for token in tokens {
+        let offset = offset_in(stripped.as_bytes(), token.as_bytes());
+        let first = token.chars().next().unwrap_or_default();
+        if first.is_ascii_digit() && token.contains(':') && time.is_none() {
+            time = Some(parse_time(token).ok_or_else(|| DateParseError::InvalidTime(error_at(offset, "time")))?);
+        } else if first.is_ascii_digit() && token.contains(['.', '-', '/']) {
+            let parts: Vec<_> = token.split(['.', '-', '/']).collect();
+            if parts.len() != 3 || day.is_some() {
+                return Err(DateParseError::InvalidDay(error_at(offset, "date")));
+            }
+            let (d, m, y) = if parts[0].len() == 4 {
+                (parts[2], parts[1], parts[0])
+            } else {
+                (parts[0], parts[1], parts[2])
+            };
+            day = Some(d.parse().map_err(|_| DateParseError::InvalidDay(error_at(offset, "day of month")))?);
+            month = Some(match m.parse() {
+                Ok(m) => m,
+                Err(_) => parse_month(m).ok_or_else(|| DateParseError::InvalidMonth(error_at(offset, "month")))?.0,
+            });
+            year = Some((y.parse().map_err(|_| DateParseError::InvalidYear(error_at(offset, "year")))?, y.len()));
+            push_heuristic(DateHeuristic::DottedOrDashedDate);
+        } else if first.is_ascii_digit() {
+            let value = token.parse().map_err(|_| DateParseError::InvalidDay(error_at(offset, "number")))?;
+            if token.len() <= 2 && day.is_none() {
+                day = Some(value);
+            } else if year.is_none() {
+                year = Some((value, token.len()));
+            } else {
+                push_heuristic(DateHeuristic::IgnoredData);
+            }
+        } else if first == '+' || first == '-' {
+            let (shift, is_standard) = parse_offset(token).ok_or_else(|| DateParseError::InvalidZone(error_at(offset, "zone")))?;
+            if !is_standard {
+                push_heuristic(DateHeuristic::NonStandardZone);
+            }
+            timezone_shift = Some(shift);
+        } else if let Some(w) = parse_weekday(token).filter(|_| weekday.is_none()) {
+            weekday = Some(w);
+        } else if let Some((m, is_full)) = parse_month(token).filter(|_| month.is_none()) {
+            if is_full {
+                push_heuristic(DateHeuristic::FullMonthName);
+            }
+            is_month_first = day.is_none();
+            month = Some(m);
+        } else if let Some(shift) = parse_zone(token) {
+            timezone_shift = Some(shift);
+        } else if let Some(shift) = parse_extra_zone(token) {
+            push_heuristic(DateHeuristic::NonStandardZone);
+            timezone_shift = Some(shift);
+        } else if let Some(idx) = token.find(['+', '-']) {
+            let name = &token[..idx];
+            if !["GMT", "UTC", "UT"].iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                return Err(DateParseError::InvalidZone(error_at(offset, "zone")));
+            }
+            let (shift, _) = parse_offset(&token[idx..])
+                .ok_or_else(|| DateParseError::InvalidZone(error_at(offset + idx, "zone offset")))?;
+            push_heuristic(DateHeuristic::GmtOffsetZone);
+            timezone_shift = Some(shift);
+        } else if time.is_some() && timezone_shift.is_none() {
+            push_heuristic(DateHeuristic::UnknownZone);
+            timezone_shift = Some(0);
+        } else {
+            push_heuristic(DateHeuristic::IgnoredData);
+        }
+    }
+
+    let day = day.ok_or_else(|| DateParseError::InvalidDay(error_at(text.len(), "day of month")))?;
+    let month = month.ok_or_else(|| DateParseError::InvalidMonth(error_at(text.len(), "month")))?;
+    let year = match year.ok_or_else(|| DateParseError::InvalidYear(error_at(text.len(), "year")))? {
+        (year, 2) if year < 50 => 2000 + year,
+        (year, 2) | (year, 3) => 1900 + year,
+        (year, _) => year,
+    };
+    if is_month_first {
+        push_heuristic(DateHeuristic::Asctime);
+    }
+    let (hour, minute, second) = time.unwrap_or_else(|| {
+        push_heuristic(DateHeuristic::MissingTime);
+        (0, 0, 0)
+    });
+    let timezone_shift = timezone_shift.unwrap_or_else(|| {
+        push_heuristic(DateHeuristic::MissingZone);
+        0
+    });
+    let date = Date::from_components(
+        i64::from(year), month, day,
+        hour, minute, second,
+        timezone_shift,
+    )?;
+    if weekday.map(|w| w != date.weekday()).unwrap_or(false) {
+        push_heuristic(DateHeuristic::WeekDayMismatch);
+    }
+    Ok((date, heuristics))
+}
+
+struct DateCursor<'a> {
+    source: &'a str,
+    text: &'a str,
+}
+
+impl<'a> DateCursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            source: text,
+            text,
+        }
+    }
+
+    /// position returns byte offset of cursor in source text
+    fn position(&self) -> usize {
+        self.source.len() - self.text.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text.chars().next()
+    }
+
+    fn peek_digits(&self) -> usize {
+        self.text.bytes().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    /// skip_white_space skips white chars and returns true if any was skipped
+    fn skip_white_space(&mut self) -> bool {
+        let len = self.text.len();
+        self.text = self.text.trim_start_matches([' ', '\t', '\r', '\n']);
+        self.text.len() != len
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.text.starts_with(c) {
+            self.text = &self.text[c.len_utf8()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let idx = self.text.find(|c| !f(c)).unwrap_or(self.text.len());
+        let (res, rest) = self.text.split_at(idx);
+        self.text = rest;
+        res
+    }
+
+    fn take_number(&mut self, min_len: usize, max_len: usize) -> Option<u32> {
+        let len = self.peek_digits();
+        if len < min_len || len > max_len {
+            return None;
+        }
+        let (res, rest) = self.text.split_at(len);
+        self.text = rest;
+        res.parse().ok()
+    }
+
+    fn take_zone(&mut self) -> Option<i32> {
+        let zone = self.take_while(|c| c == '+' || c == '-' || c.is_ascii_alphanumeric());
+        parse_zone(zone)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -259,4 +1066,141 @@ mod tests {
             1354309043
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn format_dates() {
+        for (i, o) in [
+            (0, "Thu, 01 Jan 1970 00:00:00 +0000"),
+            (1474842993, "Sun, 25 Sep 2016 22:36:33 +0000"),
+            (13601001600, "Sun, 31 Dec 2400 00:00:00 +0000"),
+            (951782400, "Tue, 29 Feb 2000 00:00:00 +0000"),
+        ].iter() {
+            assert_eq!(format_date(*i), *o);
+            assert_eq!(parse_date(o).unwrap(), *i);
+        }
+    }
+
+    #[test]
+    fn parse_structured_dates() {
+        for (i, o) in [
+            ("Sun, 25 Sep 2016 18:36:33 -0400", Ok((1474842993, -4 * 3600))),
+            ("25 Sep 2016 18:36:33 -0400", Ok((1474842993, -4 * 3600))),
+            ("  Sun ,25 Sep 2016 18 : 36 -0400 ", Ok((1474842960, -4 * 3600))),
+            ("Sun, 25 Sep 2016 18:36:33 -0400 (EDT)", Ok((1474842993, -4 * 3600))),
+            ("Sun, 25 (a (nested) comment) Sep 2016 18:36:33 EDT", Ok((1474842993, -4 * 3600))),
+            ("Fri, 21 Nov 1997 09:55:06 -0600", Ok((880127706, -6 * 3600))),
+            ("Fri, 21 Nov 97 09:55:06 GMT", Ok((880106106, 0))),
+            ("Mon, 1 Jan 01 00:00:00 +0000", Ok((978307200, 0))),
+            ("Thu, 13 Feb 1969 23:32:54 -0330", Ok((-27723426, -3 * 3600 - 1800))),
+            ("Wed, 31 Dec 1969 23:59:59 Z", Ok((-1, 0))),
+            ("Mon, 1 Jan 0001 00:00:00 +0000", Ok((-62135596800, 0))),
+            ("Sun, 31 Dec 2400 00:00:00 +0000", Ok((13601001600, 0))),
+            ("Tue, 29 Feb 2000 00:00:00 +0000", Ok((951782400, 0))),
+        ].iter() {
+            let res = Date::parse(i).map(|d| (d.unix_timestamp(), d.timezone_shift()));
+            assert_eq!(res, *o, "input {:?}", i);
+        }
+    }
+
+    #[test]
+    fn structured_date_errors_point_at_invalid_part() {
+        type E = fn(Option<ErrorPosition>) -> DateParseError;
+        for (i, err, offset, expected) in [
+            ("Fri, 31 Dec 2400 00:00:00 +0000", DateParseError::WeekDayMismatch as E, 0, "day of week matching date"),
+            ("Foo, 31 Dec 2400 00:00:00 +0000", DateParseError::InvalidWeekDay, 0, "day of week"),
+            ("Sun 25 Sep 2016 18:36:33 -0400", DateParseError::InvalidWeekDay, 4, "comma"),
+            ("Thu, 29 Feb 2001 00:00:00 +0000", DateParseError::InvalidDay, 5, "valid day of month"),
+            ("31 Foo 2400 00:00:00 +0000", DateParseError::InvalidMonth, 3, "month name"),
+            ("31 Dec 240000 23:00:00 +0000", DateParseError::InvalidYear, 7, "year"),
+            ("31 Dec 2400 24:00:00 +0000", DateParseError::InvalidTime, 12, "valid time"),
+            ("31 Dec 2400 23-00 +0000", DateParseError::InvalidTime, 14, "colon"),
+            ("31 Dec 2400 23:00:00 +0060", DateParseError::InvalidZone, 21, "zone"),
+            ("31 Dec 2400 23:00:00 XYZ", DateParseError::InvalidZone, 21, "zone"),
+            ("31 Dec 2400 23:00:00 +0000 x", DateParseError::TrailingData, 27, "end of date"),
+            ("31 Dec 2400 23:00:00 +0000 (x", DateParseError::InvalidComment, 27, "matching parenthesis"),
+            ("31 Dec) 2400 23:00:00 +0000", DateParseError::InvalidComment, 6, "matching parenthesis"),
+        ].iter() {
+            let res = Date::parse(i);
+            assert_eq!(res, Err(err(Some(ErrorPosition::new(i, *offset, expected)))), "input {:?}", i);
+        }
+    }
+
+    #[test]
+    fn invalid_components_are_rejected() {
+        for (year, month, day, o) in [
+            (1969, 1, 1, DateParseError::InvalidYear(None)),
+            (1970, 0, 1, DateParseError::InvalidMonth(None)),
+            (1970, 13, 1, DateParseError::InvalidMonth(None)),
+            (1970, 1, 0, DateParseError::InvalidDay(None)),
+            (2001, 2, 29, DateParseError::InvalidDay(None)),
+        ].iter() {
+            assert_eq!(seconds_to_date(*year, *month, *day), Err(o.clone()));
+            let date = Date::from_components(*year as i64, *month as u32, *day as u32, 0, 0, 0, 0);
+            if *year >= 1970 {
+                assert_eq!(date, Err(o.clone()));
+            }
+        }
+        assert_eq!(Date::from_components(2001, 1, 1, 24, 0, 0, 0), Err(DateParseError::InvalidTime(None)));
+
+        let err = parse_date("Fri, 30 Foo 2012 20:57:23 GMT").unwrap_err();
+        assert_eq!(err, DateParseError::InvalidMonth(Some(ErrorPosition::new("Fri, 30 Foo 2012 20:57:23 GMT", 8, "month name"))));
+        assert_eq!(err.to_string(), "invalid month: expected month name at byte 8, found \"Foo 2012 20:57:23 GMT\"");
+    }
+
+    #[test]
+    fn format_structured_dates() {
+        for (i, o) in [
+            (Date::new(1474842993, -4 * 3600), "Sun, 25 Sep 2016 18:36:33 -0400"),
+            (Date::new(1057049557, 2 * 3600), "Tue, 01 Jul 2003 10:52:37 +0200"),
+            (Date::new(-27723426, -3 * 3600 - 1800), "Thu, 13 Feb 1969 23:32:54 -0330"),
+            (Date::new(-62135596800, 0), "Mon, 01 Jan 0001 00:00:00 +0000"),
+        ].iter() {
+            assert_eq!(i.to_rfc_5322(), *o);
+            assert_eq!(Date::parse(o).unwrap(), *i);
+        }
+    }
+
+    #[test]
+    fn dates_are_ordered_by_point_in_time() {
+        let mut dates: Vec<Date> = [
+            "Sun, 25 Sep 2016 18:36:33 -0400",
+            "Sun, 25 Sep 2016 20:36:32 +0000",
+            "Sun, 25 Sep 2016 23:00:00 +0100",
+        ].iter().map(|d| d.parse().unwrap()).collect();
+        dates.sort();
+        assert_eq!(dates[0].hour(), 20);
+        assert_eq!(dates[1].hour(), 23);
+        assert_eq!(dates[2].hour(), 18);
+    }
+
+    #[test]
+    fn parse_lenient_dates() {
+        use DateHeuristic::*;
+        for (i, o) in vec![
+            ("Tue, 3 Jun 2008 11:05:30 +0200", Some(("Tue, 03 Jun 2008 11:05:30 +0200", vec![]))),
+            ("Tue, 3 Jun 2008 11:05 +0200 (CEST)", Some(("Tue, 03 Jun 2008 11:05:00 +0200", vec![]))),
+            ("Tue, 3 Jun 2008 11:05:30 GMT+0200", Some(("Tue, 03 Jun 2008 11:05:30 +0200", vec![GmtOffsetZone]))),
+            ("Tue, 3 Jun 2008 11:05:30 UTC-3", Some(("Tue, 03 Jun 2008 11:05:30 -0300", vec![GmtOffsetZone]))),
+            ("2008-06-03T11:05:30Z", Some(("Tue, 03 Jun 2008 11:05:30 +0000", vec![Iso8601]))),
+            ("2008-06-03 11:05:30.123+02:00", Some(("Tue, 03 Jun 2008 11:05:30 +0200", vec![Iso8601]))),
+            ("2008-06-03", Some(("Tue, 03 Jun 2008 00:00:00 +0000", vec![Iso8601]))),
+            ("Tue Jun  3 11:05:30 2008", Some(("Tue, 03 Jun 2008 11:05:30 +0000", vec![Asctime, MissingZone]))),
+            ("Tue Jun  3 11:05:30 PDT 2008", Some(("Tue, 03 Jun 2008 11:05:30 -0700", vec![Asctime]))),
+            ("Tue, 3 Jun 2008 11:05:30 +0200 (CEST", Some(("Tue, 03 Jun 2008 11:05:30 +0200", vec![InvalidComment]))),
+            ("Tuesday, 3 June 2008 11:05:30 CEST", Some(("Tue, 03 Jun 2008 11:05:30 +0200", vec![FullMonthName, NonStandardZone]))),
+            ("03.06.2008 11:05:30 +02:00", Some(("Tue, 03 Jun 2008 11:05:30 +0200", vec![DottedOrDashedDate, NonStandardZone]))),
+            ("3-Jun-2008 11:05:30 -0000", Some(("Tue, 03 Jun 2008 11:05:30 +0000", vec![DottedOrDashedDate]))),
+            ("Mon, 3 Jun 2008 11:05:30 +0200", Some(("Tue, 03 Jun 2008 11:05:30 +0200", vec![WeekDayMismatch]))),
+            ("Tue, 3 Jun 2008", Some(("Tue, 03 Jun 2008 00:00:00 +0000", vec![MissingTime, MissingZone]))),
+            ("Tue, 3 Jun 2008 11:05:30 XYZ", Some(("Tue, 03 Jun 2008 11:05:30 +0000", vec![UnknownZone]))),
+            ("Tue, 3 Jun 2008 at 11:05:30 +0000", Some(("Tue, 03 Jun 2008 11:05:30 +0000", vec![IgnoredData]))),
+            ("Tue, 3 Jun", None),
+            ("Tue, 31 Feb 2008 11:05:30 +0000", None),
+            ("Tue, 3 Jun 2008 11:05:30 FOO+0200", None),
+            ("", None),
+        ].into_iter() {
+            let res = Date::parse_lenient(i).ok().map(|(d, h)| (d.to_string(), h));
+            assert_eq!(res, o.map(|(d, h)| (d.to_string(), h)), "input {:?}", i);
+        }
+    }
+}
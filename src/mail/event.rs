@@ -0,0 +1,382 @@
+use std::io::{self, Read};
+
+use crate::encoding::multipart::{MultipartReader, read_part_headers};
+use crate::mail::header::{ContentTransferEncodingDecoder, find_param, RawHeaderBag, RawHeaderParseOptions};
+use crate::mail::message::{
+    MAX_NESTING_DEPTH,
+    MessageParseError,
+    parse_content_type,
+    parse_transfer_encoding,
+};
+
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// MimeEvent is single event emitted by `MimeEventReader`.
+///
+/// Each `PartStart` has matching `PartEnd`. Between them there is either:
+/// - any number of `BodyChunk`s for leaf parts
+/// - `MultipartStart`, child parts and `MultipartEnd` for `multipart/*` parts
+/// - single child part for `message/rfc822` parts
+#[derive(Debug, PartialEq, Eq)]
+pub enum MimeEvent<'b> {
+    PartStart {
+        headers: RawHeaderBag<'static>,
+    },
+    /// BodyChunk contains piece of body already decoded using part's `Content-Transfer-Encoding`
+    BodyChunk(&'b [u8]),
+    PartEnd,
+
+    MultipartStart,
+    MultipartEnd,
+}
+
+/// Source is stack of readers. Each multipart level adds one `MultipartReader` on top of it.
+enum Source<R> {
+    Root(R),
+    Multipart(Box<MultipartReader<Source<R>>>),
+}
+
+impl<R> Read for Source<R> where R: Read {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        match self {
+            Source::Root(r) => r.read(buf),
+            Source::Multipart(r) => r.read(buf),
+        }
+    }
+}
+
+enum Frame {
+    Multipart {
+        is_digest: bool,
+    },
+    Message,
+}
+
+enum State<R> {
+    /// Headers of part are about to be read
+    Headers {
+        source: Source<R>,
+        is_digest: bool,
+    },
+    /// Part is multipart. `MultipartStart` is about to be emitted
+    MultipartStart(Source<R>),
+    /// Next part of multipart is about to be read
+    NextPart(Source<R>),
+    Body(ContentTransferEncodingDecoder<Source<R>>),
+    /// Content of part was read. `PartEnd` is about to be emitted
+    PartDone(Source<R>),
+    /// Part has ended. Now parent part has to be resumed
+    Ascend(Source<R>),
+    Done,
+}
+
+/// MimeEventReader walks MIME message in streaming manner and emits events for each part it finds.
+/// Only headers are buffered(up to `MAX_HEADER_SIZE` bytes) so messages of any size may be processed.
+///
+/// Once error is returned reader should not be used anymore.
+///
+/// # Example
+/// ```rust
+///# use std::io::Cursor;
+///# use smtpc::mail::event::{MimeEvent, MimeEventReader};
+///const MAIL: &str = "\
+///Subject: Test\r\n\
+///Content-Type: multipart/mixed; boundary=b1\r\n\
+///\r\n\
+///--b1\r\n\
+///Content-Transfer-Encoding: base64\r\n\
+///\r\n\
+///SGVsbG8h\r\n\
+///--b1--\r\n";
+///let mut r = MimeEventReader::new(Cursor::new(MAIL.as_bytes()));
+///let mut body = Vec::new();
+///let mut depth = 0;
+///while let Some(event) = r.next_event().unwrap() {
+///    match event {
+///        MimeEvent::PartStart { .. } => depth += 1,
+///        MimeEvent::PartEnd => depth -= 1,
+///        MimeEvent::BodyChunk(chunk) => body.extend_from_slice(chunk),
+///        _ => {}
+///    }
+///}
+///assert_eq!(depth, 0);
+///assert_eq!(body, b"Hello!");
+/// ```
+pub struct MimeEventReader<R> {
+    state: State<R>,
+    stack: Vec<Frame>,
+    buf: Vec<u8>,
+    options: RawHeaderParseOptions,
+}
+
+impl<R> MimeEventReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// with_chunk_size creates reader which emits `BodyChunk`s of at most given size
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        Self::with_options(reader, chunk_size, RawHeaderParseOptions::default())
+    }
+
+    /// with_options creates reader which emits `BodyChunk`s of at most given size
+    /// and parses headers of parts using given options
+    pub fn with_options(reader: R, chunk_size: usize, options: RawHeaderParseOptions) -> Self {
+        assert!(chunk_size > 0, "Chunk size must not be zero");
+        Self {
+            state: State::Headers {
+                source: Source::Root(reader),
+                is_digest: false,
+            },
+            stack: Vec::new(),
+            buf: vec![0u8; chunk_size],
+            options,
+        }
+    }
+}
+
+impl<R> MimeEventReader<R> where R: Read {
+    /// start_part decides how part should be read once it's headers are known.
+    fn start_part(&mut self, headers: RawHeaderBag<'static>, source: Source<R>, is_digest: bool) -> Result<MimeEvent<'_>, MessageParseError> {
+        let (mime_type, mime_subtype, params) = parse_content_type(&headers, is_digest);
+        let boundary = find_param(&params, "boundary");
+        let is_multipart = boundary.is_some() && mime_type.eq_ignore_ascii_case("multipart");
+        let is_message = mime_type.eq_ignore_ascii_case("message") && mime_subtype.eq_ignore_ascii_case("rfc822");
+
+        if (is_multipart || is_message) && self.stack.len() >= MAX_NESTING_DEPTH {
+            return Err(MessageParseError::NestingTooDeep);
+        }
+        self.state = match boundary {
+            Some(boundary) if is_multipart => {
+                self.stack.push(Frame::Multipart {
+                    is_digest: mime_subtype.eq_ignore_ascii_case("digest"),
+                });
+                let mr = MultipartReader::with_options(source, boundary.as_bytes(), self.options);
+                State::MultipartStart(Source::Multipart(Box::new(mr)))
+            }
+            _ if is_message => {
+                self.stack.push(Frame::Message);
+                State::Headers {
+                    source,
+                    is_digest: false,
+                }
+            }
+            _ => State::Body(parse_transfer_encoding(&headers).get_decoder(source)),
+        };
+        Ok(MimeEvent::PartStart { headers })
+    }
+
+    /// next_event reads message until next event occurs.
+    /// `None` is returned once entire message was read.
+    pub fn next_event(&mut self) -> Result<Option<MimeEvent<'_>>, MessageParseError> {
+        loop {
+            match std::mem::replace(&mut self.state, State::Done) {
+                State::Headers { source, is_digest } => {
+                    let (headers, source) = read_part_headers(source, self.options)?;
+                    return self.start_part(headers, source, is_digest).map(Some);
+                }
+                State::MultipartStart(source) => {
+                    self.state = State::NextPart(source);
+                    return Ok(Some(MimeEvent::MultipartStart));
+                }
+                State::NextPart(Source::Multipart(mut mr)) => {
+                    let is_digest = match self.stack.last() {
+                        Some(Frame::Multipart { is_digest }) => *is_digest,
+                        _ => unreachable!("Next part has to be read in multipart"),
+                    };
+                    match mr.next_part()? {
+                        Some(headers) => {
+                            return self.start_part(headers, Source::Multipart(mr), is_digest).map(Some);
+                        }
+                        None => {
+                            self.stack.pop();
+                            self.state = State::PartDone(mr.into_inner());
+                            return Ok(Some(MimeEvent::MultipartEnd));
+                        }
+                    }
+                }
+                State::NextPart(Source::Root(_)) => unreachable!("Part of multipart has to be read with multipart reader"),
+                State::Body(mut decoder) => {
+                    let sz = decoder.read(&mut self.buf)?;
+                    if sz == 0 {
+                        self.state = State::PartDone(decoder.into_inner());
+                    } else {
+                        self.state = State::Body(decoder);
+                        return Ok(Some(MimeEvent::BodyChunk(&self.buf[..sz])));
+                    }
+                }
+                State::PartDone(source) => {
+                    self.state = State::Ascend(source);
+                    return Ok(Some(MimeEvent::PartEnd));
+                }
+                State::Ascend(source) => {
+                    self.state = match self.stack.last() {
+                        None => State::Done,
+                        Some(Frame::Message) => {
+                            self.stack.pop();
+                            State::PartDone(source)
+                        }
+                        // multipart reader skips whatever is left, for instance epilogue of nested multipart
+                        Some(Frame::Multipart { .. }) => State::NextPart(source),
+                    };
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::mail::header::UnfoldingMode;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum OwnedEvent {
+        PartStart(Option<String>),
+        Body(Vec<u8>),
+        PartEnd,
+        MultipartStart,
+        MultipartEnd,
+    }
+
+    fn collect_events(mail: &str, chunk_size: usize) -> Vec<OwnedEvent> {
+        let mut r = MimeEventReader::with_chunk_size(Cursor::new(mail.as_bytes()), chunk_size);
+        let mut res: Vec<OwnedEvent> = Vec::new();
+        while let Some(e) = r.next_event().unwrap() {
+            let e = match e {
+                MimeEvent::PartStart { headers } => OwnedEvent::PartStart(
+                    headers.container().get("Content-Type").map(|v| String::from_utf8_lossy(&v[0]).into_owned())
+                ),
+                MimeEvent::BodyChunk(c) => {
+                    // merge chunks so result does not depend on chunk size
+                    if let Some(OwnedEvent::Body(b)) = res.last_mut() {
+                        b.extend_from_slice(c);
+                        continue;
+                    }
+                    OwnedEvent::Body(c.to_vec())
+                }
+                MimeEvent::PartEnd => OwnedEvent::PartEnd,
+                MimeEvent::MultipartStart => OwnedEvent::MultipartStart,
+                MimeEvent::MultipartEnd => OwnedEvent::MultipartEnd,
+            };
+            res.push(e);
+        }
+        res
+    }
+
+    #[test]
+    fn test_can_read_simple_message() {
+        for sz in [1, 2, 3, 16, 1024].iter().cloned() {
+            assert_eq!(collect_events("Subject: Test\r\n\r\nSome body", sz), vec![
+                OwnedEvent::PartStart(None),
+                OwnedEvent::Body(b"Some body".to_vec()),
+                OwnedEvent::PartEnd,
+            ]);
+        }
+    }
+
+    #[test]
+    fn test_can_read_nested_message() {
+        const MAIL: &str = "\
+Content-Type: multipart/mixed; boundary=outer\r\n\
+\r\n\
+preamble\r\n\
+--outer\r\n\
+Content-Type: multipart/alternative; boundary=inner\r\n\
+\r\n\
+--inner\r\n\
+\r\n\
+plain\r\n\
+--inner\r\n\
+Content-Type: text/html\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+<p>=C5=81</p>\r\n\
+--inner--\r\n\
+inner epilogue\r\n\
+--outer\r\n\
+Content-Type: message/rfc822\r\n\
+\r\n\
+Subject: Inner\r\n\
+\r\n\
+inner message\r\n\
+--outer\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+YWFh\r\n\
+YWE=\r\n\
+--outer--\r\n\
+epilogue";
+        for sz in [1, 2, 3, 16, 1024].iter().cloned() {
+            assert_eq!(collect_events(MAIL, sz), vec![
+                OwnedEvent::PartStart(Some("multipart/mixed; boundary=outer".to_string())),
+                OwnedEvent::MultipartStart,
+                OwnedEvent::PartStart(Some("multipart/alternative; boundary=inner".to_string())),
+                OwnedEvent::MultipartStart,
+                OwnedEvent::PartStart(None),
+                OwnedEvent::Body(b"plain".to_vec()),
+                OwnedEvent::PartEnd,
+                OwnedEvent::PartStart(Some("text/html".to_string())),
+                OwnedEvent::Body("<p>Ł</p>".as_bytes().to_vec()),
+                OwnedEvent::PartEnd,
+                OwnedEvent::MultipartEnd,
+                OwnedEvent::PartEnd,
+                OwnedEvent::PartStart(Some("message/rfc822".to_string())),
+                OwnedEvent::PartStart(None),
+                OwnedEvent::Body(b"inner message".to_vec()),
+                OwnedEvent::PartEnd,
+                OwnedEvent::PartEnd,
+                OwnedEvent::PartStart(None),
+                OwnedEvent::Body(b"aaaaa".to_vec()),
+                OwnedEvent::PartEnd,
+                OwnedEvent::MultipartEnd,
+                OwnedEvent::PartEnd,
+            ]);
+        }
+    }
+
+    #[test]
+    fn test_tolerates_missing_final_boundary() {
+        assert_eq!(collect_events("Content-Type: multipart/mixed; boundary=b\r\n\r\n--b\r\n\r\nbody", 16), vec![
+            OwnedEvent::PartStart(Some("multipart/mixed; boundary=b".to_string())),
+            OwnedEvent::MultipartStart,
+            OwnedEvent::PartStart(None),
+            OwnedEvent::Body(b"body".to_vec()),
+            OwnedEvent::PartEnd,
+            OwnedEvent::MultipartEnd,
+            OwnedEvent::PartEnd,
+        ]);
+    }
+
+    #[test]
+    fn test_can_read_with_options() {
+        const MAIL: &str = "\
+X-Test: a\r\n  b\r\n\
+Content-Type: multipart/mixed; boundary=b\r\n\
+\r\n\
+--b\r\n\
+X-Test: c\r\n  d\r\n\
+\r\n\
+body\r\n\
+--b--\r\n";
+        for (unfolding, o) in [
+            (UnfoldingMode::Spec, vec!["a  b", "c  d"]),
+            (UnfoldingMode::Legacy, vec!["ab", "cd"]),
+        ].iter() {
+            let options = RawHeaderParseOptions {
+                unfolding: *unfolding,
+            };
+            let mut r = MimeEventReader::with_options(Cursor::new(MAIL.as_bytes()), 16, options);
+            let mut values = Vec::new();
+            while let Some(e) = r.next_event().unwrap() {
+                if let MimeEvent::PartStart { headers } = e {
+                    values.push(String::from_utf8_lossy(&headers.container()["X-Test"][0]).into_owned());
+                }
+            }
+            assert_eq!(&values, o, "{:?}", unfolding);
+        }
+    }
+}
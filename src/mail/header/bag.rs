@@ -1,37 +1,275 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::str::FromStr;
+use std::error;
+use std::fmt;
 
 use mime::FromStrError;
 
-use crate::mail::address::{EmailAddress, EmailAddressParseError};
-use crate::mail::header::{ContentTransferEncoding, MessageIDParseError, parse_message_id, parse_multiple_message_id, RawHeaderBag};
-use crate::utils::quoted::{parse_maybe_rfc_2047, QuotedStringError};
+use crate::encoding::charset::Charset;
+use crate::mail::address::{Address, EmailAddress, EmailAddressParseError};
+use crate::mail::date::{Date, DateParseError};
+use crate::mail::header::{ContentDisposition, ContentTransferEncoding, CustomHeader, Header, HeaderRegistry, into_owned_params, MessageIDParseError, Params, ParamsParseError, RawHeaderBag};
+use crate::mail::header::typed::parse_builtin_header;
+use crate::utils::quoted::{decode_rfc_2047_strict, QuotedStringError};
 
+/// DEFAULT_HEADER_CHARSET is charset used to decode header values which are not valid utf-8.
+/// Windows-1252 is superset of ISO-8859-1, which is the most common charset of such headers.
+pub const DEFAULT_HEADER_CHARSET: Charset = Charset::Windows1252;
+
+/// decode_header_value decodes raw header value to text.
+///
+/// Valid utf-8 is returned as is. Otherwise value is decoded with fallback charset
+/// and if it's not valid in that charset either, invalid utf-8 sequences are replaced with replacement char.
+pub fn decode_header_value(data: &[u8], fallback: Charset) -> Cow<'_, str> {
+    match std::str::from_utf8(data) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => fallback.decode_strict(data)
+            .unwrap_or_else(|_| String::from_utf8_lossy(data)),
+    }
+}
+
+/// ParsedHeaderMap maps header names to their parsed values in order they were found in message
+pub type ParsedHeaderMap<'a> = HashMap<Cow<'a, str>, Vec<ParsedMailHeader<'a>>>;
+
+/// ParsedHeaderBag is list of parsed headers in order they were found in message.
+/// Map of header names to their values and index of lowercase names are derived from it for lookups.
 #[derive(Debug, Clone, PartialEq, Eq)]
-#[derive(From, Into)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct ParsedHeaderBag<'a> {
-    container: HashMap<Cow<'a, str>, Vec<ParsedMailHeader<'a>>>,
+    headers: Vec<(Cow<'a, str>, ParsedMailHeader<'a>)>,
+    container: ParsedHeaderMap<'a>,
+    // positions of headers in `headers` by lowercase name
+    index: HashMap<String, Vec<usize>>,
+}
+
+impl<'a> From<Vec<(Cow<'a, str>, ParsedMailHeader<'a>)>> for ParsedHeaderBag<'a> {
+    #[inline]
+    fn from(headers: Vec<(Cow<'a, str>, ParsedMailHeader<'a>)>) -> Self {
+        Self::from_headers(headers)
+    }
+}
+
+impl<'a> From<ParsedHeaderMap<'a>> for ParsedHeaderBag<'a> {
+    /// from creates bag from map of headers. Map has no order, so headers are sorted by name.
+    fn from(container: ParsedHeaderMap<'a>) -> Self {
+        let mut names: Vec<_> = container.keys().cloned().collect();
+        names.sort();
+        let headers: Vec<_> = names.into_iter()
+            .flat_map(|name| container[&name].iter().map(move |h| (name.clone(), h.clone())).collect::<Vec<_>>())
+            .collect();
+        Self::from_headers(headers)
+    }
+}
+
+impl<'a> From<ParsedHeaderBag<'a>> for ParsedHeaderMap<'a> {
+    fn from(bag: ParsedHeaderBag<'a>) -> Self {
+        bag.into_inner()
+    }
 }
 
 impl<'a> ParsedHeaderBag<'a> {
-    pub fn parse_raw_bag(bag: &'a RawHeaderBag<'a>) -> Self {
-        let mut c = HashMap::new();
-        for (k, v) in bag.container().iter() {
-            let mut res_vec = Vec::with_capacity(v.len());
-            for val in v {
-                res_vec.push(ParsedMailHeader::try_parse(k, val.as_ref()));
-            }
-            c.insert(Cow::Borrowed(k.as_ref()), res_vec);
+    // it's not `From::from`, since `from` method returns `From` header
+    fn from_headers(headers: Vec<(Cow<'a, str>, ParsedMailHeader<'a>)>) -> Self {
+        let mut container: ParsedHeaderMap<'a> = HashMap::new();
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, (name, h)) in headers.iter().enumerate() {
+            container.entry(name.clone()).or_default().push(h.clone());
+            index.entry(name.to_ascii_lowercase()).or_default().push(i);
         }
         Self {
-            container: c
+            headers,
+            container,
+            index,
         }
     }
 
+    /// parse_raw_bag parses headers of raw bag.
+    /// Values which are not valid utf-8 are decoded with `DEFAULT_HEADER_CHARSET`.
+    #[inline]
+    pub fn parse_raw_bag(bag: &'a RawHeaderBag<'a>) -> Self {
+        Self::parse_raw_bag_with_charset(bag, DEFAULT_HEADER_CHARSET)
+    }
+
+    /// parse_raw_bag_with_charset parses headers of raw bag.
+    /// Values which are not valid utf-8 are decoded with given charset. See `decode_header_value`
+    #[inline]
+    pub fn parse_raw_bag_with_charset(bag: &'a RawHeaderBag<'a>, fallback: Charset) -> Self {
+        Self::parse_raw_bag_with_registry(bag, &HeaderRegistry::new(), fallback)
+    }
+
+    /// parse_raw_bag_with_registry parses headers of raw bag with parsers from given registry.
+    /// Values which are not valid utf-8 are decoded with given charset. See `decode_header_value`
+    pub fn parse_raw_bag_with_registry(bag: &'a RawHeaderBag<'a>, registry: &HeaderRegistry, fallback: Charset) -> Self {
+        let mut headers = Vec::with_capacity(bag.headers().len());
+        for h in bag.headers() {
+            let parsed = match decode_header_value(&h.value, fallback) {
+                Cow::Borrowed(text) => registry.try_parse(&h.name, text),
+                Cow::Owned(text) => registry.try_parse(&h.name, &text).into_owned(),
+            };
+            headers.push((Cow::Borrowed(h.name.as_ref()), parsed));
+        }
+        Self::from_headers(headers)
+    }
+
+    /// parse_raw_bag_strict parses headers of raw bag the same way `parse_raw_bag_with_registry` does,
+    /// but it fails on first header whose value does not match it's type.
+    /// Subject with encoded word which can't be decoded fails too, rather than being kept as it is.
+    /// Headers which are not known by registry nor this crate are kept as `ParsedMailHeader::UnknownHeader`.
+    pub fn parse_raw_bag_strict(bag: &'a RawHeaderBag<'a>, registry: &HeaderRegistry, fallback: Charset) -> Result<Self, HeaderError> {
+        let mut headers = Vec::with_capacity(bag.headers().len());
+        for h in bag.headers() {
+            let value = decode_header_value(&h.value, fallback);
+            let parsed = match &value {
+                Cow::Borrowed(text) => registry.parse(&h.name, text),
+                Cow::Owned(text) => registry.parse(&h.name, text).map(ParsedMailHeader::into_owned),
+            };
+            let parsed = parsed.and_then(|p| match p {
+                ParsedMailHeader::Subject(_) => decode_rfc_2047_strict(&value).map(|_| p).map_err(MailHeaderParseError::from),
+                p => Ok(p),
+            });
+            let parsed = match parsed {
+                Ok(parsed) => parsed,
+                Err(MailHeaderParseError::TypeNotMatched) => ParsedMailHeader::UnknownHeader(value),
+                Err(error) => return Err(HeaderError {
+                    name: h.original_name.to_string(),
+                    offset: h.offset,
+                    error,
+                }),
+            };
+            headers.push((Cow::Borrowed(h.name.as_ref()), parsed));
+        }
+        Ok(Self::from_headers(headers))
+    }
+
+    /// get_all returns all headers with given name in order they were found in message.
+    /// Name is compared case insensitive.
+    pub fn get_all<'b: 'n, 'n>(&'b self, name: &'n str) -> impl Iterator<Item=&'b ParsedMailHeader<'a>> + 'n {
+        // index is scanned instead of looked up, so name does not have to be lowercased into new string
+        self.index.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| &v[..])
+            .unwrap_or(&[])
+            .iter()
+            .map(move |i| &self.headers[*i].1)
+    }
+
+    /// get_first returns first header with given name. Name is compared case insensitive.
+    #[inline]
+    pub fn get_first(&self, name: &str) -> Option<&ParsedMailHeader<'a>> {
+        self.get_all(name).next()
+    }
+
+    /// get returns first header of type `H` which could be parsed.
+    #[inline]
+    pub fn get<'b, H: Header<'b> + 'b>(&'b self) -> Option<H> {
+        self.get_all_of::<H>().next()
+    }
+
+    /// get_all_of returns all headers of type `H` which could be parsed.
+    pub fn get_all_of<'b, H: Header<'b> + 'b>(&'b self) -> impl Iterator<Item=H> + 'b {
+        self.get_all(H::NAME).filter_map(H::from_parsed)
+    }
+
+    /// find_header returns first value of headers with given name for which `f` returns something.
+    fn find_header<'b, T>(&'b self, name: &str, f: impl FnMut(&'b ParsedMailHeader<'a>) -> Option<T>) -> Option<T> {
+        self.get_all(name).find_map(f)
+    }
+
+    pub fn subject(&self) -> Option<&str> {
+        self.find_header("Subject", |h| match h {
+            ParsedMailHeader::Subject(s) => Some(s.as_ref()),
+            _ => None,
+        })
+    }
+
+    pub fn from(&self) -> Option<&EmailAddress<'a>> {
+        self.find_header("From", |h| match h {
+            ParsedMailHeader::From(a) => Some(a),
+            _ => None,
+        })
+    }
+
+    pub fn reply_to(&self) -> Option<&EmailAddress<'a>> {
+        self.find_header("Reply-To", |h| match h {
+            ParsedMailHeader::ReplyTo(a) => Some(a),
+            _ => None,
+        })
+    }
+
+    pub fn return_path(&self) -> Option<&EmailAddress<'a>> {
+        self.find_header("Return-Path", |h| match h {
+            ParsedMailHeader::ReturnPath(a) => Some(a),
+            _ => None,
+        })
+    }
+
+    pub fn to(&self) -> Option<&[Address<'a>]> {
+        self.find_header("To", |h| match h {
+            ParsedMailHeader::To(a) => Some(&a[..]),
+            _ => None,
+        })
+    }
+
+    pub fn cc(&self) -> Option<&[Address<'a>]> {
+        self.find_header("Cc", |h| match h {
+            ParsedMailHeader::Cc(a) => Some(&a[..]),
+            _ => None,
+        })
+    }
+
+    pub fn bcc(&self) -> Option<&[Address<'a>]> {
+        self.find_header("Bcc", |h| match h {
+            ParsedMailHeader::Bcc(a) => Some(&a[..]),
+            _ => None,
+        })
+    }
+
+    pub fn date(&self) -> Option<Date> {
+        self.find_header("Date", |h| match h {
+            ParsedMailHeader::Date(d) => Some(*d),
+            _ => None,
+        })
+    }
+
+    /// message_id returns message id without angle brackets
+    pub fn message_id(&self) -> Option<&str> {
+        self.find_header("Message-ID", |h| match h {
+            ParsedMailHeader::MessageID(id) => Some(id.as_ref()),
+            _ => None,
+        })
+    }
+
+    pub fn in_reply_to(&self) -> Option<&str> {
+        self.find_header("In-Reply-To", |h| match h {
+            ParsedMailHeader::InReplyTo(id) => Some(id.as_ref()),
+            _ => None,
+        })
+    }
+
+    pub fn references(&self) -> Option<&[Cow<'a, str>]> {
+        self.find_header("References", |h| match h {
+            ParsedMailHeader::References(ids) => Some(&ids[..]),
+            _ => None,
+        })
+    }
+
+    /// content_type returns type, subtype, suffix and parameters of `Content-Type` header
+    pub fn content_type(&self) -> Option<(&str, &str, Option<&str>, &Params<'a>)> {
+        self.find_header("Content-Type", |h| match h {
+            ParsedMailHeader::ContentType(t, st, suffix, params) => Some((t.as_ref(), st.as_ref(), suffix.as_ref().map(|s| s.as_ref()), params)),
+            _ => None,
+        })
+    }
+
+    pub fn content_disposition(&self) -> Option<&ContentDisposition<'a>> {
+        self.find_header("Content-Disposition", |h| match h {
+            ParsedMailHeader::ContentDisposition(cd) => Some(cd),
+            _ => None,
+        })
+    }
+
     /// get_content_transfer_encoding gets `ContentTransferEncoding` from headers.
-    /// If there are many valid `Content-Transfer-Encoding` headers any of results may be returned by this function.
+    /// If there are many valid `Content-Transfer-Encoding` headers first one is returned.
     ///
     /// `None` Is returned when there is no `Content-Transfer-Encoding` header was found or it was not parsed properly.
     ///
@@ -45,25 +283,32 @@ impl<'a> ParsedHeaderBag<'a> {
     ///assert_eq!(parsed.get_content_transfer_encoding().unwrap(), ContentTransferEncoding::QuotedPrintable);
     /// ```
     pub fn get_content_transfer_encoding(&self) -> Option<ContentTransferEncoding> {
-        // Note: we can't lookup hash map as case may vary
-        // right now `RawHeaderBag` does not normalize header names(?)
-        for parsed_header in self.container.values().map(|v| v.iter()).flatten() {
-            if let ParsedMailHeader::ContentTransferEncoding(cte) = parsed_header {
-                return Some(*cte);
-            }
-        }
-        None
+        self.find_header("Content-Transfer-Encoding", |h| match h {
+            ParsedMailHeader::ContentTransferEncoding(cte) => Some(*cte),
+            _ => None,
+        })
     }
 
     #[inline]
-    pub fn into_inner(self) -> HashMap<Cow<'a, str>, Vec<ParsedMailHeader<'a>>> {
+    pub fn into_inner(self) -> ParsedHeaderMap<'a> {
         self.container
     }
 
     #[inline]
-    pub fn container(&self) -> &HashMap<Cow<'a, str>, Vec<ParsedMailHeader<'a>>> {
+    pub fn container(&self) -> &ParsedHeaderMap<'a> {
         &self.container
     }
+
+    /// headers returns names and values of all headers in order they were found in message
+    #[inline]
+    pub fn headers(&self) -> &[(Cow<'a, str>, ParsedMailHeader<'a>)] {
+        &self.headers
+    }
+
+    #[inline]
+    pub fn into_headers(self) -> Vec<(Cow<'a, str>, ParsedMailHeader<'a>)> {
+        self.headers
+    }
 }
 
 #[derive(Debug, From)]
@@ -73,6 +318,73 @@ pub enum MailHeaderParseError {
     EmailAddressParseError(EmailAddressParseError),
     MessageIDParseError(MessageIDParseError),
     MimeError(FromStrError),
+    DateParseError(DateParseError),
+    ParamsParseError(ParamsParseError),
+}
+
+impl fmt::Display for MailHeaderParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailHeaderParseError::TypeNotMatched => write!(f, "value does not match type of header"),
+            MailHeaderParseError::QuotedStringError(e) => e.fmt(f),
+            MailHeaderParseError::EmailAddressParseError(e) => e.fmt(f),
+            MailHeaderParseError::MessageIDParseError(e) => e.fmt(f),
+            MailHeaderParseError::MimeError(e) => write!(f, "invalid mime type: {}", e),
+            MailHeaderParseError::DateParseError(e) => e.fmt(f),
+            MailHeaderParseError::ParamsParseError(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for MailHeaderParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            MailHeaderParseError::TypeNotMatched => None,
+            MailHeaderParseError::QuotedStringError(e) => e.source(),
+            MailHeaderParseError::EmailAddressParseError(e) => e.source(),
+            MailHeaderParseError::MessageIDParseError(e) => e.source(),
+            MailHeaderParseError::MimeError(e) => e.source(),
+            MailHeaderParseError::DateParseError(e) => e.source(),
+            MailHeaderParseError::ParamsParseError(e) => e.source(),
+        }
+    }
+}
+
+/// HeaderError is returned when value of single header of message can't be parsed.
+///
+/// # Example
+/// ```rust
+///# use smtpc::encoding::charset::Charset;
+///# use smtpc::mail::header::{HeaderRegistry, ParsedHeaderBag, RawHeaderBag};
+///let raw = RawHeaderBag::parse("Subject: Hello\r\nTo: John Doe <john@example.com\r\n").unwrap();
+///let err = ParsedHeaderBag::parse_raw_bag_strict(&raw, &HeaderRegistry::new(), Charset::Windows1252).unwrap_err();
+///assert_eq!(err.name, "To");
+///assert_eq!(err.offset, 16);
+///assert_eq!(
+///    err.to_string(),
+///    "invalid value of header `To` at byte 16: invalid address: expected `>` at byte 26, found end of input",
+///);
+/// ```
+#[derive(Debug)]
+pub struct HeaderError {
+    /// name is name of header as it was written in message
+    pub name: String,
+    /// offset is byte offset of header in parsed text. See `RawHeader::offset`
+    pub offset: usize,
+    /// error is error of parsing value of header. Positions in it are relative to the beginning of unfolded value.
+    pub error: MailHeaderParseError,
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid value of header `{}` at byte {}: {}", self.name, self.offset, self.error)
+    }
+}
+
+impl error::Error for HeaderError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.error.source()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -84,9 +396,9 @@ pub enum ParsedMailHeader<'a> {
     ReturnPath(EmailAddress<'a>),
     EnvelopeTo(EmailAddress<'a>),
 
-    Bcc(Vec<EmailAddress<'a>>),
-    Cc(Vec<EmailAddress<'a>>),
-    To(Vec<EmailAddress<'a>>),
+    Bcc(Vec<Address<'a>>),
+    Cc(Vec<Address<'a>>),
+    To(Vec<Address<'a>>),
 
     From(EmailAddress<'a>),
 
@@ -104,13 +416,19 @@ pub enum ParsedMailHeader<'a> {
         Option<Cow<'a, str>>, // xml
         HashMap<Cow<'a, str>, Vec<Cow<'a, str>>>, // encoding=utf8
     ),
+    // for example: Content-Disposition: attachment; filename=report.pdf
+    ContentDisposition(ContentDisposition<'a>),
     ContentTransferEncoding(ContentTransferEncoding),
     // language may be in somewhat parsed/normalized form?
     ContentLanguage(Cow<'a, str>),
-    // Date() // TODO(teawithsand) implement this
+    Date(Date),
 
     // TODO(teawithsand): DKIM header
 
+    /// Custom is header parsed by type registered in `HeaderRegistry`. It's not serialized.
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    Custom(CustomHeader<'a>),
+
     UnknownHeader(Cow<'a, str>),
 }
 
@@ -124,77 +442,47 @@ impl<'a> ParsedMailHeader<'a> {
         }
     }
 
-    // name is assumed to be canonical
-    pub fn parse(name: &str, content: &'a str) -> Result<Self, MailHeaderParseError> {
-        // TODO(teawithsand) introduce case insensitive compare(so string is not copied) and then accept form which may be not normalized
-        //  For instance both `Subject` and `subject` are valid names for smtp headers but first one is normalized
-        let name = name.to_ascii_lowercase();
-        match &name[..] {
-            "subject" => {
-                Ok(ParsedMailHeader::Subject(parse_maybe_rfc_2047(content)?))
-            }
-            "return-path" => {
-                Ok(ParsedMailHeader::ReturnPath(EmailAddress::parse_single(content)?))
-            }
-            "envelope-to" => {
-                Ok(ParsedMailHeader::ReturnPath(EmailAddress::parse_single(content)?))
-            }
-            "reply-to" => {
-                Ok(ParsedMailHeader::ReplyTo(EmailAddress::parse_single(content)?))
-            }
-            "bcc" => {
-                Ok(ParsedMailHeader::Bcc(EmailAddress::parse_group(content)?))
-            }
-            "cc" => {
-                Ok(ParsedMailHeader::Cc(EmailAddress::parse_group(content)?))
-            }
-            "to" => {
-                Ok(ParsedMailHeader::To(EmailAddress::parse_group(content)?))
-            }
-            "from" => {
-                Ok(ParsedMailHeader::From(EmailAddress::parse_single(content)?))
-            }
-            "message-id" => {
-                Ok(ParsedMailHeader::MessageID(parse_message_id(content)?))
-            }
-            "in-reply-to" => {
-                Ok(ParsedMailHeader::InReplyTo(parse_message_id(content)?))
-            }
-            "references" => {
-                Ok(ParsedMailHeader::References(parse_multiple_message_id(content)?))
-            }
-            "content-type" => {
-                let mime = mime::Mime::from_str(content)?;
-                let mut v: HashMap<_, Vec<_>> = HashMap::new();
-                for (name, value) in mime.params().map(|(p1, p2)| (p1.to_string(), p2.to_string())) {
-                    let k = Cow::Owned(name);
-                    if let Some(val) = v.get_mut(&k) {
-                        val.push(Cow::Owned(value));
-                    } else {
-                        v.insert(k, vec![Cow::Owned(value)]);
-                    }
-                }
-                let res = ParsedMailHeader::ContentType(
-                    Cow::Owned(mime.type_().as_str().to_string()),
-                    Cow::Owned(mime.subtype().as_str().to_string()),
-                    mime.suffix().map(|s| Cow::Owned(s.as_str().to_string())),
-                    v,
-                );
-                Ok(res)
-            }
-            "content-transfer-encoding" => {
-                Ok(ParsedMailHeader::ContentTransferEncoding(ContentTransferEncoding::decode(content)))
-            }
-            "content-language" => {
-                Ok(ParsedMailHeader::ContentLanguage(Cow::Borrowed(content)))
-            }
-            _ => Err(MailHeaderParseError::TypeNotMatched)
+    /// into_owned makes `ParsedMailHeader` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> ParsedMailHeader<'static> {
+        let owned = |c: Cow<str>| Cow::Owned(c.into_owned());
+        match self {
+            ParsedMailHeader::Subject(s) => ParsedMailHeader::Subject(owned(s)),
+            ParsedMailHeader::ReplyTo(a) => ParsedMailHeader::ReplyTo(a.into_owned()),
+            ParsedMailHeader::ReturnPath(a) => ParsedMailHeader::ReturnPath(a.into_owned()),
+            ParsedMailHeader::EnvelopeTo(a) => ParsedMailHeader::EnvelopeTo(a.into_owned()),
+            ParsedMailHeader::Bcc(a) => ParsedMailHeader::Bcc(a.into_iter().map(Address::into_owned).collect()),
+            ParsedMailHeader::Cc(a) => ParsedMailHeader::Cc(a.into_iter().map(Address::into_owned).collect()),
+            ParsedMailHeader::To(a) => ParsedMailHeader::To(a.into_iter().map(Address::into_owned).collect()),
+            ParsedMailHeader::From(a) => ParsedMailHeader::From(a.into_owned()),
+            ParsedMailHeader::MessageID(id) => ParsedMailHeader::MessageID(owned(id)),
+            ParsedMailHeader::InReplyTo(id) => ParsedMailHeader::InReplyTo(owned(id)),
+            ParsedMailHeader::References(ids) => ParsedMailHeader::References(ids.into_iter().map(owned).collect()),
+            ParsedMailHeader::ContentType(t, st, suffix, params) => ParsedMailHeader::ContentType(
+                owned(t),
+                owned(st),
+                suffix.map(owned),
+                into_owned_params(params),
+            ),
+            ParsedMailHeader::ContentDisposition(cd) => ParsedMailHeader::ContentDisposition(cd.into_owned()),
+            ParsedMailHeader::ContentTransferEncoding(cte) => ParsedMailHeader::ContentTransferEncoding(cte),
+            ParsedMailHeader::ContentLanguage(l) => ParsedMailHeader::ContentLanguage(owned(l)),
+            ParsedMailHeader::Date(d) => ParsedMailHeader::Date(d),
+            ParsedMailHeader::Custom(c) => ParsedMailHeader::Custom(c.into_owned()),
+            ParsedMailHeader::UnknownHeader(h) => ParsedMailHeader::UnknownHeader(owned(h)),
         }
     }
+
+    /// parse parses header of one of types known by this crate. See `HeaderRegistry` for parsing custom headers.
+    /// Name is compared case insensitive.
+    pub fn parse(name: &str, content: &'a str) -> Result<Self, MailHeaderParseError> {
+        parse_builtin_header(name, content).unwrap_or(Err(MailHeaderParseError::TypeNotMatched))
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::mail::header::{DispositionType, find_param};
+
     use super::*;
 
     // TODO(teawithsand) tests for parsed mail bag
@@ -220,7 +508,35 @@ mod test {
             ("Subject", "=?UTF-8?Q?hello?=", Some(ParsedMailHeader::Subject(
                 Cow::Borrowed("hello")
             ))),
-            ("Subject", "=?UTF-8?B?qq?=", None),
+            ("Subject", "=?UTF-8?B?qq?=", Some(ParsedMailHeader::Subject(
+                Cow::Borrowed("=?UTF-8?B?qq?=")
+            ))),
+            ("Subject", "Re: =?UTF-8?Q?Za=C5=BC=C3?=\r\n =?UTF-8?Q?=B3=C5=82=C4=87?= =?ISO-8859-2?Q?g=EA=B6l=B1?= jaźń", Some(ParsedMailHeader::Subject(
+                Cow::Borrowed("Re: Zażółćgęślą jaźń")
+            ))),
+            ("Date", "Tue, 1 Jul 2003 10:52:37 +0200", Some(ParsedMailHeader::Date(
+                Date::new(1057049557, 7200)
+            ))),
+            ("Date", "Tue, 1 Jul 2003 10:52:37", None),
+            ("Content-Type", "text/plain; charset=utf-8; name*=utf-8''%E2%82%AC.txt", Some(ParsedMailHeader::ContentType(
+                Cow::Borrowed("text"),
+                Cow::Borrowed("plain"),
+                None,
+                vec![
+                    (Cow::Borrowed("charset"), vec![Cow::Borrowed("utf-8")]),
+                    (Cow::Borrowed("name"), vec![Cow::Borrowed("€.txt")]),
+                ].into_iter().collect(),
+            ))),
+            ("Content-Disposition", "attachment; filename*0=\"long \"; filename*1*=%E2%82%AC; filename*2=.txt", Some(ParsedMailHeader::ContentDisposition(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                filename: Some(Cow::Borrowed("long €.txt")),
+                creation_date: None,
+                modification_date: None,
+                read_date: None,
+                size: None,
+                params: vec![(Cow::Borrowed("filename"), vec![Cow::Borrowed("long €.txt")])].into_iter().collect(),
+            }))),
+            ("Content-Disposition", "; filename=a.txt", None),
         ].iter().cloned() {
             let res = ParsedMailHeader::parse(n, c);
             if let Some(o) = o {
@@ -230,4 +546,124 @@ mod test {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_can_decode_header_value() {
+        for (i, c, o) in [
+            (&b"Za\xc5\xbc\xc3\xb3\xc5\x82\xc4\x87"[..], Charset::Windows1252, "Zażółć"),
+            (b"Za\xbf\xf3\xb3\xe6", Charset::Iso8859_2, "Zażółć"),
+            (b"Caf\xe9", Charset::Windows1252, "Café"),
+            (b"\x81\xe9", Charset::Windows1252, "\u{FFFD}\u{FFFD}"),
+        ].iter() {
+            assert_eq!(decode_header_value(i, *c), *o);
+        }
+    }
+
+    #[test]
+    fn test_can_parse_8bit_headers() {
+        let raw = RawHeaderBag::parse(&b"Subject: Za\xbf\xf3\xb3\xe6\r\nFrom: Caf\xe9 <a@example.com>\r\n"[..]).unwrap();
+        for (c, subject, from) in [
+            (DEFAULT_HEADER_CHARSET, "Za¿ó³æ", "Café"),
+            (Charset::Iso8859_2, "Zażółć", "Café"),
+        ].iter() {
+            let parsed = ParsedHeaderBag::parse_raw_bag_with_charset(&raw, *c);
+            assert_eq!(parsed.container()["Subject"], vec![ParsedMailHeader::Subject(Cow::Borrowed(subject))]);
+            assert_eq!(parsed.container()["From"], vec![ParsedMailHeader::From(EmailAddress {
+                name: Cow::Borrowed(from),
+                address: Cow::Borrowed("a@example.com"),
+            })]);
+        }
+    }
+
+    #[test]
+    fn test_can_get_typed_headers() {
+        let raw = RawHeaderBag::parse("\
+subject: Hello\r\n\
+FROM: Bob <bob@example.com>\r\n\
+To: a@example.com, b@example.com\r\n\
+Date: Tue, 1 Jul 2003 10:52:37 +0200\r\n\
+Message-Id: <id@example.com>\r\n\
+References: <a@example.com> <b@example.com>\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+Content-Disposition: attachment; filename=a.txt\r\n\
+content-transfer-encoding: base64\r\n\
+Received: from a\r\n\
+Received: from b\r\n\
+RECEIVED: from c\r\n").unwrap();
+        let parsed = ParsedHeaderBag::parse_raw_bag(&raw);
+        assert_eq!(parsed.subject(), Some("Hello"));
+        assert_eq!(parsed.from().unwrap().address, "bob@example.com");
+        assert_eq!(parsed.to().unwrap().len(), 2);
+        assert_eq!(parsed.cc(), None);
+        assert_eq!(parsed.date(), Some(Date::new(1057049557, 7200)));
+        assert_eq!(parsed.message_id(), Some("id@example.com"));
+        assert_eq!(parsed.references().unwrap(), &[Cow::Borrowed("a@example.com"), Cow::Borrowed("b@example.com")][..]);
+        let (t, st, suffix, params) = parsed.content_type().unwrap();
+        assert_eq!((t, st, suffix, find_param(params, "charset")), ("text", "plain", None, Some("utf-8")));
+        assert_eq!(parsed.content_disposition().unwrap().filename.as_ref().unwrap(), "a.txt");
+        assert_eq!(parsed.get_content_transfer_encoding(), Some(ContentTransferEncoding::Base64));
+
+        let received: Vec<_> = parsed.get_all("received").collect();
+        assert_eq!(received, vec![
+            &ParsedMailHeader::UnknownHeader(Cow::Borrowed("from a")),
+            &ParsedMailHeader::UnknownHeader(Cow::Borrowed("from b")),
+            &ParsedMailHeader::UnknownHeader(Cow::Borrowed("from c")),
+        ]);
+        assert_eq!(parsed.get_first("X-Missing"), None);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Received<'a>(&'a str);
+
+    impl<'a> Header<'a> for Received<'a> {
+        const NAME: &'static str = "Received";
+
+        fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+            value.strip_prefix("from ").map(Received).ok_or(MailHeaderParseError::TypeNotMatched)
+        }
+
+        fn to_raw_value(&self) -> String {
+            format!("from {}", self.0)
+        }
+    }
+
+    #[test]
+    fn test_can_get_custom_headers() {
+        let raw = RawHeaderBag::parse("Received: by x\r\nReceived: from a\r\nreceived: from b\r\n").unwrap();
+        let parsed = ParsedHeaderBag::parse_raw_bag(&raw);
+        let received: Vec<_> = parsed.get_all_of::<Received>().collect();
+        assert_eq!(received, vec![Received("a"), Received("b")]);
+        assert!(parsed.get::<Received>().is_some());
+        assert_eq!(parsed.headers()[2], (Cow::Borrowed("Received"), ParsedMailHeader::UnknownHeader(Cow::Borrowed("from b"))));
+
+        let map: ParsedHeaderMap = parsed.clone().into();
+        let bag: ParsedHeaderBag = map.into();
+        assert_eq!(bag.headers(), parsed.headers());
+    }
+
+    #[test]
+    fn test_strict_parsing_keeps_unknown_headers() {
+        let raw = RawHeaderBag::parse("X-Custom: <a>\r\nSubject: Hello\r\nMessage-ID: <a> <b>\r\n").unwrap();
+        let err = ParsedHeaderBag::parse_raw_bag_strict(&raw, &HeaderRegistry::new(), Charset::Windows1252).unwrap_err();
+        assert_eq!(err.name, "Message-ID");
+        assert_eq!(err.offset, 31);
+        match err.error {
+            MailHeaderParseError::MessageIDParseError(e) => assert_eq!(e.position().offset, 4),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        let raw = RawHeaderBag::parse("X-Custom: <a>\r\nSubject: Hello\r\n").unwrap();
+        let parsed = ParsedHeaderBag::parse_raw_bag_strict(&raw, &HeaderRegistry::new(), Charset::Windows1252).unwrap();
+        assert_eq!(parsed.get_first("X-Custom"), Some(&ParsedMailHeader::UnknownHeader(Cow::Borrowed("<a>"))));
+        assert_eq!(parsed.subject(), Some("Hello"));
+
+        let raw = RawHeaderBag::parse("Subject: a =?utf-8?b?!!!?=\r\n").unwrap();
+        assert_eq!(ParsedHeaderBag::parse_raw_bag(&raw).subject(), Some("a =?utf-8?b?!!!?="));
+        let err = ParsedHeaderBag::parse_raw_bag_strict(&raw, &HeaderRegistry::new(), Charset::Windows1252).unwrap_err();
+        assert_eq!(err.name, "Subject");
+        match err.error {
+            MailHeaderParseError::QuotedStringError(_) => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+}
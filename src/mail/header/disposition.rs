@@ -0,0 +1,177 @@
+use std::borrow::Cow;
+
+use crate::mail::date::Date;
+use crate::mail::header::{decode_rfc_2231_params, find_param, into_owned_params, Params, ParamsParseError, parse_params};
+
+/// DispositionType is type of `Content-Disposition` header. See RFC 2183 2.1 and RFC 7578 4.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum DispositionType<'a> {
+    Inline,
+    Attachment,
+    FormData,
+
+    /// Extension is any disposition type that was not recognised. Unrecognized types should be treated as attachment.
+    Extension(Cow<'a, str>),
+}
+
+impl<'a> DispositionType<'a> {
+    pub fn parse(text: &'a str) -> Self {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("inline") {
+            DispositionType::Inline
+        } else if text.eq_ignore_ascii_case("attachment") {
+            DispositionType::Attachment
+        } else if text.eq_ignore_ascii_case("form-data") {
+            DispositionType::FormData
+        } else {
+            DispositionType::Extension(Cow::Borrowed(text))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            DispositionType::Inline => "inline",
+            DispositionType::Attachment => "attachment",
+            DispositionType::FormData => "form-data",
+            DispositionType::Extension(e) => e.as_ref(),
+        }
+    }
+
+    /// into_owned makes `DispositionType` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> DispositionType<'static> {
+        match self {
+            DispositionType::Inline => DispositionType::Inline,
+            DispositionType::Attachment => DispositionType::Attachment,
+            DispositionType::FormData => DispositionType::FormData,
+            DispositionType::Extension(e) => DispositionType::Extension(Cow::Owned(e.into_owned())),
+        }
+    }
+}
+
+/// ContentDisposition is parsed `Content-Disposition` header. See RFC 2183
+///
+/// Parameters are RFC 2231 decoded. Well known ones are available as fields, all are kept in `params`.
+/// Dates and size which can't be parsed are ignored, so single malformed parameter does not make whole header invalid.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::header::{ContentDisposition, DispositionType};
+///let cd = ContentDisposition::parse("attachment; filename=genome.jpeg;\r\n \
+///    modification-date=\"Wed, 12 Feb 1997 16:29:51 -0500\"; size=1024").unwrap();
+///assert_eq!(cd.disposition, DispositionType::Attachment);
+///assert_eq!(cd.filename.as_ref().unwrap(), "genome.jpeg");
+///assert_eq!(cd.modification_date.unwrap().unix_timestamp(), 855782991);
+///assert_eq!(cd.size, Some(1024));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ContentDisposition<'a> {
+    pub disposition: DispositionType<'a>,
+    pub filename: Option<Cow<'a, str>>,
+    pub creation_date: Option<Date>,
+    pub modification_date: Option<Date>,
+    pub read_date: Option<Date>,
+    pub size: Option<u64>,
+    pub params: Params<'a>,
+}
+
+impl<'a> ContentDisposition<'a> {
+    pub fn parse(text: &'a str) -> Result<Self, ParamsParseError> {
+        let (disposition, params) = parse_params(text)?;
+        let params = decode_rfc_2231_params(params.into_iter().map(|(n, v)| (Cow::Borrowed(n), v)));
+        let date = |name| find_param(&params, name).and_then(parse_param_date);
+        Ok(Self {
+            disposition: DispositionType::parse(disposition),
+            filename: find_param(&params, "filename").map(|f| Cow::Owned(f.to_string())),
+            creation_date: date("creation-date"),
+            modification_date: date("modification-date"),
+            read_date: date("read-date"),
+            size: find_param(&params, "size").and_then(|s| s.trim().parse().ok()),
+            params,
+        })
+    }
+
+    /// into_owned makes `ContentDisposition` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> ContentDisposition<'static> {
+        ContentDisposition {
+            disposition: self.disposition.into_owned(),
+            filename: self.filename.map(|f| Cow::Owned(f.into_owned())),
+            creation_date: self.creation_date,
+            modification_date: self.modification_date,
+            read_date: self.read_date,
+            size: self.size,
+            params: into_owned_params(self.params),
+        }
+    }
+
+    /// param returns first value of parameter with given name.
+    /// Name is compared case insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        find_param(&self.params, name)
+    }
+
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.disposition == DispositionType::Inline
+    }
+
+    /// is_attachment returns true for `attachment` and unrecognized disposition types. See RFC 2183 2.8
+    #[inline]
+    pub fn is_attachment(&self) -> bool {
+        matches!(self.disposition, DispositionType::Attachment | DispositionType::Extension(_))
+    }
+}
+
+/// parse_param_date parses date in RFC 5322 format.
+/// Generators often emit dates which are not valid, so lenient parsing is used as fallback.
+fn parse_param_date(text: &str) -> Option<Date> {
+    Date::parse(text).ok()
+        .or_else(|| Date::parse_lenient(text).ok().map(|(d, _)| d))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_parse_content_disposition() {
+        for (i, o) in [
+            ("inline", Some((DispositionType::Inline, None, None, None))),
+            ("INLINE; filename=a.txt", Some((DispositionType::Inline, Some("a.txt"), None, None))),
+            ("form-data; name=field", Some((DispositionType::FormData, None, None, None))),
+            ("x-custom", Some((DispositionType::Extension(Cow::Borrowed("x-custom")), None, None, None))),
+            (
+                "attachment; filename*=utf-8''%E2%82%AC.txt; size=12",
+                Some((DispositionType::Attachment, Some("€.txt"), Some(12), None)),
+            ),
+            (
+                "attachment; size=-1; read-date=\"Tue, 1 Jul 2003 10:52:37 +0200\"",
+                Some((DispositionType::Attachment, None, None, Some(Date::new(1057049557, 7200)))),
+            ),
+            (
+                "attachment; read-date=\"2003-07-01T10:52:37+02:00\"",
+                Some((DispositionType::Attachment, None, None, Some(Date::new(1057049557, 7200)))),
+            ),
+            ("attachment; read-date=yesterday", Some((DispositionType::Attachment, None, None, None))),
+            ("", None),
+            ("attachment; filename=\"a.txt", None),
+        ].iter().cloned() {
+            let res = ContentDisposition::parse(i).ok();
+            let res = res.as_ref().map(|cd| (cd.disposition.clone(), cd.filename.as_ref().map(|f| f.as_ref()), cd.size, cd.read_date));
+            assert_eq!(res, o);
+        }
+    }
+
+    #[test]
+    fn test_unknown_disposition_is_attachment() {
+        for (i, o) in [
+            ("inline", false),
+            ("attachment", true),
+            ("form-data", false),
+            ("x-unknown", true),
+        ].iter() {
+            assert_eq!(ContentDisposition::parse(i).unwrap().is_attachment(), *o);
+        }
+    }
+}
@@ -1,22 +1,58 @@
 use std::borrow::Cow;
+use std::error;
+use std::fmt;
 
+use crate::error::{ErrorPosition, offset_in};
 use crate::utils::iter_ext::CharsOffsetEnumerate;
 
 // not sure if this is right place for this module...
 
+/// MessageIDParseError is returned when message id can't be parsed.
+/// Positions are relative to the beginning of parsed text.
 #[derive(Debug, From)]
 pub enum MessageIDParseError {
-    NoEntryFound,
-    BracketNotClosed,
-    InvalidCharBetweenBrackets,
-    FoundMany,
+    NoEntryFound(ErrorPosition),
+    BracketNotClosed(ErrorPosition),
+    InvalidCharBetweenBrackets(ErrorPosition),
+    /// FoundMany points at second id when single one was expected
+    FoundMany(ErrorPosition),
 }
 
+impl MessageIDParseError {
+    /// position returns place in input where error was found
+    pub fn position(&self) -> &ErrorPosition {
+        match self {
+            MessageIDParseError::NoEntryFound(p) |
+            MessageIDParseError::BracketNotClosed(p) |
+            MessageIDParseError::InvalidCharBetweenBrackets(p) |
+            MessageIDParseError::FoundMany(p) => p,
+        }
+    }
+}
+
+impl fmt::Display for MessageIDParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageIDParseError::NoEntryFound(p) => write!(f, "no message id found: {}", p),
+            MessageIDParseError::BracketNotClosed(p) => write!(f, "message id is not closed: {}", p),
+            MessageIDParseError::InvalidCharBetweenBrackets(p) => write!(f, "invalid char between message ids: {}", p),
+            MessageIDParseError::FoundMany(p) => write!(f, "found many message ids: {}", p),
+        }
+    }
+}
+
+impl error::Error for MessageIDParseError {}
+
 // TODO(teawithsand): make it not parse_simple all ids if many found
 pub fn parse_message_id(text: &str) -> Result<Cow<str>, MessageIDParseError> {
     let mut res = parse_multiple_message_id(text)?;
     if res.len() > 1 {
-        Err(MessageIDParseError::FoundMany)
+        // ids are borrowed from text, so bracket is right before second one
+        let offset = match &res[1] {
+            Cow::Borrowed(id) => offset_in(text.as_bytes(), id.as_bytes()) - 1,
+            Cow::Owned(_) => 0,
+        };
+        Err(MessageIDParseError::FoundMany(ErrorPosition::new(text, offset, "end of input")))
     } else {
         // this one does not cause vector reallocation unlike remove
         Ok(res.swap_remove(0))
@@ -24,7 +60,10 @@ pub fn parse_message_id(text: &str) -> Result<Cow<str>, MessageIDParseError> {
 }
 
 pub fn parse_multiple_message_id(text: &str) -> Result<Vec<Cow<str>>, MessageIDParseError> {
+    let original = text;
     let text = text.trim();
+    let base = offset_in(original.as_bytes(), text.as_bytes());
+    let position = |offset: usize, expected| ErrorPosition::new(original, base + offset, expected);
 
     let mut state = 0;
     let mut previous_offset = 0;
@@ -36,7 +75,7 @@ pub fn parse_multiple_message_id(text: &str) -> Result<Vec<Cow<str>>, MessageIDP
                 state = 1;
                 continue;
             } else {
-                return Err(MessageIDParseError::NoEntryFound);
+                return Err(MessageIDParseError::NoEntryFound(position(byte_offset - c.len_utf8(), "`<`")));
             }
         } else if state == 1 {
             if c == '>' {
@@ -54,7 +93,9 @@ pub fn parse_multiple_message_id(text: &str) -> Result<Vec<Cow<str>>, MessageIDP
                 previous_offset = byte_offset;
                 state = 1;
             } else { // not whitespace char not between '<' and '>' brackets
-                return Err(MessageIDParseError::InvalidCharBetweenBrackets);
+                return Err(MessageIDParseError::InvalidCharBetweenBrackets(
+                    position(byte_offset - c.len_utf8(), "`<` or white space")
+                ));
             }
         } else {
             unreachable!("Invalid state");
@@ -63,9 +104,9 @@ pub fn parse_multiple_message_id(text: &str) -> Result<Vec<Cow<str>>, MessageIDP
     // zero state is no bracket found and one state is looking for bracket end
     // none of them is valid
     if state == 0 {
-        return Err(MessageIDParseError::NoEntryFound);
+        return Err(MessageIDParseError::NoEntryFound(position(text.len(), "`<`")));
     } else if state == 1 {
-        return Err(MessageIDParseError::BracketNotClosed);
+        return Err(MessageIDParseError::BracketNotClosed(position(text.len(), "`>`")));
     }
     Ok(results)
 }
@@ -124,4 +165,18 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_message_id_errors_name_invalid_part() {
+        for (i, o) in [
+            ("", "no message id found: expected `<` at byte 0, found end of input"),
+            ("  x", "no message id found: expected `<` at byte 2, found \"x\""),
+            (" <a", "message id is not closed: expected `>` at byte 3, found end of input"),
+            ("<a> x", "invalid char between message ids: expected `<` or white space at byte 4, found \"x\""),
+            ("  <a> <b>", "found many message ids: expected end of input at byte 6, found \"<b>\""),
+        ].iter() {
+            let err = parse_message_id(i).unwrap_err();
+            assert_eq!(err.to_string(), *o, "{:?}", i);
+        }
+    }
 }
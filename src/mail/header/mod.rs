@@ -1,12 +1,25 @@
 pub use reader::*;
 pub use raw_bag::*;
+// both `raw_bag` and `bag` define `MailHeaderParseError` so raw one gets unambiguous alias
+pub use raw_bag::MailHeaderParseError as RawMailHeaderParseError;
 pub use bag::*;
+pub use bag::MailHeaderParseError;
 pub use transfer_encoding::*;
+pub use params::*;
+pub use disposition::*;
+pub use typed::*;
+pub use registry::*;
 pub(crate) use message_id::*;
+// parsing functions are internal, but their error is part of public errors
+pub use message_id::MessageIDParseError;
 
 mod reader;
 mod raw_bag;
 mod bag;
 
 mod message_id;
-mod transfer_encoding;
\ No newline at end of file
+mod transfer_encoding;
+mod params;
+mod disposition;
+mod typed;
+mod registry;
\ No newline at end of file
@@ -0,0 +1,428 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use crate::encoding::charset::decode_with_label;
+use crate::error::{ErrorPosition, offset_in};
+use crate::utils::cc::is_white_space;
+use crate::utils::hex::encode_hex_char;
+use crate::utils::quoted::{quote_string, QuotedStringError, unquote_string};
+
+/// Params is map of header parameters. Names are kept as they were written,
+/// single name may have multiple values.
+pub type Params<'a> = HashMap<Cow<'a, str>, Vec<Cow<'a, str>>>;
+
+/// RawParams is list of parameters in order they were written. Values are unquoted.
+pub type RawParams<'a> = Vec<(&'a str, Cow<'a, str>)>;
+
+/// ParamsParseError is returned when value with parameters can't be parsed.
+/// Positions are relative to the beginning of parsed text.
+#[derive(Debug, From)]
+pub enum ParamsParseError {
+    EmptyValue(ErrorPosition),
+    /// InvalidParam is returned when parameter has no `=` or empty name
+    InvalidParam(ErrorPosition),
+    QuotedStringError(QuotedStringError),
+}
+
+impl fmt::Display for ParamsParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsParseError::EmptyValue(p) => write!(f, "value is empty: {}", p),
+            ParamsParseError::InvalidParam(p) => write!(f, "invalid parameter: {}", p),
+            ParamsParseError::QuotedStringError(e) => write!(f, "invalid parameter value: {}", e),
+        }
+    }
+}
+
+impl error::Error for ParamsParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ParamsParseError::QuotedStringError(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+/// find_param finds first value of parameter with given name.
+/// Name is compared case insensitive.
+pub(crate) fn find_param<'b>(params: &'b Params<'_>, name: &str) -> Option<&'b str> {
+    params.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .and_then(|(_, v)| v.first())
+        .map(|v| v.as_ref())
+}
+
+/// encode_param encodes content type or disposition parameter.
+/// Non ASCII values are encoded as described in RFC 2231.
+pub(crate) fn encode_param(name: &str, value: &str) -> String {
+    if value.is_ascii() {
+        return format!("{}={}", name, quote_string(value, true));
+    }
+    let mut res = format!("{}*=utf-8''", name);
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            res.push(b as char);
+        } else {
+            let enc = encode_hex_char(b);
+            res.push('%');
+            res.push(enc[0] as char);
+            res.push(enc[1] as char);
+        }
+    }
+    res
+}
+
+/// format_params formats parameters so they can be appended to `Content-Type` or `Content-Disposition` value.
+/// Parameters are sorted by name, so result does not depend on order of hash map.
+pub(crate) fn format_params(params: &Params<'_>) -> String {
+    let mut names: Vec<_> = params.keys().collect();
+    names.sort();
+    let mut res = String::new();
+    for name in names {
+        for value in params[name].iter() {
+            res.push_str("; ");
+            res.push_str(&encode_param(name, value));
+        }
+    }
+    res
+}
+
+/// into_owned_params makes `Params` of arbitrary lifetime `'static`
+pub(crate) fn into_owned_params(params: Params<'_>) -> Params<'static> {
+    params.into_iter()
+        .map(|(k, v)| (
+            Cow::Owned(k.into_owned()),
+            v.into_iter().map(|v| Cow::Owned(v.into_owned())).collect()
+        ))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtendedValueParseError {
+    /// MissingCharset is returned when value has no `charset'language'` prefix
+    MissingCharset,
+    InvalidPercentEncoding,
+}
+
+impl fmt::Display for ExtendedValueParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtendedValueParseError::MissingCharset => write!(f, "extended value has no charset"),
+            ExtendedValueParseError::InvalidPercentEncoding => write!(f, "invalid percent encoding of extended value"),
+        }
+    }
+}
+
+impl error::Error for ExtendedValueParseError {}
+
+/// ExtendedValue is value of RFC 2231 extended parameter like `utf-8'en'%E2%82%AC%20rates`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ExtendedValue {
+    pub charset: String,
+    pub language: Option<String>,
+    pub value: String,
+}
+
+impl ExtendedValue {
+    /// parse parses and decodes extended value. See RFC 2231 4
+    pub fn parse(text: &str) -> Result<Self, ExtendedValueParseError> {
+        let mut parts = text.splitn(3, '\'');
+        let (charset, language, value) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(charset), Some(language), Some(value)) => (charset, language, value),
+            _ => return Err(ExtendedValueParseError::MissingCharset),
+        };
+        let bytes = percent_decode(value)?;
+        Ok(Self {
+            charset: charset.to_string(),
+            language: if language.is_empty() { None } else { Some(language.to_string()) },
+            value: decode_with_label(charset, &bytes).into_owned(),
+        })
+    }
+}
+
+fn percent_decode(text: &str) -> Result<Vec<u8>, ExtendedValueParseError> {
+    let mut res = Vec::with_capacity(text.len());
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or(ExtendedValueParseError::InvalidPercentEncoding)?;
+            res.push(hex);
+            i += 3;
+        } else {
+            res.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(res)
+}
+
+/// parse_params parses header value followed by parameters, like `attachment; filename="a.txt"`.
+/// Values may be tokens or quoted strings. Parameters are not RFC 2231 decoded.
+pub fn parse_params(text: &str) -> Result<(&str, RawParams<'_>), ParamsParseError> {
+    let (value, mut rest) = match text.find(';') {
+        Some(idx) => (text[..idx].trim(), &text[idx + 1..]),
+        None => (text.trim(), ""),
+    };
+    if value.is_empty() {
+        return Err(ParamsParseError::EmptyValue(ErrorPosition::new(text, 0, "value")));
+    }
+    let position = |rest: &str, offset: usize, expected| ErrorPosition::new(
+        text,
+        offset_in(text.as_bytes(), rest.as_bytes()) + offset,
+        expected,
+    );
+
+    let mut params = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c| is_white_space(c) || c == ';' || c == '\r' || c == '\n');
+        if rest.is_empty() {
+            break;
+        }
+        let idx = rest.find('=')
+            .ok_or_else(|| ParamsParseError::InvalidParam(position(rest, rest.len(), "`=`")))?;
+        let name = rest[..idx].trim();
+        if name.is_empty() || name.contains(|c| is_white_space(c) || c == ';') {
+            return Err(ParamsParseError::InvalidParam(position(rest, 0, "parameter name")));
+        }
+        rest = rest[idx + 1..].trim_start();
+        if rest.starts_with('"') {
+            let end = find_quoted_string_end(rest).ok_or_else(|| {
+                QuotedStringError::LastCharacterIsNotQuote(position(rest, rest.len(), "closing `\"`"))
+            })?;
+            let value = unquote_string(&rest[..end], true)
+                .map_err(|e| e.shift(offset_in(text.as_bytes(), rest.as_bytes())))?;
+            params.push((name, Cow::Owned(value)));
+            rest = &rest[end..];
+        } else {
+            let end = rest.find(';').unwrap_or(rest.len());
+            params.push((name, Cow::Borrowed(rest[..end].trim_end())));
+            rest = &rest[end..];
+        }
+    }
+    Ok((value, params))
+}
+
+/// find_quoted_string_end returns offset right after closing quote of quoted string at the beginning of text
+fn find_quoted_string_end(text: &str) -> Option<usize> {
+    let mut is_escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if is_escaped => is_escaped = false,
+            '\\' => is_escaped = true,
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+struct Section<'a> {
+    number: u32,
+    is_extended: bool,
+    value: &'a str,
+}
+
+/// SectionGroup is base name, lower case base name and sections of single parameter
+type SectionGroup<'a> = (&'a str, String, Vec<Section<'a>>);
+
+/// decode_rfc_2231_params reassembles parameters split into sections and decodes extended ones. See RFC 2231
+///
+/// For instance `filename*0*=utf-8''%E2%82%AC; filename*1=.txt` becomes `filename=€.txt`.
+/// When both regular and extended value is present, extended one is used.
+/// Parameters which can't be decoded are left as they are.
+///
+/// # Example
+/// ```rust
+///# use std::borrow::Cow;
+///# use smtpc::mail::header::decode_rfc_2231_params;
+///let params = decode_rfc_2231_params(vec![
+///    (Cow::Borrowed("filename*0*"), Cow::Borrowed("utf-8''%E2%82%AC%20rates")),
+///    (Cow::Borrowed("filename*1"), Cow::Borrowed(".txt")),
+///]);
+///assert_eq!(params["filename"], vec![Cow::Borrowed("€ rates.txt")]);
+/// ```
+pub fn decode_rfc_2231_params<'a>(params: impl IntoIterator<Item=(Cow<'a, str>, Cow<'a, str>)>) -> Params<'a> {
+    let params: Vec<_> = params.into_iter().collect();
+    // sections are grouped by lower case name, first seen name is used as key
+    let mut groups: Vec<SectionGroup<'_>> = Vec::new();
+    for (name, value) in params.iter() {
+        if let Some((base, section)) = parse_section_name(name, value) {
+            let key = base.to_ascii_lowercase();
+            match groups.iter_mut().find(|(_, k, _)| *k == key) {
+                Some((_, _, sections)) => sections.push(section),
+                None => groups.push((base, key, vec![section])),
+            }
+        }
+    }
+
+    let mut res: Params<'a> = HashMap::new();
+    let mut decoded_keys = Vec::new();
+    for (base, key, mut sections) in groups {
+        sections.sort_by_key(|s| s.number);
+        // parameters which can't be decoded are left as they were
+        if let Some(value) = decode_sections(&sections) {
+            res.insert(Cow::Owned(base.to_string()), vec![Cow::Owned(value)]);
+            decoded_keys.push(key);
+        }
+    }
+    for (name, value) in params.iter() {
+        let base = parse_section_name(name, value).map_or(name.as_ref(), |(base, _)| base);
+        if decoded_keys.iter().any(|k| k.eq_ignore_ascii_case(base)) {
+            continue;
+        }
+        res.entry(name.clone()).or_default().push(value.clone());
+    }
+    res
+}
+
+/// parse_section_name splits name like `filename*1*` into base name and section.
+/// `None` is returned for regular parameters.
+fn parse_section_name<'b>(name: &'b str, value: &'b str) -> Option<(&'b str, Section<'b>)> {
+    let (base, is_extended) = match name.strip_suffix('*') {
+        Some(base) => (base, true),
+        None => (name, false),
+    };
+    let (base, number) = match base.rfind('*') {
+        Some(idx) if idx > 0 && !base[idx + 1..].starts_with('+') => match base[idx + 1..].parse::<u32>() {
+            Ok(number) => (&base[..idx], Some(number)),
+            Err(_) => (base, None),
+        },
+        _ => (base, None),
+    };
+    if base.is_empty() || (!is_extended && number.is_none()) {
+        return None;
+    }
+    Some((base, Section {
+        number: number.unwrap_or(0),
+        is_extended,
+        value,
+    }))
+}
+
+/// decode_sections joins sorted sections of single parameter.
+/// Charset is taken from the first section, so multibyte chars may be split between sections.
+/// Sections have to be numbered from 0 without gaps, otherwise `None` is returned. See RFC 2231 section 3
+fn decode_sections(sections: &[Section<'_>]) -> Option<String> {
+    let mut charset = None;
+    let mut bytes = Vec::new();
+    for (i, section) in sections.iter().enumerate() {
+        if section.number != i as u32 {
+            return None;
+        }
+        if i == 0 && section.is_extended {
+            let mut parts = section.value.splitn(3, '\'');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(c), Some(_), Some(value)) => {
+                    charset = Some(c);
+                    bytes.extend(percent_decode(value).ok()?);
+                }
+                _ => return None,
+            }
+        } else if section.is_extended {
+            bytes.extend(percent_decode(section.value).ok()?);
+        } else {
+            bytes.extend_from_slice(section.value.as_bytes());
+        }
+    }
+    Some(decode_with_label(charset.unwrap_or("utf-8"), &bytes).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_parse_params() {
+        for (i, o) in [
+            ("attachment", Some(("attachment", vec![]))),
+            ("attachment;", Some(("attachment", vec![]))),
+            (
+                "attachment; filename=\"a \\\"b\\\".txt\"; size=100",
+                Some(("attachment", vec![("filename", "a \"b\".txt"), ("size", "100")])),
+            ),
+            (
+                "inline ;\r\n filename = a.txt ; x=\"a;b\"",
+                Some(("inline", vec![("filename", "a.txt"), ("x", "a;b")])),
+            ),
+            ("", None),
+            ("attachment; filename", None),
+            ("attachment; filename=\"a", None),
+        ].iter() {
+            let res = parse_params(i).ok();
+            let res = res.as_ref().map(|(v, p)| (*v, p.iter().map(|(n, v)| (*n, v.as_ref())).collect::<Vec<_>>()));
+            assert_eq!(res, o.clone());
+        }
+    }
+
+    #[test]
+    fn test_can_parse_extended_value() {
+        assert_eq!(ExtendedValue::parse("utf-8'en'%E2%82%AC%20rates").unwrap(), ExtendedValue {
+            charset: "utf-8".into(),
+            language: Some("en".into()),
+            value: "€ rates".into(),
+        });
+        assert_eq!(ExtendedValue::parse("iso-8859-1''%E9t%E9").unwrap().value, "été");
+        assert_eq!(ExtendedValue::parse("koi8-r''%F0%D2%C9%D7%C5%D4").unwrap().value, "Привет");
+        assert_eq!(ExtendedValue::parse("%E9t%E9"), Err(ExtendedValueParseError::MissingCharset));
+        assert_eq!(ExtendedValue::parse("utf-8''%E"), Err(ExtendedValueParseError::InvalidPercentEncoding));
+    }
+
+    #[test]
+    fn test_can_decode_rfc_2231_params() {
+        for (i, o) in [
+            (vec![("name", "a.txt")], vec![("name", "a.txt")]),
+            (vec![("title*", "us-ascii'en-us'This%20is%20%2A%2A%2Afun%2A%2A%2A")], vec![("title", "This is ***fun***")]),
+            (
+                vec![("URL*1", "cms/this/is/it"), ("URL*0", "ftp://"), ("charset", "utf-8")],
+                vec![("URL", "ftp://cms/this/is/it"), ("charset", "utf-8")],
+            ),
+            (
+                vec![("filename*0*", "utf-8''%E2%82"), ("filename*1*", "%AC"), ("filename*2", ".txt")],
+                vec![("filename", "€.txt")],
+            ),
+            (
+                vec![("filename", "fallback.txt"), ("filename*", "utf-8''%C5%81%C3%B3d%C5%BA.txt")],
+                vec![("filename", "Łódź.txt")],
+            ),
+            (vec![("filename*", "%C5%81")], vec![("filename*", "%C5%81")]),
+            (
+                vec![("filename", "plain.txt"), ("filename*0", "a"), ("filename*2", "c")],
+                vec![("filename", "plain.txt"), ("filename*0", "a"), ("filename*2", "c")],
+            ),
+            (
+                vec![("filename*1*", "utf-8''%C5%81"), ("filename*2", ".txt")],
+                vec![("filename*1*", "utf-8''%C5%81"), ("filename*2", ".txt")],
+            ),
+        ].iter() {
+            let res = decode_rfc_2231_params(i.iter().map(|(n, v)| (Cow::Borrowed(*n), Cow::Borrowed(*v))));
+            let mut res: Vec<_> = res.iter()
+                .map(|(n, v)| (n.as_ref(), v.iter().map(|v| v.as_ref()).collect::<Vec<_>>().join(",")))
+                .collect();
+            res.sort();
+            let mut o: Vec<_> = o.iter().map(|(n, v)| (*n, v.to_string())).collect();
+            o.sort();
+            assert_eq!(res, o);
+        }
+    }
+
+    #[test]
+    fn test_params_errors_name_invalid_part() {
+        for (i, o) in [
+            ("; a=b", "value is empty: expected value at byte 0, found \"; a=b\""),
+            ("text/plain; charset", "invalid parameter: expected `=` at byte 19, found end of input"),
+            ("text/plain; a b=c", "invalid parameter: expected parameter name at byte 12, found \"a b=c\""),
+            ("a; b=\"c", "invalid parameter value: quoted string does not end with quote: expected closing `\"` at byte 7, found end of input"),
+            ("a; b=\"c\u{1}\"", "invalid parameter value: invalid character in quoted string: expected quoted text at byte 7, found \"\\u{1}\\\"\""),
+        ].iter() {
+            let err = parse_params(i).unwrap_err();
+            assert_eq!(err.to_string(), *o, "{:?}", i);
+        }
+    }
+}
@@ -1,21 +1,104 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::ops::Range;
 
+use crate::error::ErrorPosition;
 use crate::utils::cc::is_white_space;
 
+/// MailHeaderParseError is returned when headers section can't be split into headers.
+/// Positions are relative to the beginning of parsed text.
 #[derive(Debug, From)]
 pub enum MailHeaderParseError {
     FirstCharInvalid,
-    InvalidHeaderName,
-    InvalidHeaderContent,
-    InvalidHeaderValue,
+    InvalidHeaderName(ErrorPosition),
+    InvalidHeaderContent(ErrorPosition),
+    InvalidHeaderValue(ErrorPosition),
 }
 
+impl MailHeaderParseError {
+    /// position returns place in input where error was found if it's known
+    pub fn position(&self) -> Option<&ErrorPosition> {
+        match self {
+            MailHeaderParseError::FirstCharInvalid => None,
+            MailHeaderParseError::InvalidHeaderName(p) |
+            MailHeaderParseError::InvalidHeaderContent(p) |
+            MailHeaderParseError::InvalidHeaderValue(p) => Some(p),
+        }
+    }
+
+    /// shift moves position of error by given number of bytes. See `ErrorPosition::shift`
+    pub(crate) fn shift(self, offset: usize) -> Self {
+        match self {
+            MailHeaderParseError::FirstCharInvalid => MailHeaderParseError::FirstCharInvalid,
+            MailHeaderParseError::InvalidHeaderName(p) => MailHeaderParseError::InvalidHeaderName(p.shift(offset)),
+            MailHeaderParseError::InvalidHeaderContent(p) => MailHeaderParseError::InvalidHeaderContent(p.shift(offset)),
+            MailHeaderParseError::InvalidHeaderValue(p) => MailHeaderParseError::InvalidHeaderValue(p.shift(offset)),
+        }
+    }
+}
+
+impl fmt::Display for MailHeaderParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailHeaderParseError::FirstCharInvalid => write!(f, "first char of headers is invalid"),
+            MailHeaderParseError::InvalidHeaderName(p) => write!(f, "invalid header name: {}", p),
+            MailHeaderParseError::InvalidHeaderContent(p) => write!(f, "invalid header content: {}", p),
+            MailHeaderParseError::InvalidHeaderValue(p) => write!(f, "invalid header value: {}", p),
+        }
+    }
+}
+
+impl error::Error for MailHeaderParseError {}
+
 // SMTP headers can't be read in reader because they contain multiline syntax:
 // if line after given header starts with space then it's next line of previous header...
 
+/// UnfoldingMode selects how folded header values are joined into single line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum UnfoldingMode {
+    /// Spec removes line breaks only, white chars of continuation lines are preserved.
+    /// Both space and tab start continuation line. See RFC 5322 2.2.3
+    #[default]
+    Spec,
+
+    /// Legacy accepts only continuation lines starting with space.
+    /// White chars at the beginning of each line are removed, so `Test\r\n Test` becomes `TestTest`.
+    Legacy,
+}
+
+/// RawHeaderParseOptions configures parsing of `RawHeaderBag`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct RawHeaderParseOptions {
+    pub unfolding: UnfoldingMode,
+}
+
 struct MailHeadersParser<'a> {
-    text: &'a str,
+    text: &'a [u8],
+    source: &'a [u8],
+    // number of white chars trimmed from the end of source
+    trimmed_end: usize,
+    options: RawHeaderParseOptions,
+}
+
+#[inline]
+fn is_wsp(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+/// trim_start_bytes removes ASCII white chars from the beginning of data
+fn trim_start_bytes(data: &[u8]) -> &[u8] {
+    let n = data.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    &data[n..]
+}
+
+/// trim_end_bytes removes ASCII white chars from the end of data
+fn trim_end_bytes(data: &[u8]) -> &[u8] {
+    let n = data.iter().rev().take_while(|b| b.is_ascii_whitespace()).count();
+    &data[..data.len() - n]
 }
 
 fn is_char_valid_name_char(c: char) -> bool {
@@ -41,15 +124,13 @@ fn canonicalize_header_name(text: &str) -> Result<Cow<str>, ()> {
 
     let mut do_upper_case = true;
     let mut res = Cow::Borrowed(text);
+    // all chars are ASCII so char index is byte index
     for (i, c) in text.chars().enumerate() {
         if c.is_ascii_alphabetic() { // is upper/lower case makes any sense for given char
             if do_upper_case && c.is_ascii_lowercase() {
-                let data = res.to_mut();
-                data[i..=i].to_ascii_uppercase();
-                // do_upper_case = false;
+                res.to_mut()[i..=i].make_ascii_uppercase();
             } else if !do_upper_case && c.is_ascii_uppercase() {
-                let data = res.to_mut();
-                data[i..=i].to_ascii_lowercase();
+                res.to_mut()[i..=i].make_ascii_lowercase();
             }
         }
         do_upper_case = c == '-';
@@ -59,14 +140,29 @@ fn canonicalize_header_name(text: &str) -> Result<Cow<str>, ()> {
 }
 
 impl<'a> MailHeadersParser<'a> {
-    pub fn new(text: &'a str) -> Self {
+    /// new creates parser of given text. White chars around it are skipped.
+    pub fn new(text: &'a [u8], options: RawHeaderParseOptions) -> Self {
         Self {
-            text,
+            text: trim_start_bytes(trim_end_bytes(text)),
+            source: text,
+            trimmed_end: text.len() - trim_end_bytes(text).len(),
+            options,
         }
     }
 
+    /// position returns byte offset of parser in source text
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.source.len() - self.trimmed_end - self.text.len()
+    }
+
+    /// error_position returns position of error at given offset in source text
+    fn error_position(&self, offset: usize, expected: &'static str) -> ErrorPosition {
+        ErrorPosition::from_bytes(self.source, offset, expected)
+    }
+
     pub fn take_white_chars(&mut self) {
-        self.text = self.text.trim_start();
+        self.text = trim_start_bytes(self.text);
     }
 
     /*
@@ -75,10 +171,10 @@ impl<'a> MailHeadersParser<'a> {
     }
     */
 
-    pub fn consume(&mut self, c: char) -> bool {
-        if let Some(d) = self.peek_char() {
+    pub fn consume(&mut self, c: u8) -> bool {
+        if let Some(d) = self.peek_byte() {
             if d == c {
-                self.take_char().expect("Take char can't fail. Peek succeed");
+                self.take_byte().expect("Take byte can't fail. Peek succeed");
                 true
             } else {
                 false
@@ -112,94 +208,111 @@ impl<'a> MailHeadersParser<'a> {
         self.text.is_empty()
     }
 
-    pub fn peek_char(&mut self) -> Option<char> {
-        self.text.chars().nth(0)
+    pub fn peek_byte(&mut self) -> Option<u8> {
+        self.text.first().cloned()
     }
 
-    pub fn take_char(&mut self) -> Option<char> {
-        if let Some(c) = self.peek_char() {
-            self.text = &self.text[c.len_utf8()..];
-            Some(c)
+    pub fn take_byte(&mut self) -> Option<u8> {
+        if let Some(b) = self.peek_byte() {
+            self.text = &self.text[1..];
+            Some(b)
         } else {
             None
         }
     }
 
     /// take_header_name reads header name(AKA key)
-    /// Returned header name is canonical.
-    pub fn take_header_name(&mut self) -> Result<Cow<'a, str>, MailHeaderParseError> {
+    /// Returned header name is not canonicalized.
+    pub fn take_header_name(&mut self) -> Result<&'a str, MailHeaderParseError> {
         let mut byte_offset = 0;
         let text = self.text;
+        let start = self.position();
+        let source = self.source;
+        let error = |offset: usize, expected| MailHeaderParseError::InvalidHeaderName(
+            ErrorPosition::from_bytes(source, start + offset, expected)
+        );
         loop {
-            let c = match self.take_char() {
-                Some(c) => c,
+            let c = match self.take_byte() {
+                Some(b) => b as char,
                 None => {
-                    return Err(MailHeaderParseError::InvalidHeaderName);
+                    return Err(error(byte_offset, "`:`"));
                 }
             };
             if byte_offset == 0 && is_white_space(c) {
-                return Err(MailHeaderParseError::InvalidHeaderName);
+                return Err(error(byte_offset, "header name"));
             }
 
             if c == ':' {
                 break;
             }
             if !is_char_valid_name_char(c) {
-                return Err(MailHeaderParseError::InvalidHeaderName);
+                return Err(error(byte_offset, "header name char or `:`"));
             }
-            byte_offset += c.len_utf8();
+            byte_offset += 1;
         }
-        let text = text[..byte_offset].trim_start_matches(|c| is_white_space(c));
+        // name chars are ASCII so it's valid utf-8
+        let text = std::str::from_utf8(&text[..byte_offset])
+            .map_err(|_| error(0, "header name"))?;
         if text.is_empty() {
-            return Err(MailHeaderParseError::InvalidHeaderName);
+            return Err(error(0, "header name"));
         }
-        Ok(canonicalize_header_name(text).map_err(|_| MailHeaderParseError::InvalidHeaderName)?)
+        Ok(text)
     }
 
-    pub fn take_header_value(&mut self) -> Result<Cow<'a, str>, MailHeaderParseError> {
-        self.take_white_chars();
-
+    /// take_line reads text up to the end of line. Line terminator is consumed but not returned.
+    pub fn take_line(&mut self) -> &'a [u8] {
         // use following new line detection strategy:
         // 1. Find \n
         // 2. If char before it \r then rm it
-        let mut byte_offset = 0;
-        let text = self.text;
-        loop {
-            let c = match self.take_char() {
-                Some(c) => c,
-                None => {
-                    // eof is fine.
-                    break;
-                    // return Err(MailHeaderParseError::InvalidHeaderValue);
-                }
-            };
-            if c == '\n' {
-                break;
-            }
-            byte_offset += c.len_utf8();
-        }
-        if byte_offset == 0 {
-            return Err(MailHeaderParseError::InvalidHeaderContent);
-        }
-        let text = &text[..byte_offset];
-        // use as bytes to not violate utf8 char boundary
-        let text = if text.as_bytes()[text.as_bytes().len() - 1] == b'\r' {
-            &text[..text.len() - 1]
-        } else {
-            text
+        let (line, rest) = match self.text.iter().position(|b| *b == b'\n') {
+            Some(i) => (&self.text[..i], &self.text[i + 1..]),
+            // eof is fine.
+            None => (self.text, &[][..]),
         };
+        self.text = rest;
+        match line.split_last() {
+            Some((b'\r', line)) => line,
+            _ => line,
+        }
+    }
 
+    /// take_header_value reads single line of header value with leading white chars skipped.
+    /// It's used by legacy unfolding.
+    pub fn take_header_value(&mut self) -> Result<Cow<'a, [u8]>, MailHeaderParseError> {
+        self.take_white_chars();
+        let start = self.position();
+        let text = self.take_line();
+        if text.is_empty() {
+            return Err(MailHeaderParseError::InvalidHeaderContent(self.error_position(start, "header value")));
+        }
         Ok(Cow::Borrowed(text))
     }
 
-    pub fn take_header_name_and_value(&mut self) -> Result<(Cow<'a, str>, Cow<'a, str>), MailHeaderParseError> {
-        let name = self.take_header_name()?;
+    /// take_unfolded_value reads header value along with it's continuation lines.
+    /// Only line breaks are removed, white chars are preserved. See RFC 5322 2.2.3
+    pub fn take_unfolded_value(&mut self) -> Cow<'a, [u8]> {
+        let trim_wsp = |line: &'a [u8]| &line[line.iter().take_while(|b| is_wsp(**b)).count()..];
+        let mut value = Cow::Borrowed(trim_wsp(self.take_line()));
+        while self.peek_byte().is_some_and(is_wsp) {
+            let line = self.take_line();
+            if value.is_empty() {
+                // value may start in continuation line
+                value = Cow::Borrowed(trim_wsp(line));
+            } else {
+                value.to_mut().extend_from_slice(line);
+            }
+        }
+        value
+    }
+
+    /// take_legacy_unfolded_value reads header value along with continuation lines starting with space.
+    /// White chars at the beginning of lines are removed, so lines are glued together.
+    pub fn take_legacy_unfolded_value(&mut self) -> Result<Cow<'a, [u8]>, MailHeaderParseError> {
         let mut values = vec![self.take_header_value()?];
         loop {
             // take header value took newline. no check here.
-            if self.consume(' ') {
+            if self.consume(b' ') {
                 let rd_value = self.take_header_value()?;
-                // TODO(teawithsand) how join multiline headers? With space char? No char?
                 values.push(rd_value);
             } else {
                 break;
@@ -207,19 +320,93 @@ impl<'a> MailHeadersParser<'a> {
         }
 
         if values.len() == 1 {
-            Ok((name, values.remove(0)))
+            Ok(values.remove(0))
         } else {
-            Ok((name, Cow::Owned(values.join(""))))
+            Ok(Cow::Owned(values.concat()))
+        }
+    }
+
+    /// take_header reads header name and value along with span of source it was read from.
+    pub fn take_header(&mut self) -> Result<RawHeader<'a>, MailHeaderParseError> {
+        let start = self.position();
+        let original_name = self.take_header_name()?;
+        let name = canonicalize_header_name(original_name)
+            .map_err(|_| MailHeaderParseError::InvalidHeaderName(self.error_position(start, "header name")))?;
+        let value = match self.options.unfolding {
+            UnfoldingMode::Spec => self.take_unfolded_value(),
+            UnfoldingMode::Legacy => self.take_legacy_unfolded_value()?,
+        };
+
+        // trailing white chars and line terminator of last header are not part of parsed text,
+        // so they are looked up in source
+        let mut end = self.position();
+        if self.is_empty() {
+            end += self.source[end..].iter().take_while(|b| is_wsp(**b)).count();
+            let rest = &self.source[end..];
+            if rest.starts_with(b"\r\n") {
+                end += 2;
+            } else if rest.starts_with(b"\n") {
+                end += 1;
+            }
+        }
+        Ok(RawHeader {
+            name,
+            original_name: Cow::Borrowed(original_name),
+            value,
+            raw: Cow::Borrowed(&self.source[start..end]),
+            offset: start,
+        })
+    }
+}
+
+/// RawHeader is single header as it was found in message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct RawHeader<'a> {
+    /// name is canonical name of header
+    pub name: Cow<'a, str>,
+    /// original_name is name of header with casing it had in message
+    pub original_name: Cow<'a, str>,
+    /// value is unfolded value of header.
+    /// It's not decoded since headers may contain 8-bit text in any charset or invalid utf-8.
+    pub value: Cow<'a, [u8]>,
+    /// raw is exact bytes of header in message including name, folded lines and line terminator
+    pub raw: Cow<'a, [u8]>,
+    /// offset is byte offset of raw header in parsed text.
+    /// Headers of `MimePart` have offsets relative to the beginning of message.
+    pub offset: usize,
+}
+
+impl<'a> RawHeader<'a> {
+    /// span returns range of bytes of parsed text occupied by this header
+    #[inline]
+    pub fn span(&self) -> Range<usize> {
+        self.offset..self.offset + self.raw.len()
+    }
+
+    /// into_owned makes `RawHeader` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> RawHeader<'static> {
+        RawHeader {
+            name: Cow::Owned(self.name.into_owned()),
+            original_name: Cow::Owned(self.original_name.into_owned()),
+            value: Cow::Owned(self.value.into_owned()),
+            raw: Cow::Owned(self.raw.into_owned()),
+            offset: self.offset,
         }
     }
 }
 
 
+/// RawHeaderMap maps header names to their values in order they were found in message
+pub type RawHeaderMap<'a> = HashMap<Cow<'a, str>, Vec<Cow<'a, [u8]>>>;
+
+/// RawHeaderBag is list of headers in order they were found in message.
+/// Map of header names to their values is derived from it for lookups.
 #[derive(Debug, Clone, PartialEq, Eq)]
-#[derive(From, Into)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct RawHeaderBag<'a> {
-    container: HashMap<Cow<'a, str>, Vec<Cow<'a, str>>>,
+    headers: Vec<RawHeader<'a>>,
+    container: RawHeaderMap<'a>,
 }
 
 impl<'a> Default for RawHeaderBag<'a> {
@@ -228,31 +415,99 @@ impl<'a> Default for RawHeaderBag<'a> {
     }
 }
 
+impl<'a> From<Vec<RawHeader<'a>>> for RawHeaderBag<'a> {
+    fn from(headers: Vec<RawHeader<'a>>) -> Self {
+        let mut container: RawHeaderMap<'a> = HashMap::new();
+        for h in headers.iter() {
+            container.entry(h.name.clone()).or_default().push(h.value.clone());
+        }
+        Self {
+            headers,
+            container,
+        }
+    }
+}
+
+impl<'a> From<RawHeaderMap<'a>> for RawHeaderBag<'a> {
+    /// from creates bag from map of headers.
+    /// Map has no order, so headers are sorted by name and `raw` is formatted as `Name: value\r\n`.
+    /// Offsets are given as if headers were written one after another.
+    fn from(container: RawHeaderMap<'a>) -> Self {
+        let mut names: Vec<_> = container.keys().cloned().collect();
+        names.sort();
+        let mut headers = Vec::new();
+        let mut offset = 0;
+        for name in names {
+            for value in container[&name].iter() {
+                let mut raw = Vec::with_capacity(name.len() + value.len() + 4);
+                raw.extend_from_slice(name.as_bytes());
+                raw.extend_from_slice(b": ");
+                raw.extend_from_slice(value);
+                raw.extend_from_slice(b"\r\n");
+                headers.push(RawHeader {
+                    name: name.clone(),
+                    original_name: name.clone(),
+                    value: value.clone(),
+                    offset,
+                    raw: Cow::Owned(raw),
+                });
+                offset += headers.last().unwrap().raw.len();
+            }
+        }
+        Self {
+            headers,
+            container,
+        }
+    }
+}
+
+impl<'a> From<RawHeaderBag<'a>> for RawHeaderMap<'a> {
+    fn from(bag: RawHeaderBag<'a>) -> Self {
+        bag.into_inner()
+    }
+}
+
 impl<'a> RawHeaderBag<'a> {
     #[inline]
-    pub fn container(&self) -> &HashMap<Cow<'a, str>, Vec<Cow<'a, str>>> {
+    pub fn container(&self) -> &RawHeaderMap<'a> {
         &self.container
     }
 
     #[inline]
-    pub fn into_inner(self) -> HashMap<Cow<'a, str>, Vec<Cow<'a, str>>> {
+    pub fn into_inner(self) -> RawHeaderMap<'a> {
         self.container
     }
 
-    /// parse parses given text and crates header bag from it
-    pub fn parse(text: &'a str) -> Result<Self, MailHeaderParseError> {
-        let text = text.trim();
-        let mut parser = MailHeadersParser::new(text);
+    /// headers returns all headers in order they were found in message
+    #[inline]
+    pub fn headers(&self) -> &[RawHeader<'a>] {
+        &self.headers
+    }
+
+    #[inline]
+    pub fn into_headers(self) -> Vec<RawHeader<'a>> {
+        self.headers
+    }
+
+    /// parse parses given text and crates header bag from it using default options.
+    /// Offsets of headers are relative to the beginning of given text.
+    #[inline]
+    pub fn parse<T: AsRef<[u8]> + ?Sized>(text: &'a T) -> Result<Self, MailHeaderParseError> {
+        Self::parse_with_options(text, RawHeaderParseOptions::default())
+    }
+
+    /// parse_with_options parses given text and crates header bag from it.
+    /// Offsets of headers are relative to the beginning of given text.
+    pub fn parse_with_options<T: AsRef<[u8]> + ?Sized>(text: &'a T, options: RawHeaderParseOptions) -> Result<Self, MailHeaderParseError> {
+        let mut parser = MailHeadersParser::new(text.as_ref(), options);
         if parser.is_empty() {
-            return Ok(Self {
-                container: HashMap::new(),
-            });
+            return Ok(Self::new());
         }
 
-        let mut res: HashMap<Cow<'a, str>, Vec<Cow<'a, str>>> = HashMap::new();
-        match parser.peek_char() {
-            None | Some(' ') => {
-                return Err(MailHeaderParseError::InvalidHeaderValue);
+        let mut res = Vec::new();
+        match parser.peek_byte() {
+            None | Some(b' ') => {
+                return Err(MailHeaderParseError::InvalidHeaderValue(parser.error_position(parser.position(), "header name")));
             }
             Some(_) => {}
         };
@@ -260,25 +515,30 @@ impl<'a> RawHeaderBag<'a> {
             if parser.is_empty() {
                 break;
             }
-            let (key, value) = parser.take_header_name_and_value()?;
-            // looks like borrow checker is too stupid to use HashMap.entry().
-            // Value has to be moved in one place and in the other one at the same time.
-            if res.contains_key(&key) {
-                res.get_mut(&key).unwrap().push(value);
-            } else {
-                res.insert(key, vec![value]);
-            }
+            res.push(parser.take_header()?);
         }
-        Ok(Self {
-            container: res,
-        })
+        Ok(Self::from(res))
     }
 
     pub fn new() -> Self {
         Self {
+            headers: Vec::new(),
             container: HashMap::new(),
         }
     }
+
+    /// shift moves offsets of all headers by given number of bytes.
+    /// It's used when headers were parsed from part of bigger text.
+    pub(crate) fn shift(&mut self, offset: usize) {
+        for h in self.headers.iter_mut() {
+            h.offset += offset;
+        }
+    }
+
+    /// into_owned makes `RawHeaderBag` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> RawHeaderBag<'static> {
+        RawHeaderBag::from(self.headers.into_iter().map(RawHeader::into_owned).collect::<Vec<_>>())
+    }
 }
 
 #[cfg(test)]
@@ -349,12 +609,114 @@ mod test {
                 })
             ),
         ].iter().cloned() {
+            let options = RawHeaderParseOptions {
+                unfolding: UnfoldingMode::Legacy,
+            };
             if let Some(o) = o {
-                let c: HashMap<_, _> = RawHeaderBag::parse(i).unwrap().into();
+                let c: HashMap<_, _> = RawHeaderBag::parse_with_options(i, options).unwrap().into();
+                let c: HashMap<_, Vec<_>> = c.into_iter()
+                    .map(|(k, v)| (k, v.into_iter().map(|v| Cow::Owned(String::from_utf8(v.into_owned()).unwrap())).collect()))
+                    .collect();
                 assert_eq!(c, o);
             } else {
-                RawHeaderBag::parse(i).unwrap_err();
+                RawHeaderBag::parse_with_options(i, options).unwrap_err();
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_can_unfold_headers() {
+        for (i, o) in [
+            ("Subject: Test", Some(vec!["Test"])),
+            ("Subject: Test\r\n Test", Some(vec!["Test Test"])),
+            ("Subject: Test\r\n\tTest", Some(vec!["Test\tTest"])),
+            ("Subject: Test  \r\n   Test\r\nSubject: asdf", Some(vec!["Test     Test", "asdf"])),
+            ("Subject: Test\n Test\n  Test\nSubject: asdf", Some(vec!["Test Test  Test", "asdf"])),
+            ("Subject:\r\n Test", Some(vec!["Test"])),
+            ("Subject:\r\nSubject: Test", Some(vec!["", "Test"])),
+            ("Subject: Test\r\nNot a header", None),
+        ].iter().cloned() {
+            let res = RawHeaderBag::parse(i).ok()
+                .map(|b| b.container().get("Subject").cloned().unwrap_or_default());
+            assert_eq!(res, o.map(|o| o.into_iter().map(|v| Cow::Borrowed(v.as_bytes())).collect()));
+        }
+    }
+
+    #[test]
+    fn test_headers_keep_order_and_source() {
+        let text = "\r\nTo: a@example.com\r\nsubject: Test\r\n Test\r\nTO: b@example.com\nX-Last: end\r\n\r\n";
+        let bag = RawHeaderBag::parse(text).unwrap();
+        let headers: Vec<_> = bag.headers().iter()
+            .map(|h| (h.original_name.as_ref(), h.value.as_ref(), h.raw.as_ref(), h.offset))
+            .collect();
+        assert_eq!(headers, vec![
+            ("To", &b"a@example.com"[..], &b"To: a@example.com\r\n"[..], 2),
+            ("subject", b"Test Test", b"subject: Test\r\n Test\r\n", 21),
+            ("TO", b"b@example.com", b"TO: b@example.com\n", 43),
+            ("X-Last", b"end", b"X-Last: end\r\n", 61),
+        ]);
+        for h in bag.headers() {
+            assert_eq!(&text.as_bytes()[h.span()], h.raw.as_ref());
+        }
+        assert_eq!(bag.container()["To"], vec![Cow::Borrowed(&b"a@example.com"[..]), Cow::Borrowed(&b"b@example.com"[..])]);
+        assert_eq!(bag.headers()[1].name, "Subject");
+
+        let owned = bag.clone().into_owned();
+        assert_eq!(owned.headers(), bag.headers());
+        assert_eq!(owned.container(), bag.container());
+    }
+
+    #[test]
+    fn test_last_header_span_without_line_terminator() {
+        for (i, o) in [
+            ("Subject: Test", "Subject: Test"),
+            ("Subject: Test  ", "Subject: Test  "),
+            ("Subject: Test \t\r\n\r\n", "Subject: Test \t\r\n"),
+            ("Subject: Test\n", "Subject: Test\n"),
+            ("Subject: Test\r\n", "Subject: Test\r\n"),
+        ].iter() {
+            let bag = RawHeaderBag::parse(i).unwrap();
+            assert_eq!(bag.headers()[0].raw.as_ref(), o.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_can_create_bag_from_map() {
+        let bag = RawHeaderBag::from(map! {
+            Cow::Borrowed("To") => vec![Cow::Borrowed(&b"a@example.com"[..])],
+            Cow::Borrowed("Subject") => vec![Cow::Borrowed(&b"a"[..]), Cow::Borrowed(&b"b"[..])]
+        });
+        let headers: Vec<_> = bag.headers().iter()
+            .map(|h| (h.name.as_ref(), h.raw.as_ref(), h.offset))
+            .collect();
+        assert_eq!(headers, vec![
+            ("Subject", &b"Subject: a\r\n"[..], 0),
+            ("Subject", b"Subject: b\r\n", 12),
+            ("To", b"To: a@example.com\r\n", 24),
+        ]);
+        assert_eq!(bag.container()["Subject"].len(), 2);
+    }
+
+    #[test]
+    fn test_can_parse_8bit_headers() {
+        let text = &b"Subject: Za\xbf\xf3\xb3\xe6\r\nFrom: \xff\xfe <a@example.com>\r\n"[..];
+        let bag = RawHeaderBag::parse(text).unwrap();
+        assert_eq!(bag.container()["Subject"], vec![Cow::Borrowed(&b"Za\xbf\xf3\xb3\xe6"[..])]);
+        assert_eq!(bag.container()["From"], vec![Cow::Borrowed(&b"\xff\xfe <a@example.com>"[..])]);
+    }
+
+    #[test]
+    fn test_header_section_errors_name_invalid_header() {
+        for (i, options, o) in [
+            ("Subject: a\r\nTo b\r\n", RawHeaderParseOptions::default(), "invalid header name: expected header name char or `:` at byte 14, found \" b\\r\\n\""),
+            ("Subject: a\r\n: b\r\n", RawHeaderParseOptions::default(), "invalid header name: expected header name at byte 12, found \": b\\r\\n\""),
+            ("Subject: a\r\nTo", RawHeaderParseOptions::default(), "invalid header name: expected `:` at byte 14, found end of input"),
+            ("Subject: a\r\nX:", RawHeaderParseOptions {
+                unfolding: UnfoldingMode::Legacy,
+            }, "invalid header content: expected header value at byte 14, found end of input"),
+        ].iter() {
+            let err = RawHeaderBag::parse_with_options(*i, *options).unwrap_err();
+            assert_eq!(err.to_string(), *o, "{:?}", i);
+        }
+    }
+}
@@ -64,6 +64,7 @@ pub struct MailHeaderReader<R> {
     is_in_mail: bool,
     is_unexpected_eof: bool,
     is_finished: bool,
+    is_terminated: bool,
 
     rd_buf: [u8; 5],
     rd_buf_sz: u8,
@@ -78,6 +79,7 @@ impl<R> MailHeaderReader<R> {
             is_in_mail: in_mail,
             is_unexpected_eof: false,
             is_finished: false,
+            is_terminated: false,
             rd_buf: [0u8; 5],
             rd_buf_sz: 0,
             bd: BoundaryDetector::new(Cow::Borrowed(b"\r\n\r\n")),
@@ -87,6 +89,18 @@ impl<R> MailHeaderReader<R> {
     pub fn is_done(&self) -> bool {
         self.is_finished
     }
+
+    /// is_terminated checks if headers were terminated with `\r\n\r\n` sequence rather than EOF
+    pub fn is_terminated(&self) -> bool {
+        self.is_terminated
+    }
+
+    /// into_inner returns underlying reader.
+    /// Once reader is done it's positioned right after `\r\n\r\n` sequence.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 }
 
 impl<R> Read for MailHeaderReader<R> where R: Read {
@@ -134,6 +148,7 @@ impl<R> Read for MailHeaderReader<R> where R: Read {
                 BoundaryDetectorResult::MatchBegin => {}
                 BoundaryDetectorResult::MatchDone => {
                     self.is_finished = true;
+                    self.is_terminated = true;
                     break;
                 }
                 BoundaryDetectorResult::MatchBroke(v) => {
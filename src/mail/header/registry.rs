@@ -0,0 +1,168 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use crate::mail::header::bag::{MailHeaderParseError, ParsedMailHeader};
+use crate::mail::header::typed::parse_builtin_header;
+use crate::mail::header::Header;
+
+/// HeaderParseFn parses value of header
+pub type HeaderParseFn = for<'a> fn(&'a str) -> Result<ParsedMailHeader<'a>, MailHeaderParseError>;
+
+/// AnyHeader is object safe part of `Header` used to keep custom headers in `ParsedMailHeader`
+trait AnyHeader: Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn to_raw_value(&self) -> String;
+}
+
+impl<H> AnyHeader for H
+    where H: for<'a> Header<'a> + Debug + Send + Sync + 'static
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn to_raw_value(&self) -> String {
+        Header::to_raw_value(self)
+    }
+}
+
+/// CustomHeader is header parsed by type registered in `HeaderRegistry`.
+/// It keeps both parsed value and value it was parsed from.
+#[derive(Clone)]
+pub struct CustomHeader<'a> {
+    value: Cow<'a, str>,
+    parsed: Arc<dyn AnyHeader>,
+}
+
+impl<'a> CustomHeader<'a> {
+    pub fn new<H>(value: Cow<'a, str>, parsed: H) -> Self
+        where H: for<'b> Header<'b> + Debug + Send + Sync + 'static
+    {
+        Self {
+            value,
+            parsed: Arc::new(parsed),
+        }
+    }
+
+    /// value returns value of header this one was parsed from
+    #[inline]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// downcast_ref returns parsed header if it's of type `H`
+    pub fn downcast_ref<H: Any>(&self) -> Option<&H> {
+        self.parsed.as_any().downcast_ref()
+    }
+
+    /// to_raw_value renders parsed header. See `Header::to_raw_value`
+    pub fn to_raw_value(&self) -> String {
+        self.parsed.to_raw_value()
+    }
+
+    /// into_owned makes `CustomHeader` of arbitrary lifetime `'static`
+    pub fn into_owned(self) -> CustomHeader<'static> {
+        CustomHeader {
+            value: Cow::Owned(self.value.into_owned()),
+            parsed: self.parsed,
+        }
+    }
+}
+
+impl<'a> Debug for CustomHeader<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomHeader").field(&self.parsed).finish()
+    }
+}
+
+/// Custom headers are equal when they are of the same type and were parsed from the same value
+impl<'a> PartialEq for CustomHeader<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.parsed.as_any().type_id() == other.parsed.as_any().type_id()
+    }
+}
+
+impl<'a> Eq for CustomHeader<'a> {}
+
+fn parse_custom<'a, H>(value: &'a str) -> Result<ParsedMailHeader<'a>, MailHeaderParseError>
+    where H: for<'b> Header<'b> + Debug + Send + Sync + 'static
+{
+    let parsed = H::parse(value)?;
+    Ok(ParsedMailHeader::Custom(CustomHeader::new(Cow::Borrowed(value), parsed)))
+}
+
+/// HeaderRegistry selects how headers are parsed by name.
+/// Headers which were not registered are parsed with `ParsedMailHeader::parse`, so registered ones may override builtin ones.
+/// When registered parser fails on header known by this crate, the builtin parser is used instead.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::header::{Header, HeaderRegistry, MailHeaderParseError, ParsedHeaderBag, ParsedMailHeader, RawHeaderBag};
+///# use smtpc::encoding::charset::Charset;
+///#[derive(Debug)]
+///struct TenantID(String);
+///
+///impl<'a> Header<'a> for TenantID {
+///    const NAME: &'static str = "X-Tenant-ID";
+///
+///    fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+///        Ok(TenantID(value.trim().to_string()))
+///    }
+///
+///    fn to_raw_value(&self) -> String {
+///        self.0.clone()
+///    }
+///}
+///
+///let mut registry = HeaderRegistry::new();
+///registry.register::<TenantID>();
+///
+///let raw = RawHeaderBag::parse("X-Tenant-ID: acme\r\n").unwrap();
+///let parsed = ParsedHeaderBag::parse_raw_bag_with_registry(&raw, &registry, Charset::Windows1252);
+///match parsed.get_first("x-tenant-id") {
+///    Some(ParsedMailHeader::Custom(c)) => assert_eq!(c.downcast_ref::<TenantID>().unwrap().0, "acme"),
+///    h => panic!("Unexpected header: {:?}", h),
+///}
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeaderRegistry {
+    parsers: Vec<(&'static str, HeaderParseFn)>,
+}
+
+impl HeaderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register makes headers named `H::NAME` parsed as `H`.
+    /// They are kept as `ParsedMailHeader::Custom`.
+    pub fn register<H>(&mut self) -> &mut Self
+        where H: for<'a> Header<'a> + Debug + Send + Sync + 'static
+    {
+        self.register_parser(<H as Header<'static>>::NAME, parse_custom::<H>)
+    }
+
+    /// register_parser makes headers with given name parsed with given function.
+    /// Parser registered later takes precedence.
+    pub fn register_parser(&mut self, name: &'static str, parser: HeaderParseFn) -> &mut Self {
+        self.parsers.push((name, parser));
+        self
+    }
+
+    /// parse parses header with parser registered for it's name. Name is compared case insensitive.
+    /// If registered parser fails on header known by this crate, result of builtin parser is returned.
+    pub fn parse<'a>(&self, name: &str, value: &'a str) -> Result<ParsedMailHeader<'a>, MailHeaderParseError> {
+        match self.parsers.iter().rev().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            Some((_, parser)) => parser(value).or_else(|e| parse_builtin_header(name, value).unwrap_or(Err(e))),
+            None => ParsedMailHeader::parse(name, value),
+        }
+    }
+
+    /// try_parse parses header if it's possible or returns `ParsedMailHeader::UnknownHeader`
+    /// which contains value of given header
+    pub fn try_parse<'a>(&self, name: &str, value: &'a str) -> ParsedMailHeader<'a> {
+        self.parse(name, value).unwrap_or(ParsedMailHeader::UnknownHeader(Cow::Borrowed(value)))
+    }
+}
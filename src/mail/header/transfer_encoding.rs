@@ -3,6 +3,7 @@ use std::str::FromStr;
 
 use crate::encoding::base64::Base64Reader;
 use crate::encoding::quoted_printable::QuotedPrintableReader;
+use crate::encoding::spaceless::SpacelessReader;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -21,10 +22,22 @@ pub enum ContentTransferEncoding {
 pub enum ContentTransferEncodingDecoder<R> {
     /// NoDecoder is decoder used when either 7bit or 8bit or binary or Other encoding has been applied
     NoDecoder(R),
-    Base64(Base64Reader<R>),
+    /// Base64 decoder skips line breaks, which are always present in base64 encoded mail bodies
+    Base64(Base64Reader<SpacelessReader<R>>),
     QuotedPrintable(QuotedPrintableReader<R>),
 }
 
+impl<R> ContentTransferEncodingDecoder<R> {
+    /// into_inner returns underlying reader. Any data buffered by decoder is lost.
+    pub fn into_inner(self) -> R {
+        match self {
+            ContentTransferEncodingDecoder::NoDecoder(r) => r,
+            ContentTransferEncodingDecoder::Base64(r) => r.into_inner().into_inner(),
+            ContentTransferEncodingDecoder::QuotedPrintable(r) => r.into_inner(),
+        }
+    }
+}
+
 impl<R> Read for ContentTransferEncodingDecoder<R>
     where R: Read
 {
@@ -60,13 +73,25 @@ impl FromStr for ContentTransferEncoding {
 impl ContentTransferEncoding {
     pub fn get_decoder<R>(self, r: R) -> ContentTransferEncodingDecoder<R> {
         match self {
-            // TODO(teawithsand) introduce spaceless here
-            ContentTransferEncoding::Base64 => ContentTransferEncodingDecoder::Base64(Base64Reader::new(r)),
+            ContentTransferEncoding::Base64 => ContentTransferEncodingDecoder::Base64(Base64Reader::new(SpacelessReader::new(r))),
             ContentTransferEncoding::QuotedPrintable => ContentTransferEncodingDecoder::QuotedPrintable(QuotedPrintableReader::new(r)),
             _ => ContentTransferEncodingDecoder::NoDecoder(r)
         }
     }
 
+    /// as_str returns name of encoding as it's put in header.
+    /// `Other` encoding has no name so `x-unknown` is returned for it.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentTransferEncoding::Base64 => "base64",
+            ContentTransferEncoding::QuotedPrintable => "quoted-printable",
+            ContentTransferEncoding::EightBitAscii => "8bit",
+            ContentTransferEncoding::SevenBitAscii => "7bit",
+            ContentTransferEncoding::Binary => "binary",
+            ContentTransferEncoding::Other => "x-unknown",
+        }
+    }
+
     /// decode decodes cte from it's content. It can't fail.
     /// If encoding was not recognised `Other` is returned
     pub fn decode(text: &str) -> ContentTransferEncoding {
@@ -0,0 +1,434 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use crate::mail::address::{Address, EmailAddress};
+use crate::mail::date::Date;
+use crate::mail::header::{ContentDisposition, ContentTransferEncoding, decode_rfc_2231_params, format_params, Params, parse_message_id, parse_multiple_message_id};
+use crate::mail::header::bag::{MailHeaderParseError, ParsedMailHeader};
+use crate::utils::quoted::{encode_unstructured, parse_maybe_rfc_2047};
+
+/// Header is typed header which can be looked up in `ParsedHeaderBag` with `ParsedHeaderBag::get`.
+///
+/// Headers which are not recognized by `ParsedMailHeader::parse` are kept as `ParsedMailHeader::UnknownHeader`,
+/// so implementing this trait is enough to get custom headers typed.
+/// Registering it in `HeaderRegistry` makes it parsed along with the other headers.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::header::{Header, MailHeaderParseError, ParsedHeaderBag, RawHeaderBag};
+///struct SpamScore(f32);
+///
+///impl<'a> Header<'a> for SpamScore {
+///    const NAME: &'static str = "X-Spam-Score";
+///
+///    fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+///        value.trim().parse().map(SpamScore).map_err(|_| MailHeaderParseError::TypeNotMatched)
+///    }
+///
+///    fn to_raw_value(&self) -> String {
+///        self.0.to_string()
+///    }
+///}
+///
+///let raw = RawHeaderBag::parse("x-spam-score: 4.5\r\n").unwrap();
+///let parsed = ParsedHeaderBag::parse_raw_bag(&raw);
+///assert_eq!(parsed.get::<SpamScore>().unwrap().0, 4.5);
+/// ```
+pub trait Header<'a>: Sized {
+    /// NAME is name of header. It's compared case insensitive.
+    const NAME: &'static str;
+
+    /// parse parses unfolded and decoded value of header
+    fn parse(value: &'a str) -> Result<Self, MailHeaderParseError>;
+
+    /// to_raw_value renders value of header so it can be put in message.
+    /// Result is not folded.
+    fn to_raw_value(&self) -> String;
+
+    /// from_parsed gets header from one parsed with `ParsedMailHeader::parse`.
+    /// By default value of unknown or custom header is parsed with `parse`.
+    fn from_parsed(header: &'a ParsedMailHeader<'a>) -> Option<Self> {
+        match header {
+            ParsedMailHeader::UnknownHeader(value) => Self::parse(value).ok(),
+            ParsedMailHeader::Custom(custom) => Self::parse(custom.value()).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// builtin_header defines newtype of value of `ParsedMailHeader` variant and implements `Header` for it
+macro_rules! builtin_header {
+    (
+        $(#[$meta:meta])*
+        $ty:ident$(<$lt:lifetime>)?($value:ty) = $name:expr, $variant:ident,
+        parse: $parse:expr,
+        render: $render:expr $(,)?
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+        pub struct $ty$(<$lt>)?(pub $value);
+
+        impl<'a> Header<'a> for $ty$(<$lt>)? {
+            const NAME: &'static str = $name;
+
+            fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+                let parse: fn(&'a str) -> Result<$value, MailHeaderParseError> = $parse;
+                parse(value).map($ty)
+            }
+
+            fn to_raw_value(&self) -> String {
+                let render: fn(&$value) -> String = $render;
+                render(&self.0)
+            }
+
+            fn from_parsed(header: &'a ParsedMailHeader<'a>) -> Option<Self> {
+                match header {
+                    ParsedMailHeader::$variant(v) => Some($ty(v.clone())),
+                    _ => None,
+                }
+            }
+        }
+
+        impl<'a> From<$ty$(<$lt>)?> for ParsedMailHeader<'a> {
+            fn from(h: $ty$(<$lt>)?) -> Self {
+                ParsedMailHeader::$variant(h.0)
+            }
+        }
+    };
+}
+
+builtin_header!(
+    /// SubjectHeader is `Subject` header with encoded words decoded
+    SubjectHeader<'a>(Cow<'a, str>) = "Subject", Subject,
+    parse: |v| Ok(parse_maybe_rfc_2047(v)),
+    render: |v| encode_unstructured(v),
+);
+
+builtin_header!(
+    ReplyToHeader<'a>(EmailAddress<'a>) = "Reply-To", ReplyTo,
+    parse: |v| Ok(EmailAddress::parse_single(v)?),
+    render: |v| v.to_header_value(),
+);
+
+builtin_header!(
+    ReturnPathHeader<'a>(EmailAddress<'a>) = "Return-Path", ReturnPath,
+    parse: |v| Ok(EmailAddress::parse_single(v)?),
+    render: |v| v.to_header_value(),
+);
+
+builtin_header!(
+    EnvelopeToHeader<'a>(EmailAddress<'a>) = "Envelope-To", EnvelopeTo,
+    parse: |v| Ok(EmailAddress::parse_single(v)?),
+    render: |v| v.to_header_value(),
+);
+
+builtin_header!(
+    BccHeader<'a>(Vec<Address<'a>>) = "Bcc", Bcc,
+    parse: |v| Ok(Address::parse_list(v)?),
+    render: |v| Address::list_to_header_value(v),
+);
+
+builtin_header!(
+    CcHeader<'a>(Vec<Address<'a>>) = "Cc", Cc,
+    parse: |v| Ok(Address::parse_list(v)?),
+    render: |v| Address::list_to_header_value(v),
+);
+
+builtin_header!(
+    ToHeader<'a>(Vec<Address<'a>>) = "To", To,
+    parse: |v| Ok(Address::parse_list(v)?),
+    render: |v| Address::list_to_header_value(v),
+);
+
+builtin_header!(
+    FromHeader<'a>(EmailAddress<'a>) = "From", From,
+    parse: |v| Ok(EmailAddress::parse_single(v)?),
+    render: |v| v.to_header_value(),
+);
+
+builtin_header!(
+    /// MessageIDHeader is `Message-ID` header. Id is kept without angle brackets.
+    MessageIDHeader<'a>(Cow<'a, str>) = "Message-ID", MessageID,
+    parse: |v| Ok(parse_message_id(v)?),
+    render: |v| format!("<{}>", v),
+);
+
+builtin_header!(
+    InReplyToHeader<'a>(Cow<'a, str>) = "In-Reply-To", InReplyTo,
+    parse: |v| Ok(parse_message_id(v)?),
+    render: |v| format!("<{}>", v),
+);
+
+builtin_header!(
+    ReferencesHeader<'a>(Vec<Cow<'a, str>>) = "References", References,
+    parse: |v| Ok(parse_multiple_message_id(v)?),
+    render: |v| v.iter().map(|id| format!("<{}>", id)).collect::<Vec<_>>().join(" "),
+);
+
+builtin_header!(
+    ContentDispositionHeader<'a>(ContentDisposition<'a>) = "Content-Disposition", ContentDisposition,
+    parse: |v| Ok(ContentDisposition::parse(v)?),
+    render: |v| format!("{}{}", v.disposition.as_str(), format_params(&v.params)),
+);
+
+builtin_header!(
+    ContentTransferEncodingHeader(ContentTransferEncoding) = "Content-Transfer-Encoding", ContentTransferEncoding,
+    parse: |v| Ok(ContentTransferEncoding::decode(v)),
+    render: |v| v.as_str().to_string(),
+);
+
+builtin_header!(
+    ContentLanguageHeader<'a>(Cow<'a, str>) = "Content-Language", ContentLanguage,
+    parse: |v| Ok(Cow::Borrowed(v)),
+    render: |v| v.to_string(),
+);
+
+builtin_header!(
+    DateHeader(Date) = "Date", Date,
+    parse: |v| Ok(Date::parse(v)?),
+    render: |v| v.to_string(),
+);
+
+/// ContentTypeHeader is `Content-Type` header.
+/// It contains type, subtype, suffix and RFC 2231 decoded parameters, for instance `text`, `plain`, `None` and `charset=utf-8`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ContentTypeHeader<'a>(pub Cow<'a, str>, pub Cow<'a, str>, pub Option<Cow<'a, str>>, pub Params<'a>);
+
+impl<'a> Header<'a> for ContentTypeHeader<'a> {
+    const NAME: &'static str = "Content-Type";
+
+    fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+        let mime = mime::Mime::from_str(value)?;
+        let params = decode_rfc_2231_params(
+            mime.params().map(|(p1, p2)| (Cow::Owned(p1.to_string()), Cow::Owned(p2.to_string())))
+        );
+        Ok(ContentTypeHeader(
+            Cow::Owned(mime.type_().as_str().to_string()),
+            Cow::Owned(mime.subtype().as_str().to_string()),
+            mime.suffix().map(|s| Cow::Owned(s.as_str().to_string())),
+            params,
+        ))
+    }
+
+    fn to_raw_value(&self) -> String {
+        let suffix = self.2.as_ref().map(|s| format!("+{}", s)).unwrap_or_default();
+        format!("{}/{}{}{}", self.0, self.1, suffix, format_params(&self.3))
+    }
+
+    fn from_parsed(header: &'a ParsedMailHeader<'a>) -> Option<Self> {
+        match header {
+            ParsedMailHeader::ContentType(t, st, suffix, params) => Some(ContentTypeHeader(t.clone(), st.clone(), suffix.clone(), params.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<ContentTypeHeader<'a>> for ParsedMailHeader<'a> {
+    fn from(h: ContentTypeHeader<'a>) -> Self {
+        ParsedMailHeader::ContentType(h.0, h.1, h.2, h.3)
+    }
+}
+
+/// try_builtin_headers returns result of parsing value as first of given header types whose name matches
+macro_rules! try_builtin_headers {
+    ($name:expr, $value:expr, $($ty:ident),+ $(,)?) => {
+        $(
+            if $name.eq_ignore_ascii_case($ty::NAME) {
+                return Some($ty::parse($value).map(ParsedMailHeader::from));
+            }
+        )+
+    };
+}
+
+/// parse_builtin_header parses header of one of types known by this crate.
+/// `None` is returned if name of header is not known. Name is compared case insensitive.
+pub(crate) fn parse_builtin_header<'a>(name: &str, value: &'a str) -> Option<Result<ParsedMailHeader<'a>, MailHeaderParseError>> {
+    try_builtin_headers!(
+        name, value,
+        SubjectHeader,
+        ReturnPathHeader,
+        EnvelopeToHeader,
+        ReplyToHeader,
+        BccHeader,
+        CcHeader,
+        ToHeader,
+        FromHeader,
+        MessageIDHeader,
+        InReplyToHeader,
+        ReferencesHeader,
+        ContentTypeHeader,
+        ContentDispositionHeader,
+        ContentTransferEncodingHeader,
+        DateHeader,
+        ContentLanguageHeader,
+    );
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encoding::charset::Charset;
+    use crate::mail::date::DateHeuristic;
+    use crate::mail::header::{HeaderRegistry, ParsedHeaderBag, RawHeaderBag};
+
+    use super::*;
+
+    #[test]
+    fn test_builtin_headers_can_be_rendered_and_parsed_again() {
+        for (name, value) in [
+            ("Subject", "Zażółć gęślą jaźń"),
+            ("From", "John Doe <john@example.com>"),
+            ("To", "a@example.com, \"B, C\" <b@example.com>"),
+            ("To", "undisclosed-recipients:;"),
+            ("Cc", "a@example.com, Team: \"B, C\" <b@example.com>, c@example.com;"),
+            ("Message-ID", "<abc@example.com>"),
+            ("References", "<a@example.com> <b@example.com>"),
+            ("Content-Type", "text/plain; charset=utf-8"),
+            ("Content-Disposition", "attachment; filename=\"a b.txt\""),
+            ("Content-Transfer-Encoding", "quoted-printable"),
+            ("Date", "Thu, 13 Feb 1969 23:32:54 -0330"),
+        ].iter() {
+            let parsed = ParsedMailHeader::parse(name, value).unwrap();
+            let rendered = match &parsed {
+                ParsedMailHeader::Subject(v) => SubjectHeader(v.clone()).to_raw_value(),
+                ParsedMailHeader::From(v) => FromHeader(v.clone()).to_raw_value(),
+                ParsedMailHeader::To(v) => ToHeader(v.clone()).to_raw_value(),
+                ParsedMailHeader::Cc(v) => CcHeader(v.clone()).to_raw_value(),
+                ParsedMailHeader::MessageID(v) => MessageIDHeader(v.clone()).to_raw_value(),
+                ParsedMailHeader::References(v) => ReferencesHeader(v.clone()).to_raw_value(),
+                ParsedMailHeader::ContentType(t, st, suffix, params) => ContentTypeHeader(t.clone(), st.clone(), suffix.clone(), params.clone()).to_raw_value(),
+                ParsedMailHeader::ContentDisposition(v) => ContentDispositionHeader(v.clone()).to_raw_value(),
+                ParsedMailHeader::ContentTransferEncoding(v) => ContentTransferEncodingHeader(*v).to_raw_value(),
+                ParsedMailHeader::Date(v) => DateHeader(*v).to_raw_value(),
+                h => panic!("Unexpected header: {:?}", h),
+            };
+            assert_eq!(ParsedMailHeader::parse(name, &rendered).unwrap(), parsed, "{}: {}", name, rendered);
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct TenantID(String);
+
+    impl<'a> Header<'a> for TenantID {
+        const NAME: &'static str = "X-Tenant-ID";
+
+        fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+            Ok(TenantID(value.trim().to_string()))
+        }
+
+        fn to_raw_value(&self) -> String {
+            self.0.clone()
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct SpamScore(i32);
+
+    impl<'a> Header<'a> for SpamScore {
+        const NAME: &'static str = "X-Spam-Score";
+
+        fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+            value.trim().parse().map(SpamScore).map_err(|_| MailHeaderParseError::TypeNotMatched)
+        }
+
+        fn to_raw_value(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    /// Subject overrides builtin `Subject` header, which is not decoded then
+    #[derive(Debug, PartialEq)]
+    struct RawSubject(String);
+
+    impl<'a> Header<'a> for RawSubject {
+        const NAME: &'static str = "Subject";
+
+        fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+            Ok(RawSubject(value.to_string()))
+        }
+
+        fn to_raw_value(&self) -> String {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn test_can_parse_registered_headers() {
+        let mut registry = HeaderRegistry::new();
+        registry.register::<TenantID>().register::<SpamScore>();
+
+        let raw = RawHeaderBag::parse("x-tenant-id: acme\r\nX-Spam-Score: 5\r\nX-Spam-Score: high\r\nX-Other: a\r\nTo: a@example.com\r\n").unwrap();
+        let parsed = ParsedHeaderBag::parse_raw_bag_with_registry(&raw, &registry, Charset::Windows1252);
+
+        match parsed.get_first("X-Tenant-ID") {
+            Some(ParsedMailHeader::Custom(c)) => {
+                assert_eq!(c.downcast_ref::<TenantID>(), Some(&TenantID("acme".to_string())));
+                assert_eq!(c.downcast_ref::<SpamScore>(), None);
+                assert_eq!(c.value(), "acme");
+                assert_eq!(c.to_raw_value(), "acme");
+            }
+            h => panic!("Unexpected header: {:?}", h),
+        }
+        let scores: Vec<_> = parsed.get_all("X-Spam-Score").map(|h| match h {
+            ParsedMailHeader::Custom(c) => Ok(c.downcast_ref::<SpamScore>().unwrap().0),
+            ParsedMailHeader::UnknownHeader(v) => Err(v.to_string()),
+            h => panic!("Unexpected header: {:?}", h),
+        }).collect();
+        assert_eq!(scores, vec![Ok(5), Err("high".to_string())]);
+        assert_eq!(parsed.get::<SpamScore>(), Some(SpamScore(5)));
+        assert_eq!(parsed.get_first("X-Other"), Some(&ParsedMailHeader::UnknownHeader(Cow::Borrowed("a"))));
+        assert!(parsed.to().is_some());
+    }
+
+    #[test]
+    fn test_registered_headers_override_builtin_ones() {
+        let mut registry = HeaderRegistry::new();
+        registry.register::<RawSubject>();
+
+        let raw = RawHeaderBag::parse("Subject: =?utf-8?q?a?=\r\n").unwrap();
+        let parsed = ParsedHeaderBag::parse_raw_bag_with_registry(&raw, &registry, Charset::Windows1252);
+        assert_eq!(parsed.get::<RawSubject>(), Some(RawSubject("=?utf-8?q?a?=".to_string())));
+        assert_eq!(parsed.subject(), None);
+    }
+
+    /// IsoDate overrides builtin `Date` header, but accepts ISO 8601 dates only
+    #[derive(Debug, PartialEq)]
+    struct IsoDate(Date);
+
+    impl<'a> Header<'a> for IsoDate {
+        const NAME: &'static str = "Date";
+
+        fn parse(value: &'a str) -> Result<Self, MailHeaderParseError> {
+            match Date::parse_lenient(value)? {
+                (date, ref heuristics) if heuristics == &[DateHeuristic::Iso8601] => Ok(IsoDate(date)),
+                _ => Err(MailHeaderParseError::TypeNotMatched),
+            }
+        }
+
+        fn to_raw_value(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    #[test]
+    fn test_failed_registered_parser_falls_back_to_builtin_one() {
+        let mut registry = HeaderRegistry::new();
+        registry.register::<IsoDate>().register::<SpamScore>();
+
+        let iso = registry.parse("Date", "2008-06-03").unwrap();
+        assert!(matches!(iso, ParsedMailHeader::Custom(ref c) if c.downcast_ref::<IsoDate>().is_some()));
+        let builtin = registry.parse("date", "Tue, 3 Jun 2008 11:05:30 +0200").unwrap();
+        assert!(matches!(builtin, ParsedMailHeader::Date(_)));
+        match registry.parse("Date", "garbage") {
+            Err(MailHeaderParseError::DateParseError(_)) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        // header unknown to this crate has no builtin parser to fall back to
+        match registry.parse("X-Spam-Score", "high") {
+            Err(MailHeaderParseError::TypeNotMatched) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::{self, Cursor, ErrorKind, Read};
+
+use crate::encoding::charset::decode_with_label;
+use crate::encoding::multipart::{PartHeadersError, PartReader, PartReaderState};
+use crate::error::offset_in;
+use crate::mail::header::{
+    ContentDisposition,
+    ContentTransferEncoding,
+    ContentTransferEncodingDecoder,
+    count_header_bytes,
+    decode_header_value,
+    DEFAULT_HEADER_CHARSET,
+    find_param,
+    into_owned_params,
+    ParsedHeaderBag,
+    ParsedMailHeader,
+    RawHeaderBag,
+    RawHeaderParseOptions,
+    RawMailHeaderParseError,
+};
+
+pub use crate::encoding::multipart::MAX_HEADER_SIZE;
+
+/// MAX_NESTING_DEPTH limits how deep parts may be nested in each other.
+/// It protects parser from stack overflow on malicious messages.
+pub const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug, From)]
+pub enum MessageParseError {
+    HeaderParseError(RawMailHeaderParseError),
+    IOError(io::Error),
+    NestingTooDeep,
+    HeaderTooLong,
+}
+
+impl fmt::Display for MessageParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageParseError::HeaderParseError(e) => e.fmt(f),
+            MessageParseError::IOError(e) => e.fmt(f),
+            MessageParseError::NestingTooDeep => write!(f, "parts are nested too deep"),
+            MessageParseError::HeaderTooLong => write!(f, "headers section is too long"),
+        }
+    }
+}
+
+impl From<PartHeadersError> for MessageParseError {
+    fn from(e: PartHeadersError) -> Self {
+        match e {
+            PartHeadersError::HeaderParseError(e) => MessageParseError::HeaderParseError(e),
+            PartHeadersError::IOError(e) => MessageParseError::IOError(e),
+            PartHeadersError::HeaderTooLong => MessageParseError::HeaderTooLong,
+        }
+    }
+}
+
+impl error::Error for MessageParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            MessageParseError::HeaderParseError(e) => e.source(),
+            MessageParseError::IOError(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+/// MimePart is single node of MIME tree.
+///
+/// For `multipart/*` parts children are parts between boundaries.
+/// For `message/rfc822` parts there is single child which is encapsulated message.
+/// Any other part is leaf and it's body may be decoded with `decode_body`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimePart<'a> {
+    headers: RawHeaderBag<'a>,
+
+    mime_type: Cow<'a, str>,
+    mime_subtype: Cow<'a, str>,
+    params: HashMap<Cow<'a, str>, Vec<Cow<'a, str>>>,
+    transfer_encoding: ContentTransferEncoding,
+
+    raw_body: &'a [u8],
+    preamble: &'a [u8],
+    epilogue: &'a [u8],
+    children: Vec<MimePart<'a>>,
+}
+
+/// find_raw_header finds value of first header with given name in order of headers.
+/// Name is compared case insensitive. Value is decoded with `decode_header_value`.
+pub(crate) fn find_raw_header<'b>(bag: &'b RawHeaderBag<'_>, name: &str) -> Option<Cow<'b, str>> {
+    bag.headers().iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| decode_header_value(&h.value, DEFAULT_HEADER_CHARSET))
+}
+
+/// ContentType is type, subtype and parameters of `Content-Type` header
+pub(crate) type ContentType = (Cow<'static, str>, Cow<'static, str>, HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>);
+
+/// parse_content_type gets content type of part with given headers.
+/// When there is no valid `Content-Type` header default one is returned.
+pub(crate) fn parse_content_type(headers: &RawHeaderBag<'_>, is_digest: bool) -> ContentType {
+    let content_type = find_raw_header(headers, "Content-Type");
+    match content_type.as_ref()
+        .map(|v| ParsedMailHeader::parse("Content-Type", v)) {
+        Some(Ok(ParsedMailHeader::ContentType(t, st, _, p))) => (
+            Cow::Owned(t.into_owned()),
+            Cow::Owned(st.into_owned()),
+            into_owned_params(p),
+        ),
+        // RFC 2046 5.1.5: default type in digest is message/rfc822
+        _ if is_digest => (Cow::Borrowed("message"), Cow::Borrowed("rfc822"), HashMap::new()),
+        // RFC 2045 5.2: default type is text/plain
+        _ => (Cow::Borrowed("text"), Cow::Borrowed("plain"), HashMap::new()),
+    }
+}
+
+/// parse_transfer_encoding gets content transfer encoding of part with given headers.
+/// When there is no `Content-Transfer-Encoding` header 7bit is returned.
+pub(crate) fn parse_transfer_encoding(headers: &RawHeaderBag<'_>) -> ContentTransferEncoding {
+    find_raw_header(headers, "Content-Transfer-Encoding")
+        .map(|v| ContentTransferEncoding::decode(&v))
+        .unwrap_or(ContentTransferEncoding::SevenBitAscii)
+}
+
+/// split_header_section splits part into headers and body.
+/// Headers include line terminator of last header. Part with no empty line is assumed to contain headers only.
+fn split_header_section(data: &[u8]) -> (&[u8], &[u8]) {
+    if data.starts_with(b"\r\n") {
+        return (&[], &data[2..]);
+    }
+    match count_header_bytes(data) {
+        Ok(sz) if sz + 4 <= data.len() => (&data[..sz + 2], &data[sz + 4..]),
+        _ => (data, &[]),
+    }
+}
+
+/// read_part drains part reader and returns whatever or not it has found final boundary.
+/// `None` is returned when no boundary was found before EOF.
+fn read_part<R: Read>(r: &mut PartReader<R>) -> Result<Option<(usize, bool)>, io::Error> {
+    match io::copy(r, &mut io::sink()) {
+        Ok(sz) => Ok(Some((sz as usize, r.state() == PartReaderState::FoundFinalBoundary))),
+        Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// MultipartBody contains parts of multipart body without any processing applied
+struct MultipartBody<'a> {
+    preamble: &'a [u8],
+    parts: Vec<&'a [u8]>,
+    epilogue: &'a [u8],
+}
+
+/// split_multipart splits multipart body into parts using `PartReader`.
+/// Missing final boundary is tolerated, last part ends at the end of body then.
+fn split_multipart<'a>(body: &'a [u8], boundary: &[u8]) -> Result<MultipartBody<'a>, io::Error> {
+    let mut res = MultipartBody {
+        preamble: body,
+        parts: Vec::new(),
+        epilogue: &[],
+    };
+    let mut cursor = Cursor::new(body);
+
+    // first boundary is not preceded with new line when there is no preamble
+    // so fake one
+    let is_final = {
+        let mut pr = PartReader::new(Cursor::new(&b"\r\n"[..]).chain(&mut cursor), boundary, false);
+        match read_part(&mut pr)? {
+            Some((sz, is_final)) => {
+                res.preamble = &body[..sz.saturating_sub(2)];
+                is_final
+            }
+            None => return Ok(res),
+        }
+    };
+    if !is_final {
+        loop {
+            let start = cursor.position() as usize;
+            let mut pr = PartReader::new(&mut cursor, boundary, false);
+            match read_part(&mut pr)? {
+                Some((sz, is_final)) => {
+                    res.parts.push(&body[start..start + sz]);
+                    if is_final {
+                        break;
+                    }
+                }
+                None => {
+                    res.parts.push(&body[start..]);
+                    return Ok(res);
+                }
+            }
+        }
+    }
+    res.epilogue = &body[cursor.position() as usize..];
+    Ok(res)
+}
+
+impl<'a> MimePart<'a> {
+    /// parse parses part which starts at `base` offset of message.
+    /// Offsets of headers and errors are relative to the beginning of message.
+    fn parse(
+        data: &'a [u8], base: usize, is_digest: bool, depth: usize,
+        options: RawHeaderParseOptions,
+    ) -> Result<Self, MessageParseError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(MessageParseError::NestingTooDeep);
+        }
+        let (headers, body) = split_header_section(data);
+        let mut headers = RawHeaderBag::parse_with_options(headers, options)
+            .map_err(|e| MessageParseError::from(e.shift(base)))?;
+        headers.shift(base);
+
+        let (mime_type, mime_subtype, params) = parse_content_type(&headers, is_digest);
+        let transfer_encoding = parse_transfer_encoding(&headers);
+
+        let mut part = Self {
+            headers,
+            mime_type,
+            mime_subtype,
+            params,
+            transfer_encoding,
+            raw_body: body,
+            preamble: &[],
+            epilogue: &[],
+            children: Vec::new(),
+        };
+
+        if part.is_multipart() {
+            // multipart without boundary can't be split so it's treated as leaf
+            if let Some(boundary) = part.boundary().map(|b| b.as_bytes().to_vec()) {
+                let is_digest = part.mime_subtype.eq_ignore_ascii_case("digest");
+                let mp = split_multipart(body, &boundary)?;
+                part.preamble = mp.preamble;
+                part.epilogue = mp.epilogue;
+                for p in mp.parts {
+                    part.children.push(Self::parse(p, base + offset_in(data, p), is_digest, depth + 1, options)?);
+                }
+            }
+        } else if part.is_message() {
+            // RFC 2046 5.2.1 allows only identity encodings here so body does not have to be decoded
+            part.children.push(Self::parse(body, base + offset_in(data, body), false, depth + 1, options)?);
+        }
+
+        Ok(part)
+    }
+
+    #[inline]
+    pub fn headers(&self) -> &RawHeaderBag<'a> {
+        &self.headers
+    }
+
+    /// parsed_headers parses headers of this part
+    pub fn parsed_headers(&self) -> ParsedHeaderBag<'_> {
+        ParsedHeaderBag::parse_raw_bag(&self.headers)
+    }
+
+    /// mime_type returns type part of content type, for instance `text` in `text/plain`
+    #[inline]
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// mime_subtype returns subtype part of content type, for instance `plain` in `text/plain`
+    #[inline]
+    pub fn mime_subtype(&self) -> &str {
+        &self.mime_subtype
+    }
+
+    #[inline]
+    pub fn params(&self) -> &HashMap<Cow<'a, str>, Vec<Cow<'a, str>>> {
+        &self.params
+    }
+
+    /// param returns first value of content type parameter with given name.
+    /// Name is compared case insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        find_param(&self.params, name)
+    }
+
+    #[inline]
+    pub fn boundary(&self) -> Option<&str> {
+        self.param("boundary")
+    }
+
+    #[inline]
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    /// disposition parses `Content-Disposition` header of this part
+    pub fn disposition(&self) -> Option<ContentDisposition<'_>> {
+        match find_raw_header(&self.headers, "Content-Disposition")? {
+            Cow::Borrowed(v) => ContentDisposition::parse(v).ok(),
+            Cow::Owned(v) => ContentDisposition::parse(&v).ok().map(ContentDisposition::into_owned),
+        }
+    }
+
+    /// filename returns name of file carried by this part.
+    /// `filename` param of `Content-Disposition` is used, if it's missing then `name` param of `Content-Type` is.
+    pub fn filename(&self) -> Option<Cow<'_, str>> {
+        self.disposition()
+            .and_then(|cd| cd.filename)
+            .or_else(|| self.param("name").map(Cow::Borrowed))
+    }
+
+    /// is_attachment checks if this part should be listed as attachment rather than displayed.
+    /// Parts without `Content-Disposition` are attachments when they are not multipart and carry file name.
+    pub fn is_attachment(&self) -> bool {
+        match self.disposition() {
+            Some(cd) => cd.is_attachment(),
+            None => !self.is_multipart() && self.filename().is_some(),
+        }
+    }
+
+    #[inline]
+    pub fn is_multipart(&self) -> bool {
+        self.mime_type.eq_ignore_ascii_case("multipart")
+    }
+
+    #[inline]
+    pub fn is_message(&self) -> bool {
+        self.mime_type.eq_ignore_ascii_case("message") && self.mime_subtype.eq_ignore_ascii_case("rfc822")
+    }
+
+    #[inline]
+    pub fn transfer_encoding(&self) -> ContentTransferEncoding {
+        self.transfer_encoding
+    }
+
+    /// raw_body returns body of part as it is in message. No decoding is applied.
+    #[inline]
+    pub fn raw_body(&self) -> &'a [u8] {
+        self.raw_body
+    }
+
+    /// preamble returns data before first boundary of multipart part.
+    /// It's empty for non-multipart parts.
+    #[inline]
+    pub fn preamble(&self) -> &'a [u8] {
+        self.preamble
+    }
+
+    /// epilogue returns data after final boundary of multipart part.
+    /// It's empty for non-multipart parts.
+    #[inline]
+    pub fn epilogue(&self) -> &'a [u8] {
+        self.epilogue
+    }
+
+    #[inline]
+    pub fn children(&self) -> &[MimePart<'a>] {
+        &self.children
+    }
+
+    /// body_reader returns reader which decodes body using part's `Content-Transfer-Encoding`
+    pub fn body_reader(&self) -> ContentTransferEncodingDecoder<Cursor<&'a [u8]>> {
+        self.transfer_encoding.get_decoder(Cursor::new(self.raw_body))
+    }
+
+    /// decode_body decodes entire body using part's `Content-Transfer-Encoding`
+    pub fn decode_body(&self) -> Result<Vec<u8>, io::Error> {
+        let mut res = Vec::with_capacity(self.raw_body.len());
+        self.body_reader().read_to_end(&mut res)?;
+        Ok(res)
+    }
+
+    /// decode_text decodes body and converts it to string using `charset` param of `Content-Type`.
+    /// Body without charset or with unknown one is treated as UTF-8. Invalid sequences are replaced with U+FFFD.
+    pub fn decode_text(&self) -> Result<String, io::Error> {
+        let body = self.decode_body()?;
+        Ok(decode_with_label(self.charset().unwrap_or("utf-8"), &body).into_owned())
+    }
+}
+
+/// Message is RFC 5322 message parsed into tree of MIME parts.
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::message::Message;
+///const MAIL: &str = "\
+///Subject: Test\r\n\
+///Content-Type: multipart/alternative; boundary=\"b1\"\r\n\
+///\r\n\
+///--b1\r\n\
+///Content-Type: text/plain\r\n\
+///\r\n\
+///Hello!\r\n\
+///--b1\r\n\
+///Content-Type: text/html\r\n\
+///Content-Transfer-Encoding: base64\r\n\
+///\r\n\
+///PGI+SGVsbG8hPC9iPg==\r\n\
+///--b1--\r\n";
+///let message = Message::parse(MAIL.as_bytes()).unwrap();
+///let parts = message.root().children();
+///assert_eq!(parts.len(), 2);
+///assert_eq!(parts[0].decode_body().unwrap(), b"Hello!");
+///assert_eq!(parts[1].mime_subtype(), "html");
+///assert_eq!(parts[1].decode_body().unwrap(), b"<b>Hello!</b>");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message<'a> {
+    root: MimePart<'a>,
+}
+
+impl<'a> Message<'a> {
+    /// parse parses entire message with all it's parts using default options
+    pub fn parse(data: &'a [u8]) -> Result<Self, MessageParseError> {
+        Self::parse_with_options(data, RawHeaderParseOptions::default())
+    }
+
+    /// parse_with_options parses entire message with all it's parts.
+    /// Options are used to parse headers of each part.
+    pub fn parse_with_options(data: &'a [u8], options: RawHeaderParseOptions) -> Result<Self, MessageParseError> {
+        Ok(Self {
+            root: MimePart::parse(data, 0, false, 0, options)?,
+        })
+    }
+
+    #[inline]
+    pub fn root(&self) -> &MimePart<'a> {
+        &self.root
+    }
+
+    #[inline]
+    pub fn into_root(self) -> MimePart<'a> {
+        self.root
+    }
+
+    #[inline]
+    pub fn headers(&self) -> &RawHeaderBag<'a> {
+        &self.root.headers
+    }
+
+    /// parts returns iterator over all parts of message in depth-first order.
+    /// Root part is returned first.
+    pub fn parts(&self) -> Parts<'_, 'a> {
+        Parts {
+            stack: vec![&self.root],
+        }
+    }
+}
+
+/// Parts iterates over tree of MIME parts in depth-first order
+pub struct Parts<'p, 'a> {
+    stack: Vec<&'p MimePart<'a>>,
+}
+
+impl<'p, 'a> Iterator for Parts<'p, 'a> {
+    type Item = &'p MimePart<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let part = self.stack.pop()?;
+        self.stack.extend(part.children.iter().rev());
+        Some(part)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mail::header::UnfoldingMode;
+
+    const NESTED_MAIL: &str = "\
+From: sender@example.com\r\n\
+Subject: Nested\r\n\
+Content-Type: multipart/mixed; boundary=outer\r\n\
+\r\n\
+This is preamble\r\n\
+--outer\r\n\
+Content-Type: multipart/alternative; boundary=inner\r\n\
+\r\n\
+--inner\r\n\
+\r\n\
+plain text\r\n\
+--inner\r\n\
+Content-Type: text/html; charset=utf-8\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+<p>=C5=81</p>\r\n\
+--inner--\r\n\
+--outer\r\n\
+Content-Type: message/rfc822\r\n\
+\r\n\
+Subject: Inner message\r\n\
+\r\n\
+Inner body\r\n\
+--outer\r\n\
+Content-Type: application/octet-stream\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+YWFh\r\n\
+YWE=\r\n\
+--outer--\r\n\
+This is epilogue";
+
+    #[test]
+    fn test_can_parse_simple_message() {
+        let m = Message::parse(b"Subject: Test\r\n\r\nSome body").unwrap();
+        assert_eq!(m.root().mime_type(), "text");
+        assert_eq!(m.root().mime_subtype(), "plain");
+        assert_eq!(m.root().raw_body(), b"Some body");
+        assert!(m.root().children().is_empty());
+
+        let m = Message::parse(b"Subject: Test\r\n").unwrap();
+        assert_eq!(m.root().raw_body(), b"");
+
+        let m = Message::parse(b"\r\nSome body").unwrap();
+        assert!(m.headers().container().is_empty());
+        assert_eq!(m.root().raw_body(), b"Some body");
+    }
+
+    #[test]
+    fn test_can_parse_with_options() {
+        const MAIL: &str = "\
+X-Test: a\r\n  b\r\n\
+Content-Type: multipart/mixed; boundary=b\r\n\
+\r\n\
+--b\r\n\
+X-Test: c\r\n  d\r\n\
+\r\n\
+body\r\n\
+--b--\r\n";
+        for (unfolding, o) in [
+            (UnfoldingMode::Spec, vec!["a  b", "c  d"]),
+            (UnfoldingMode::Legacy, vec!["ab", "cd"]),
+        ].iter() {
+            let m = Message::parse_with_options(MAIL.as_bytes(), RawHeaderParseOptions {
+                unfolding: *unfolding,
+            }).unwrap();
+            let values: Vec<_> = m.parts()
+                .map(|p| String::from_utf8_lossy(&p.headers().container()["X-Test"][0]).into_owned())
+                .collect();
+            assert_eq!(&values, o, "{:?}", unfolding);
+        }
+    }
+
+    #[test]
+    fn test_can_parse_nested_message() {
+        let m = Message::parse(NESTED_MAIL.as_bytes()).unwrap();
+        let root = m.root();
+        assert!(root.is_multipart());
+        assert_eq!(root.preamble(), b"This is preamble");
+        assert_eq!(root.epilogue(), b"This is epilogue");
+        assert_eq!(root.children().len(), 3);
+
+        let alternative = &root.children()[0];
+        assert_eq!(alternative.mime_subtype(), "alternative");
+        assert_eq!(alternative.children().len(), 2);
+        assert_eq!(alternative.children()[0].raw_body(), b"plain text");
+        assert_eq!(alternative.children()[1].charset(), Some("utf-8"));
+        assert_eq!(alternative.children()[1].decode_body().unwrap(), "<p>Ł</p>".as_bytes());
+        assert_eq!(alternative.children()[1].decode_text().unwrap(), "<p>Ł</p>");
+
+        let message = &root.children()[1];
+        assert!(message.is_message());
+        assert_eq!(message.children().len(), 1);
+        assert_eq!(message.children()[0].raw_body(), b"Inner body");
+
+        let attachment = &root.children()[2];
+        assert_eq!(attachment.transfer_encoding(), ContentTransferEncoding::Base64);
+        assert_eq!(attachment.decode_body().unwrap(), b"aaaaa");
+
+        assert_eq!(m.parts().count(), 7);
+    }
+
+    #[test]
+    fn test_header_spans_point_at_message_bytes() {
+        let data = NESTED_MAIL.as_bytes();
+        let m = Message::parse(data).unwrap();
+        for part in m.parts() {
+            for h in part.headers().headers() {
+                assert_eq!(&data[h.span()], h.raw.as_ref());
+                assert!(h.raw.ends_with(b"\r\n"), "{:?}", h);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tolerates_missing_final_boundary() {
+        let m = Message::parse(b"\
+Content-Type: multipart/mixed; boundary=b\r\n\
+\r\n\
+--b\r\n\
+\r\n\
+first\r\n\
+--b\r\n\
+\r\n\
+second").unwrap();
+        let children = m.root().children();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].raw_body(), b"first");
+        assert_eq!(children[1].raw_body(), b"second");
+    }
+
+    #[test]
+    fn test_can_decode_text() {
+        for (i, o) in [
+            (&b"\r\nplain"[..], "plain"),
+            (b"Content-Type: text/plain; charset=iso-8859-2\r\n\r\n\xA3\xF3d\xBC", "Łódź"),
+            (b"Content-Type: text/plain; charset=koi8-r\r\nContent-Transfer-Encoding: base64\r\n\r\n8NLJ18XU", "Привет"),
+            (b"Content-Type: text/plain; charset=x-unknown\r\n\r\n\xC5\x81\xFF", "Ł\u{FFFD}"),
+        ].iter() {
+            let m = Message::parse(i).unwrap();
+            assert_eq!(m.root().decode_text().unwrap(), *o);
+        }
+    }
+
+    #[test]
+    fn test_can_get_filename() {
+        for (i, o) in [
+            ("Content-Type: text/plain\r\n\r\n", None),
+            ("Content-Type: text/plain; name=a.txt\r\n\r\n", Some("a.txt")),
+            ("Content-Type: text/plain; name=a.txt\r\nContent-Disposition: attachment; filename=\"b c.txt\"\r\n\r\n", Some("b c.txt")),
+            ("Content-Disposition: attachment;\r\n filename*0*=utf-8''%C5%81%C3%B3d%C5%BA;\r\n filename*1=.txt\r\n\r\n", Some("Łódź.txt")),
+            ("Content-Disposition: inline\r\n\r\n", None),
+        ].iter() {
+            let m = Message::parse(i.as_bytes()).unwrap();
+            assert_eq!(m.root().filename().as_ref().map(|f| f.as_ref()), *o);
+            assert_eq!(m.root().is_attachment(), o.is_some());
+        }
+    }
+
+    #[test]
+    fn test_can_parse_message_with_8bit_headers() {
+        let data = &b"Subject: Caf\xe9\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Disposition: attachment; filename=\"\xff.txt\"\r\n\r\nbody"[..];
+        let m = Message::parse(data).unwrap();
+        let root = m.root();
+        assert_eq!(root.headers().container()["Subject"], vec![Cow::Borrowed(&b"Caf\xe9"[..])]);
+        assert_eq!(root.charset(), Some("utf-8"));
+        assert_eq!(root.filename().unwrap(), "ÿ.txt");
+        assert_eq!(root.parsed_headers().container()["Subject"], vec![ParsedMailHeader::Subject(Cow::Borrowed("Café"))]);
+    }
+
+    #[test]
+    fn test_rejects_too_deep_nesting() {
+        let mut mail = String::new();
+        for _ in 0..=MAX_NESTING_DEPTH + 1 {
+            mail.push_str("Content-Type: message/rfc822\r\n\r\n");
+        }
+        match Message::parse(mail.as_bytes()) {
+            Err(MessageParseError::NestingTooDeep) => {}
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_header_error_points_at_message_bytes() {
+        let data = b"Content-Type: multipart/mixed; boundary=x\r\n\r\n\
+--x\r\n\
+Content-Type: text/plain\r\n\
+Bad Header\r\n\
+\r\n\
+body\r\n\
+--x--\r\n";
+        match Message::parse(data) {
+            Err(MessageParseError::HeaderParseError(e)) => {
+                assert_eq!(e.position().unwrap().offset, 79);
+                assert_eq!(e.position().unwrap().snippet, " Header\r\n");
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
@@ -1,8 +1,15 @@
-//! Mail module contains implementation of parsing:
-//! - Email addresses
+//! Mail module contains implementation of parsing and composing:
+//! - Email addresses and normalizing them for comparison
 //! - Email headers
 //! - Email date in separate modules
+//! - Entire MIME messages into tree of parts
+//! - MIME messages in streaming manner, part by part
+//! - Composing MIME messages with `MessageBuilder`
 
 pub mod header;
 pub mod address;
+pub mod normalize;
 pub mod date;
+pub mod message;
+pub mod event;
+pub mod builder;
@@ -0,0 +1,260 @@
+//! Normalize module contains policies used to compare addresses which differ in spelling only,
+//! like `John@Example.COM` and `john@example.com`.
+//!
+//! `EmailAddress` compares addresses as they are written, so normalization has to be requested explicitly
+//! with `EmailAddress::canonical_key` or `EmailAddress::is_same_as`.
+use std::borrow::Cow;
+
+use crate::encoding::idna::domain_to_ascii;
+use crate::mail::address::{AddressSpec, Domain, EmailAddress};
+
+/// ProviderRule describes provider specific rules of local parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ProviderRule {
+    /// domains handled by provider. Addresses in all of them are normalized to the first one.
+    /// Domains are compared case insensitive.
+    pub domains: Vec<String>,
+    /// ignore_dots removes dots from local part, so `j.doe` is same as `jdoe`
+    pub ignore_dots: bool,
+}
+
+impl ProviderRule {
+    /// gmail returns rule of Gmail, which ignores dots and treats `googlemail.com` as `gmail.com`
+    pub fn gmail() -> Self {
+        Self {
+            domains: vec!["gmail.com".to_string(), "googlemail.com".to_string()],
+            ignore_dots: true,
+        }
+    }
+
+    fn matches(&self, domain: &str) -> bool {
+        self.domains.iter().any(|d| d.eq_ignore_ascii_case(domain))
+    }
+}
+
+/// NormalizationPolicy selects which differences between addresses are ignored
+///
+/// # Example
+/// ```rust
+///# use smtpc::mail::address::EmailAddress;
+///# use smtpc::mail::normalize::NormalizationPolicy;
+///let a = EmailAddress::parse_single("John <John.Doe+news@GoogleMail.com>").unwrap();
+///let b = EmailAddress::parse_single("johndoe@gmail.com").unwrap();
+///
+///assert_eq!(a.canonical_key(&NormalizationPolicy::default()), "John.Doe+news@googlemail.com");
+///assert_eq!(a.canonical_key(&NormalizationPolicy::relaxed()), "johndoe@gmail.com");
+///assert!(a.is_same_as(&b, &NormalizationPolicy::relaxed()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct NormalizationPolicy {
+    /// lowercase_domain makes domain case insensitive, as it's in DNS.
+    /// IP address literals are formatted in canonical form as well.
+    pub lowercase_domain: bool,
+    /// lowercase_local_part makes local part case insensitive.
+    /// RFC 5321 allows local parts to be case sensitive, but most servers do not make use of it.
+    pub lowercase_local_part: bool,
+    /// subaddress_separator removes tag starting at given char from local part,
+    /// so `john+news` is same as `john` for `+`. Local part starting with separator is kept.
+    pub subaddress_separator: Option<char>,
+    /// providers contains provider specific rules, which are applied to addresses in their domains
+    pub providers: Vec<ProviderRule>,
+    /// idna converts domain to A-labels, so `bücher.example` is same as `xn--bcher-kva.example`
+    pub idna: bool,
+}
+
+impl Default for NormalizationPolicy {
+    /// default returns policy which ignores case of domain only
+    fn default() -> Self {
+        Self {
+            lowercase_domain: true,
+            lowercase_local_part: false,
+            subaddress_separator: None,
+            providers: Vec::new(),
+            idna: false,
+        }
+    }
+}
+
+impl NormalizationPolicy {
+    /// exact returns policy which does not normalize addresses at all
+    pub fn exact() -> Self {
+        Self {
+            lowercase_domain: false,
+            ..Self::default()
+        }
+    }
+
+    /// relaxed returns policy which ignores case, `+` tags and dots in Gmail addresses and converts domains to A-labels
+    pub fn relaxed() -> Self {
+        Self {
+            lowercase_domain: true,
+            lowercase_local_part: true,
+            subaddress_separator: Some('+'),
+            providers: vec![ProviderRule::gmail()],
+            idna: true,
+        }
+    }
+
+    /// normalize_domain normalizes domain according to policy.
+    /// Domain which can't be converted to A-labels is kept as it is.
+    pub fn normalize_domain<'a>(&self, domain: &Domain<'a>) -> Domain<'a> {
+        let domain = self.canonical_domain(domain);
+        match self.find_provider(&domain) {
+            Some(rule) => Domain::Name(Cow::Owned(rule.domains[0].clone())),
+            None => domain,
+        }
+    }
+
+    /// canonical_domain lowercases domain and converts it to A-labels if policy says so
+    fn canonical_domain<'a>(&self, domain: &Domain<'a>) -> Domain<'a> {
+        let mut domain = match domain {
+            Domain::Name(n) if self.lowercase_domain => Domain::Name(Cow::Owned(n.to_lowercase())),
+            Domain::Literal(_) if self.lowercase_domain => match domain.ip() {
+                Some(ip) if ip.is_ipv6() => Domain::Literal(Cow::Owned(format!("IPv6:{}", ip))),
+                Some(ip) => Domain::Literal(Cow::Owned(ip.to_string())),
+                None => domain.clone(),
+            },
+            d => d.clone(),
+        };
+        if self.idna {
+            if let Domain::Name(n) = &domain {
+                if let Ok(Cow::Owned(a)) = domain_to_ascii(n) {
+                    domain = Domain::Name(Cow::Owned(a));
+                }
+            }
+        }
+        domain
+    }
+
+    /// find_provider returns rule of provider handling given domain, which has to be normalized already
+    fn find_provider(&self, domain: &Domain) -> Option<&ProviderRule> {
+        match domain {
+            Domain::Name(n) => self.providers.iter().find(|r| r.matches(n)),
+            Domain::Literal(_) => None,
+        }
+    }
+
+    /// normalize normalizes address spec according to policy
+    pub fn normalize<'a>(&self, spec: &AddressSpec<'a>) -> AddressSpec<'a> {
+        let mut domain = self.canonical_domain(&spec.domain);
+        let rule = self.find_provider(&domain);
+        if let Some(rule) = rule {
+            domain = Domain::Name(Cow::Owned(rule.domains[0].clone()));
+        }
+        let mut local_part = spec.local_part.clone();
+        if let Some(separator) = self.subaddress_separator {
+            if let Some(idx) = local_part.find(separator).filter(|idx| *idx > 0) {
+                local_part = Cow::Owned(local_part[..idx].to_string());
+            }
+        }
+        if rule.map(|r| r.ignore_dots).unwrap_or(false) && local_part.contains('.') {
+            local_part = Cow::Owned(local_part.replace('.', ""));
+        }
+        if self.lowercase_local_part {
+            local_part = Cow::Owned(local_part.to_lowercase());
+        }
+        AddressSpec {
+            local_part,
+            domain,
+        }
+    }
+}
+
+impl<'a> EmailAddress<'a> {
+    /// canonical_key returns address normalized according to policy, which can be used to find duplicates.
+    /// Display name is not part of key. Address which can't be parsed is returned as it is.
+    pub fn canonical_key(&self, policy: &NormalizationPolicy) -> String {
+        match self.spec() {
+            Ok(spec) => policy.normalize(&spec).to_string(),
+            Err(_) => self.address.to_string(),
+        }
+    }
+
+    /// is_same_as checks if both addresses have same canonical key
+    pub fn is_same_as(&self, other: &EmailAddress, policy: &NormalizationPolicy) -> bool {
+        self.canonical_key(policy) == other.canonical_key(policy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_normalize_address() {
+        let dots_only = NormalizationPolicy {
+            providers: vec![ProviderRule::gmail()],
+            ..NormalizationPolicy::default()
+        };
+        let plus_only = NormalizationPolicy {
+            subaddress_separator: Some('+'),
+            ..NormalizationPolicy::default()
+        };
+        let idna_only = NormalizationPolicy {
+            idna: true,
+            ..NormalizationPolicy::default()
+        };
+        let idna_provider = NormalizationPolicy {
+            providers: vec![ProviderRule {
+                domains: vec!["xn--bcher-kva.example".to_string()],
+                ignore_dots: true,
+            }],
+            ..idna_only.clone()
+        };
+        for (i, policy, o) in [
+            ("John@Example.COM", NormalizationPolicy::exact(), "John@Example.COM"),
+            ("John@Example.COM", NormalizationPolicy::default(), "John@example.com"),
+            ("John@Example.COM", NormalizationPolicy::relaxed(), "john@example.com"),
+            ("john+news@example.com", NormalizationPolicy::default(), "john+news@example.com"),
+            ("john+news+daily@example.com", plus_only.clone(), "john@example.com"),
+            ("+news@example.com", plus_only, "+news@example.com"),
+            ("j.o.h.n@GoogleMail.com", dots_only.clone(), "john@gmail.com"),
+            ("j.o.h.n@example.com", dots_only, "j.o.h.n@example.com"),
+            ("info@Bücher.example", NormalizationPolicy::default(), "info@bücher.example"),
+            ("info@Bücher.example", idna_only.clone(), "info@xn--bcher-kva.example"),
+            ("info@xn--bcher-kva.example", idna_only, "info@xn--bcher-kva.example"),
+            ("\"John Doe\"@example.com", NormalizationPolicy::relaxed(), "\"john doe\"@example.com"),
+            ("user@[IPv6:2001:DB8:0::1]", NormalizationPolicy::default(), "user@[IPv6:2001:db8::1]"),
+            ("user@[IPv6:2001:DB8:0::1]", NormalizationPolicy::exact(), "user@[IPv6:2001:DB8:0::1]"),
+            ("j.doe@Bücher.example", idna_provider.clone(), "jdoe@xn--bcher-kva.example"),
+            ("j.doe@xn--BCHER-kva.example", idna_provider, "jdoe@xn--bcher-kva.example"),
+        ].iter() {
+            let address = EmailAddress::parse_single(i).unwrap();
+            assert_eq!(address.canonical_key(policy), *o, "{} {:?}", i, policy);
+        }
+    }
+
+    #[test]
+    fn test_can_find_same_addresses() {
+        let policy = NormalizationPolicy::relaxed();
+        let addresses: Vec<_> = [
+            "John Doe <John.Doe@gmail.com>",
+            "johndoe+spam@googlemail.com",
+            "JOHN <j.o.h.n.d.o.e@GMAIL.COM>",
+            "john.doe@example.com",
+            "John.Doe+x@Example.com",
+        ].iter().map(|a| EmailAddress::parse_single(a).unwrap()).collect();
+
+        assert!(addresses[0].is_same_as(&addresses[1], &policy));
+        assert!(addresses[0].is_same_as(&addresses[2], &policy));
+        assert!(!addresses[0].is_same_as(&addresses[3], &policy));
+        assert!(addresses[3].is_same_as(&addresses[4], &policy));
+        assert!(!addresses[3].is_same_as(&addresses[4], &NormalizationPolicy::default()));
+
+        let mut keys: Vec<_> = addresses.iter().map(|a| a.canonical_key(&policy)).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys, vec!["john.doe@example.com", "johndoe@gmail.com"]);
+    }
+
+    #[test]
+    fn test_invalid_address_is_its_own_key() {
+        let address = EmailAddress {
+            name: "".into(),
+            address: "not an address".into(),
+        };
+        assert_eq!(address.canonical_key(&NormalizationPolicy::relaxed()), "not an address");
+    }
+}
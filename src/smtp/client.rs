@@ -0,0 +1,781 @@
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+
+use crate::smtp::reply::{Reply, ReplyClass, ReplyParseError, ReplyParser};
+use crate::smtp::server::Param;
+
+#[derive(Debug, From)]
+pub enum SmtpClientError {
+    IOError(io::Error),
+    /// Rejected is returned when server replied with negative reply
+    Rejected(Reply),
+    /// AllRecipientsRejected is returned when server did not accept any recipient of message
+    AllRecipientsRejected(Vec<(String, Reply)>),
+    /// ReplyParseError is returned when server sent something which is not valid reply
+    ReplyParseError(ReplyParseError),
+    /// UnexpectedReply is returned when server sent reply while client did not wait for one
+    UnexpectedReply,
+    /// ConnectionClosed is returned when server closed connection while client waited for reply
+    ConnectionClosed,
+    /// MessageTooLarge is returned when message exceeds size limit declared by server with SIZE extension
+    MessageTooLarge,
+    /// Utf8NotSupported is returned when envelope contains non ASCII address and server does not support SMTPUTF8
+    Utf8NotSupported,
+    /// InvalidArgument is returned when argument of command contains chars which would break command line.
+    /// It contains invalid argument.
+    InvalidArgument(String),
+}
+
+impl fmt::Display for SmtpClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmtpClientError::IOError(e) => e.fmt(f),
+            SmtpClientError::Rejected(r) => write!(f, "server rejected command: {} {}", r.code, r.text()),
+            SmtpClientError::AllRecipientsRejected(r) => write!(f, "server rejected all {} recipients", r.len()),
+            SmtpClientError::ReplyParseError(e) => write!(f, "invalid reply: {}", e),
+            SmtpClientError::UnexpectedReply => write!(f, "server sent unexpected reply"),
+            SmtpClientError::ConnectionClosed => write!(f, "server closed connection"),
+            SmtpClientError::MessageTooLarge => write!(f, "message exceeds size limit of server"),
+            SmtpClientError::Utf8NotSupported => write!(f, "server does not support SMTPUTF8"),
+            SmtpClientError::InvalidArgument(a) => write!(f, "invalid command argument: {:?}", a),
+        }
+    }
+}
+
+impl error::Error for SmtpClientError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SmtpClientError::IOError(e) => e.source(),
+            SmtpClientError::ReplyParseError(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Extensions contains ESMTP extensions supported by server as listed in EHLO reply
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Extensions {
+    /// size is maximum message size. Zero means that there is no limit. See RFC 1870
+    pub size: Option<u64>,
+    /// See RFC 2920
+    pub pipelining: bool,
+    /// See RFC 6152
+    pub eight_bit_mime: bool,
+    /// See RFC 6531
+    pub smtp_utf8: bool,
+    /// chunking means BDAT command support. See RFC 3030
+    pub chunking: bool,
+    /// See RFC 3461
+    pub dsn: bool,
+    /// See RFC 2034
+    pub enhanced_status_codes: bool,
+    /// other contains keywords(with parameters) of unknown extensions
+    pub other: Vec<String>,
+}
+
+impl Extensions {
+    /// from_ehlo_reply parses extensions listed in EHLO reply. First line contains server domain so it's skipped.
+    pub fn from_ehlo_reply(reply: &Reply) -> Self {
+        let mut res = Self::default();
+        for line in reply.lines.iter().skip(1) {
+            let mut words = line.split_whitespace();
+            let keyword = match words.next() {
+                Some(keyword) => keyword.to_ascii_uppercase(),
+                None => continue,
+            };
+            match keyword.as_str() {
+                "SIZE" => res.size = Some(words.next().and_then(|s| s.parse().ok()).unwrap_or(0)),
+                "PIPELINING" => res.pipelining = true,
+                "8BITMIME" => res.eight_bit_mime = true,
+                "SMTPUTF8" => res.smtp_utf8 = true,
+                "CHUNKING" => res.chunking = true,
+                "DSN" => res.dsn = true,
+                "ENHANCEDSTATUSCODES" => res.enhanced_status_codes = true,
+                _ => res.other.push(line.to_string()),
+            }
+        }
+        res
+    }
+}
+
+/// Command is command sent by SMTP client. See RFC 5321 4.1.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Command {
+    Ehlo(String),
+    Helo(String),
+    MailFrom {
+        reverse_path: String,
+        params: Vec<Param>,
+    },
+    RcptTo {
+        forward_path: String,
+        params: Vec<Param>,
+    },
+    Data,
+    /// Bdat announces chunk of given size, which is sent right after command. See RFC 3030
+    Bdat {
+        size: usize,
+        is_last: bool,
+    },
+    Rset,
+    Noop,
+    Vrfy(String),
+    Quit,
+}
+
+fn write_params<W: Write>(w: &mut W, params: &[Param]) -> Result<(), io::Error> {
+    for (keyword, value) in params {
+        match value {
+            Some(value) => write!(w, " {}={}", keyword, value)?,
+            None => write!(w, " {}", keyword)?,
+        }
+    }
+    Ok(())
+}
+
+/// check_arg checks that argument does not contain line break or any of given chars,
+/// so it can't end command line and start another command
+fn check_arg(arg: &str, forbidden: &[char]) -> Result<(), SmtpClientError> {
+    if arg.contains(|c| c == '\r' || c == '\n' || forbidden.contains(&c)) {
+        return Err(SmtpClientError::InvalidArgument(arg.to_string()));
+    }
+    Ok(())
+}
+
+/// check_path checks that reverse or forward path can be put between angle brackets
+fn check_path(path: &str) -> Result<(), SmtpClientError> {
+    check_arg(path, &['<', '>'])
+}
+
+fn check_params(params: &[Param]) -> Result<(), SmtpClientError> {
+    for (keyword, value) in params {
+        check_arg(keyword, &[' ', '='])?;
+        if let Some(value) = value {
+            check_arg(value, &[' '])?;
+        }
+    }
+    Ok(())
+}
+
+impl Command {
+    /// check checks that arguments of command can't break command line
+    pub fn check(&self) -> Result<(), SmtpClientError> {
+        match self {
+            Command::Ehlo(domain) | Command::Helo(domain) => check_arg(domain, &[]),
+            Command::Vrfy(arg) => check_arg(arg, &['<', '>']),
+            Command::MailFrom { reverse_path: path, params } | Command::RcptTo { forward_path: path, params } => {
+                check_path(path)?;
+                check_params(params)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// write_to writes command line including CRLF
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        match self {
+            Command::Ehlo(domain) => write!(w, "EHLO {}", domain)?,
+            Command::Helo(domain) => write!(w, "HELO {}", domain)?,
+            Command::MailFrom { reverse_path, params } => {
+                write!(w, "MAIL FROM:<{}>", reverse_path)?;
+                write_params(w, params)?;
+            }
+            Command::RcptTo { forward_path, params } => {
+                write!(w, "RCPT TO:<{}>", forward_path)?;
+                write_params(w, params)?;
+            }
+            Command::Data => write!(w, "DATA")?,
+            Command::Bdat { size, is_last: true } => write!(w, "BDAT {} LAST", size)?,
+            Command::Bdat { size, is_last: false } => write!(w, "BDAT {}", size)?,
+            Command::Rset => write!(w, "RSET")?,
+            Command::Noop => write!(w, "NOOP")?,
+            Command::Vrfy(arg) => write!(w, "VRFY {}", arg)?,
+            Command::Quit => write!(w, "QUIT")?,
+        }
+        w.write_all(b"\r\n")
+    }
+}
+
+/// dot_stuff writes message content in format used after DATA command, including terminating `.` line.
+/// Lines starting with `.` get additional one and CRLF is appended to message if it's missing. See RFC 5321 4.5.2
+pub fn dot_stuff(message: &[u8], out: &mut Vec<u8>) {
+    out.reserve(message.len() + 5);
+    let mut is_line_start = true;
+    for b in message.iter().cloned() {
+        if is_line_start && b == b'.' {
+            out.push(b'.');
+        }
+        out.push(b);
+        is_line_start = b == b'\n';
+    }
+    if !message.is_empty() && !message.ends_with(b"\r\n") {
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b".\r\n");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expected {
+    Greeting,
+    Ehlo,
+    Other,
+}
+
+/// ClientSession is sans-IO part of SMTP client.
+/// It encodes commands into output buffer and parses replies from fed input.
+///
+/// It keeps track of commands which were not replied yet, so commands can be pipelined, and
+/// updates supported extensions when EHLO reply arrives.
+#[derive(Debug, Clone)]
+pub struct ClientSession {
+    parser: ReplyParser,
+    output: Vec<u8>,
+    expected: VecDeque<Expected>,
+    extensions: Extensions,
+}
+
+impl Default for ClientSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientSession {
+    /// new creates session which expects server greeting as first reply
+    pub fn new() -> Self {
+        let mut expected = VecDeque::new();
+        expected.push_back(Expected::Greeting);
+        Self {
+            parser: ReplyParser::new(),
+            output: Vec::new(),
+            expected,
+            extensions: Extensions::default(),
+        }
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// pending_replies returns number of replies client waits for
+    pub fn pending_replies(&self) -> usize {
+        self.expected.len()
+    }
+
+    /// send_command puts command into output.
+    /// Command with invalid arguments is rejected and nothing is put into output then.
+    pub fn send_command(&mut self, command: &Command) -> Result<(), SmtpClientError> {
+        command.check()?;
+        command.write_to(&mut self.output).expect("Writing to vector can't fail");
+        match command {
+            Command::Ehlo(_) => self.expected.push_back(Expected::Ehlo),
+            Command::Helo(_) => {
+                self.extensions = Extensions::default();
+                self.expected.push_back(Expected::Other);
+            }
+            // BDAT reply is sent after chunk data
+            _ => self.expected.push_back(Expected::Other),
+        }
+        Ok(())
+    }
+
+    /// send_message puts message content, dot-stuffed and terminated, into output.
+    /// It should be called after positive reply to DATA command.
+    pub fn send_message(&mut self, message: &[u8]) {
+        dot_stuff(message, &mut self.output);
+        self.expected.push_back(Expected::Other);
+    }
+
+    /// send_chunk puts BDAT command and chunk into output
+    pub fn send_chunk(&mut self, chunk: &[u8], is_last: bool) {
+        self.send_command(&Command::Bdat {
+            size: chunk.len(),
+            is_last,
+        }).expect("BDAT command has no invalid arguments");
+        self.output.extend_from_slice(chunk);
+    }
+
+    /// take_output returns bytes which should be sent to server
+    pub fn take_output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.output)
+    }
+
+    /// feed passes bytes received from server to session
+    pub fn feed(&mut self, data: &[u8]) {
+        self.parser.feed(data);
+    }
+
+    /// next_reply parses next complete reply from fed input.
+    /// It returns none when more input is required.
+    pub fn next_reply(&mut self) -> Result<Option<Reply>, SmtpClientError> {
+        let reply = match self.parser.next_reply()? {
+            Some(reply) => reply,
+            None => return Ok(None),
+        };
+        match self.expected.pop_front() {
+            Some(Expected::Ehlo) if reply.class() == ReplyClass::PositiveCompletion => {
+                self.extensions = Extensions::from_ehlo_reply(&reply);
+            }
+            Some(_) => {}
+            None => return Err(SmtpClientError::UnexpectedReply),
+        }
+        Ok(Some(reply))
+    }
+}
+
+/// SendReport describes result of sending message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendReport {
+    /// rejected_recipients contains recipients rejected by server along with reply
+    pub rejected_recipients: Vec<(String, Reply)>,
+    /// reply is reply received after message content was sent
+    pub reply: Reply,
+}
+
+/// SmtpClient drives `ClientSession` over any blocking stream like `TcpStream`.
+///
+/// # Example
+/// ```no_run
+///# use std::net::TcpStream;
+///# use smtpc::mail::builder::MessageBuilder;
+///# use smtpc::smtp::SmtpClient;
+///let message = MessageBuilder::new()
+///    .from("bob@example.com".parse().unwrap())
+///    .to("alice@example.com".parse().unwrap())
+///    .text("Hello")
+///    .to_bytes()
+///    .unwrap();
+///
+///let stream = TcpStream::connect("localhost:25").unwrap();
+///let mut client = SmtpClient::connect(stream).unwrap();
+///client.ehlo("client.example.com").unwrap();
+///client.send_mail("bob@example.com", &["alice@example.com"], &message).unwrap();
+///client.quit().unwrap();
+/// ```
+pub struct SmtpClient<S> {
+    stream: S,
+    session: ClientSession,
+    greeting: Reply,
+}
+
+impl<S: Read + Write> SmtpClient<S> {
+    /// connect reads server greeting from stream
+    pub fn connect(stream: S) -> Result<Self, SmtpClientError> {
+        let mut client = Self {
+            stream,
+            session: ClientSession::new(),
+            greeting: Reply::multiline(0, vec![]),
+        };
+        client.greeting = client.read_positive_reply()?;
+        Ok(client)
+    }
+
+    pub fn greeting(&self) -> &Reply {
+        &self.greeting
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        self.session.extensions()
+    }
+
+    /// into_inner returns underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn flush_output(&mut self) -> Result<(), SmtpClientError> {
+        let output = self.session.take_output();
+        self.stream.write_all(&output)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// read_reply sends any buffered output and reads next reply
+    fn read_reply(&mut self) -> Result<Reply, SmtpClientError> {
+        self.flush_output()?;
+        let mut buf = [0u8; 1024];
+        loop {
+            if let Some(reply) = self.session.next_reply()? {
+                return Ok(reply);
+            }
+            let sz = self.stream.read(&mut buf)?;
+            if sz == 0 {
+                return Err(SmtpClientError::ConnectionClosed);
+            }
+            self.session.feed(&buf[..sz]);
+        }
+    }
+
+    fn read_positive_reply(&mut self) -> Result<Reply, SmtpClientError> {
+        let reply = self.read_reply()?;
+        if reply.is_positive() {
+            Ok(reply)
+        } else {
+            Err(SmtpClientError::Rejected(reply))
+        }
+    }
+
+    /// command sends command and returns it's reply. Negative reply is returned as error.
+    pub fn command(&mut self, command: &Command) -> Result<Reply, SmtpClientError> {
+        self.session.send_command(command)?;
+        self.read_positive_reply()
+    }
+
+    /// ehlo greets server and negotiates extensions.
+    /// When server does not support EHLO command, HELO is used instead.
+    pub fn ehlo(&mut self, domain: &str) -> Result<&Extensions, SmtpClientError> {
+        self.session.send_command(&Command::Ehlo(domain.to_string()))?;
+        let reply = self.read_reply()?;
+        if reply.class() == ReplyClass::PermanentNegative {
+            self.command(&Command::Helo(domain.to_string()))?;
+        } else if !reply.is_positive() {
+            return Err(SmtpClientError::Rejected(reply));
+        }
+        Ok(self.session.extensions())
+    }
+
+    /// send_mail sends message in single transaction.
+    ///
+    /// It uses extensions supported by server: commands are pipelined if possible, BDAT is used instead of DATA
+    /// and SIZE, BODY and SMTPUTF8 parameters are added when needed.
+    /// Transaction succeeds if at least one recipient was accepted.
+    /// Paths are checked before any command is sent, so invalid one does not leave transaction started.
+    pub fn send_mail(&mut self, reverse_path: &str, recipients: &[&str], message: &[u8]) -> Result<SendReport, SmtpClientError> {
+        check_path(reverse_path)?;
+        for recipient in recipients {
+            check_path(recipient)?;
+        }
+        let extensions = self.session.extensions().clone();
+        let mut params = Vec::new();
+        if let Some(size) = extensions.size {
+            if size != 0 && message.len() as u64 > size {
+                return Err(SmtpClientError::MessageTooLarge);
+            }
+            params.push(("SIZE".to_string(), Some(message.len().to_string())));
+        }
+        if extensions.eight_bit_mime && !message.is_ascii() {
+            params.push(("BODY".to_string(), Some("8BITMIME".to_string())));
+        }
+        if !reverse_path.is_ascii() || recipients.iter().any(|r| !r.is_ascii()) {
+            if !extensions.smtp_utf8 {
+                return Err(SmtpClientError::Utf8NotSupported);
+            }
+            params.push(("SMTPUTF8".to_string(), None));
+        }
+
+        self.session.send_command(&Command::MailFrom {
+            reverse_path: reverse_path.to_string(),
+            params,
+        })?;
+        if !extensions.pipelining {
+            let reply = self.read_reply()?;
+            if !reply.is_positive() {
+                return Err(SmtpClientError::Rejected(reply));
+            }
+        }
+        let mut rejected_recipients = Vec::new();
+        for recipient in recipients {
+            self.session.send_command(&Command::RcptTo {
+                forward_path: recipient.to_string(),
+                params: vec![],
+            })?;
+            if !extensions.pipelining {
+                let reply = self.read_reply()?;
+                if !reply.is_positive() {
+                    rejected_recipients.push((recipient.to_string(), reply));
+                }
+            }
+        }
+        // DATA may be last command of pipelined group. See RFC 2920 3.1
+        let is_data_sent = !extensions.chunking && (extensions.pipelining || rejected_recipients.len() < recipients.len());
+        if is_data_sent {
+            self.session.send_command(&Command::Data)?;
+        }
+
+        let mut mail_reply = None;
+        if extensions.pipelining {
+            mail_reply = Some(self.read_reply()?);
+            for recipient in recipients {
+                let reply = self.read_reply()?;
+                if !reply.is_positive() {
+                    rejected_recipients.push((recipient.to_string(), reply));
+                }
+            }
+        }
+        let data_reply = if is_data_sent {
+            Some(self.read_reply()?)
+        } else {
+            None
+        };
+
+        if let Some(reply) = mail_reply.filter(|r| !r.is_positive()) {
+            return Err(SmtpClientError::Rejected(reply));
+        }
+        let are_all_rejected = rejected_recipients.len() == recipients.len();
+        match data_reply {
+            Some(reply) if reply.class() != ReplyClass::PositiveIntermediate => {
+                if are_all_rejected {
+                    return Err(SmtpClientError::AllRecipientsRejected(rejected_recipients));
+                }
+                return Err(SmtpClientError::Rejected(reply));
+            }
+            Some(_) if are_all_rejected => {
+                // server accepted DATA anyway, so empty message is sent to end transaction
+                self.session.send_message(b"");
+                self.read_reply()?;
+                return Err(SmtpClientError::AllRecipientsRejected(rejected_recipients));
+            }
+            Some(_) => self.session.send_message(message),
+            None if are_all_rejected => {
+                self.command(&Command::Rset)?;
+                return Err(SmtpClientError::AllRecipientsRejected(rejected_recipients));
+            }
+            None => self.session.send_chunk(message, true),
+        }
+
+        let reply = self.read_positive_reply()?;
+        Ok(SendReport {
+            rejected_recipients,
+            reply,
+        })
+    }
+
+    /// quit ends session and returns underlying stream
+    pub fn quit(mut self) -> Result<S, SmtpClientError> {
+        self.command(&Command::Quit)?;
+        Ok(self.stream)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use crate::smtp::server::{ServerEvent, ServerSession};
+
+    use super::*;
+
+    /// FakeServer is in memory stream which passes data to `ServerSession`
+    struct FakeServer {
+        session: ServerSession,
+        output: Cursor<Vec<u8>>,
+        messages: Vec<Vec<u8>>,
+    }
+
+    impl FakeServer {
+        fn new(session: ServerSession) -> Self {
+            Self {
+                session,
+                output: Cursor::new(Vec::new()),
+                messages: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for FakeServer {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            if self.output.position() as usize == self.output.get_ref().len() {
+                self.output = Cursor::new(self.session.take_output());
+            }
+            self.output.read(buf)
+        }
+    }
+
+    impl Write for FakeServer {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+            self.session.feed(buf);
+            while let Some(event) = self.session.next_event() {
+                match event {
+                    ServerEvent::RcptTo { forward_path, .. } if forward_path.starts_with("unknown") => {
+                        self.session.reject(Reply::new(550, "No such user"));
+                    }
+                    ServerEvent::DataStart => self.messages.push(Vec::new()),
+                    ServerEvent::DataChunk(chunk) => self.messages.last_mut().unwrap().extend(chunk),
+                    ServerEvent::MailFrom { .. } | ServerEvent::RcptTo { .. } |
+                    ServerEvent::DataEnd | ServerEvent::Verify(_) => self.session.accept(),
+                    _ => {}
+                }
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_can_parse_extensions() {
+        let reply = Reply::multiline(250, vec![
+            "mx.example.com greets you".into(),
+            "SIZE 1000".into(),
+            "pipelining".into(),
+            "8BITMIME".into(),
+            "SMTPUTF8".into(),
+            "CHUNKING".into(),
+            "DSN".into(),
+            "ENHANCEDSTATUSCODES".into(),
+            "AUTH PLAIN LOGIN".into(),
+        ]);
+        assert_eq!(Extensions::from_ehlo_reply(&reply), Extensions {
+            size: Some(1000),
+            pipelining: true,
+            eight_bit_mime: true,
+            smtp_utf8: true,
+            chunking: true,
+            dsn: true,
+            enhanced_status_codes: true,
+            other: vec!["AUTH PLAIN LOGIN".into()],
+        });
+    }
+
+    #[test]
+    fn test_can_dot_stuff() {
+        for (i, o) in [
+            ("", ".\r\n"),
+            ("a\r\n", "a\r\n.\r\n"),
+            ("a", "a\r\n.\r\n"),
+            (".a\r\n.\r\n..", "..a\r\n..\r\n...\r\n.\r\n"),
+        ].iter() {
+            let mut out = Vec::new();
+            dot_stuff(i.as_bytes(), &mut out);
+            assert_eq!(String::from_utf8(out).unwrap(), *o);
+        }
+    }
+
+    #[test]
+    fn test_session_can_parse_replies() {
+        let mut session = ClientSession::new();
+        session.send_command(&Command::Ehlo("a".into())).unwrap();
+        session.send_command(&Command::Noop).unwrap();
+        for chunk in [
+            &b"220 mx ESMTP"[..],
+            b"\r\n250-mx\r\n250-SIZE 100",
+            b"\r\n250 PIPELINING\r\n250 OK\r\n",
+        ].iter() {
+            session.feed(chunk);
+        }
+        assert_eq!(session.next_reply().unwrap(), Some(Reply::new(220, "mx ESMTP")));
+        assert_eq!(session.next_reply().unwrap().unwrap().lines.len(), 3);
+        assert_eq!(session.extensions().size, Some(100));
+        assert!(session.extensions().pipelining);
+        assert_eq!(session.next_reply().unwrap(), Some(Reply::new(250, "OK")));
+        assert_eq!(session.next_reply().unwrap(), None);
+        assert_eq!(session.pending_replies(), 0);
+
+        for i in [&b"250-a\r\n251 b\r\n"[..], b"abc\r\n", b"2500 OK\r\n"].iter() {
+            let mut session = ClientSession::new();
+            session.feed(i);
+            assert!(session.next_reply().is_err());
+        }
+    }
+
+    #[test]
+    fn test_client_can_send_mail() {
+        let message = b"Subject: Hi\r\n\r\n.Hello\r\n";
+        {
+            let session = ServerSession::new("mx").extension("SIZE 1000");
+            let mut client = SmtpClient::connect(FakeServer::new(session)).unwrap();
+            assert_eq!(client.greeting(), &Reply::new(220, "mx ESMTP ready"));
+            let extensions = client.ehlo("client").unwrap();
+            assert!(extensions.pipelining);
+            assert_eq!(extensions.size, Some(1000));
+
+            let report = client.send_mail("bob@example.com", &["alice@example.com", "unknown@example.com"], message).unwrap();
+            assert_eq!(report.rejected_recipients, vec![
+                ("unknown@example.com".to_string(), Reply::new(550, "No such user")),
+            ]);
+            assert_eq!(report.reply, Reply::new(250, "OK: message accepted"));
+
+            let err = client.send_mail("bob@example.com", &["unknown@example.com"], message).unwrap_err();
+            match err {
+                SmtpClientError::AllRecipientsRejected(r) => assert_eq!(r.len(), 1),
+                e => panic!("Unexpected error {:?}", e),
+            }
+
+            let server = client.quit().unwrap();
+            assert!(server.session.is_closed());
+            assert_eq!(server.messages, vec![message.to_vec()]);
+        }
+    }
+
+    #[test]
+    fn test_client_can_use_chunking() {
+        let replies = b"220 mx\r\n\
+            250-mx\r\n250 CHUNKING\r\n\
+            250 OK\r\n250 OK\r\n250 OK\r\n";
+        let stream = FakeStream {
+            input: Cursor::new(replies.to_vec()),
+            output: Vec::new(),
+        };
+        let mut client = SmtpClient::connect(stream).unwrap();
+        client.ehlo("client").unwrap();
+        client.send_mail("bob@example.com", &["alice@example.com"], b".Hi\r\n").unwrap();
+        assert_eq!(
+            String::from_utf8(client.into_inner().output).unwrap(),
+            "EHLO client\r\n\
+            MAIL FROM:<bob@example.com>\r\n\
+            RCPT TO:<alice@example.com>\r\n\
+            BDAT 5 LAST\r\n.Hi\r\n"
+        );
+    }
+
+    #[test]
+    fn test_rejects_command_injection() {
+        for c in [
+            Command::Ehlo("client\r\nRSET".into()),
+            Command::Vrfy("john\nQUIT".into()),
+            Command::MailFrom { reverse_path: "bob@example.com>\r\nRSET".into(), params: vec![] },
+            Command::RcptTo { forward_path: "x@y>".into(), params: vec![] },
+            Command::MailFrom { reverse_path: "".into(), params: vec![("SIZE".into(), Some("1\r\nRSET".into()))] },
+            Command::MailFrom { reverse_path: "".into(), params: vec![("BODY=8BITMIME SMTPUTF8".into(), None)] },
+        ].iter() {
+            let mut session = ClientSession::new();
+            match session.send_command(c) {
+                Err(SmtpClientError::InvalidArgument(_)) => {}
+                r => panic!("Unexpected result for {:?}: {:?}", c, r),
+            }
+            assert!(session.take_output().is_empty());
+            assert_eq!(session.pending_replies(), 1);
+        }
+
+        let stream = FakeStream {
+            input: Cursor::new(b"220 mx\r\n250 mx\r\n".to_vec()),
+            output: Vec::new(),
+        };
+        let mut client = SmtpClient::connect(stream).unwrap();
+        client.ehlo("client").unwrap();
+        let err = client.send_mail("bob@example.com", &["alice@example.com", "x@y>\r\nRCPT TO:<evil@example.com"], b"Hi\r\n");
+        match err {
+            Err(SmtpClientError::InvalidArgument(a)) => assert_eq!(a, "x@y>\r\nRCPT TO:<evil@example.com"),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert_eq!(String::from_utf8(client.into_inner().output).unwrap(), "EHLO client\r\n");
+    }
+
+    struct FakeStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for FakeStream {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for FakeStream {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,16 @@
+//! SMTP module implements SMTP protocol in sans-IO manner. See RFC 5321
+//!
+//! There is `ServerSession`, which is server side state machine and `ClientSession`, which is client side one.
+//! `SmtpClient` drives `ClientSession` over any blocking stream.
+//!
+//! Both of them use `Reply`, which may contain RFC 3463 `EnhancedStatusCode`.
+
+pub use client::*;
+pub use reply::*;
+pub use server::*;
+pub use status::*;
+
+mod client;
+mod reply;
+mod server;
+mod status;
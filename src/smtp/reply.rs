@@ -0,0 +1,395 @@
+use std::error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::{self, FromStr, Utf8Error};
+
+use crate::smtp::status::EnhancedStatusCode;
+
+/// MAX_REPLY_LINE_LENGTH is maximum length of reply line accepted by parser.
+/// RFC 5321 4.5.3.1.5 says it's 512 bytes but some servers send longer ones.
+pub const MAX_REPLY_LINE_LENGTH: usize = 4096;
+
+/// ReplyClass is meaning of first digit of reply code. See RFC 5321 4.2.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ReplyClass {
+    /// PositiveCompletion is 2xx reply: requested action was completed
+    PositiveCompletion,
+    /// PositiveIntermediate is 3xx reply: more information is required, like message content after DATA
+    PositiveIntermediate,
+    /// TransientNegative is 4xx reply: action was not taken but it may succeed later
+    TransientNegative,
+    /// PermanentNegative is 5xx reply: action was not taken and should not be retried
+    PermanentNegative,
+    /// Other is any other, invalid reply
+    Other,
+}
+
+/// Reply is response sent by SMTP server to client. See RFC 5321 4.2
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Reply {
+    pub code: u16,
+    /// enhanced_code is RFC 3463 status code which prefixes text of each line
+    pub enhanced_code: Option<EnhancedStatusCode>,
+    /// lines contains text of each reply line without code, separator and enhanced code
+    pub lines: Vec<String>,
+}
+
+impl Reply {
+    pub fn new(code: u16, text: &str) -> Self {
+        Self {
+            code,
+            enhanced_code: None,
+            lines: vec![text.to_string()],
+        }
+    }
+
+    pub fn multiline(code: u16, lines: Vec<String>) -> Self {
+        Self {
+            code,
+            enhanced_code: None,
+            lines,
+        }
+    }
+
+    pub fn with_enhanced_code(mut self, enhanced_code: EnhancedStatusCode) -> Self {
+        self.enhanced_code = Some(enhanced_code);
+        self
+    }
+
+    /// text returns text of all lines joined with new line char
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn class(&self) -> ReplyClass {
+        match self.code / 100 {
+            2 => ReplyClass::PositiveCompletion,
+            3 => ReplyClass::PositiveIntermediate,
+            4 => ReplyClass::TransientNegative,
+            5 => ReplyClass::PermanentNegative,
+            _ => ReplyClass::Other,
+        }
+    }
+
+    /// is_positive checks if reply code is 2xx or 3xx one
+    pub fn is_positive(&self) -> bool {
+        self.code >= 200 && self.code < 400
+    }
+
+    /// write_to writes reply in wire format, with `-` separator in all lines except the last one.
+    /// Enhanced code is written in each line.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        if self.lines.is_empty() {
+            return match self.enhanced_code {
+                Some(enhanced_code) => write!(w, "{} {}\r\n", self.code, enhanced_code),
+                None => write!(w, "{}\r\n", self.code),
+            };
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            let content = match self.enhanced_code {
+                Some(enhanced_code) if line.is_empty() => enhanced_code.to_string(),
+                Some(enhanced_code) => format!("{} {}", enhanced_code, line),
+                None => line.clone(),
+            };
+            if i + 1 < self.lines.len() {
+                write!(w, "{}-{}\r\n", self.code, content)?;
+            } else if content.is_empty() {
+                write!(w, "{}\r\n", self.code)?;
+            } else {
+                write!(w, "{} {}\r\n", self.code, content)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write_to(&mut res).expect("Writing to vector can't fail");
+        res
+    }
+}
+
+impl FromStr for Reply {
+    type Err = ReplyParseError;
+
+    /// from_str parses single complete reply
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = ReplyParser::new();
+        parser.feed(s.as_bytes());
+        let reply = parser.next_reply()?.ok_or(ReplyParseError::Incomplete)?;
+        if !parser.is_empty() {
+            return Err(ReplyParseError::TrailingData);
+        }
+        Ok(reply)
+    }
+}
+
+#[derive(Debug, From)]
+pub enum ReplyParseError {
+    NotUtf8(Utf8Error),
+    /// InvalidLine is returned when line does not start with three digit code and separator
+    InvalidLine,
+    /// CodeMismatch is returned when lines of multiline reply have different codes
+    CodeMismatch,
+    LineTooLong,
+    Incomplete,
+    TrailingData,
+}
+
+impl fmt::Display for ReplyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplyParseError::NotUtf8(e) => write!(f, "reply is not valid utf-8: {}", e),
+            ReplyParseError::InvalidLine => write!(f, "reply line does not start with code"),
+            ReplyParseError::CodeMismatch => write!(f, "lines of reply have different codes"),
+            ReplyParseError::LineTooLong => write!(f, "reply line is too long"),
+            ReplyParseError::Incomplete => write!(f, "reply is incomplete"),
+            ReplyParseError::TrailingData => write!(f, "unexpected data after reply"),
+        }
+    }
+}
+
+impl error::Error for ReplyParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ReplyParseError::NotUtf8(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+/// parse_reply_line parses code, separator and text of reply line like `250-PIPELINING`
+fn parse_reply_line(line: &str) -> Option<(u16, bool, &str)> {
+    let bytes = line.as_bytes();
+    if bytes.len() < 3 || !bytes[..3].iter().all(|b| b.is_ascii_digit()) || !(b'2'..=b'5').contains(&bytes[0]) {
+        return None;
+    }
+    let code = line[..3].parse().ok()?;
+    match bytes.get(3) {
+        None => Some((code, true, "")),
+        Some(b' ') => Some((code, true, &line[4..])),
+        Some(b'-') => Some((code, false, &line[4..])),
+        Some(_) => None,
+    }
+}
+
+/// ReplyParser parses replies from stream of bytes fed in arbitrary chunks. See RFC 5321 4.2.1
+///
+/// Enhanced status code is extracted when first line starts with one, which matches class of reply code.
+/// It's removed from other lines only if it's the same code.
+///
+/// # Example
+/// ```rust
+///# use smtpc::smtp::{ReplyParser, StatusSubject};
+///let mut parser = ReplyParser::new();
+///parser.feed(b"550-5.1.1 <x@y>: Recipient address\r\n550 5.1.1 rejected\r\n");
+///let reply = parser.next_reply().unwrap().unwrap();
+///assert_eq!(reply.code, 550);
+///assert_eq!(reply.enhanced_code.unwrap().subject, StatusSubject::Addressing);
+///assert_eq!(reply.text(), "<x@y>: Recipient address\nrejected");
+///assert!(parser.next_reply().unwrap().is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ReplyParser {
+    input: Vec<u8>,
+    // line which was too long is skipped up to it's end
+    is_discarding_line: bool,
+}
+
+impl ReplyParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        self.input.extend_from_slice(data);
+    }
+
+    /// is_empty checks if there is no data buffered by parser
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// next_reply parses next complete reply from fed data.
+    /// It returns none when more data is required.
+    ///
+    /// When error is returned, reply is discarded up to the end of line which caused it,
+    /// so next call parses data which follows that line.
+    pub fn next_reply(&mut self) -> Result<Option<Reply>, ReplyParseError> {
+        if self.is_discarding_line {
+            match self.input.iter().position(|b| *b == b'\n') {
+                Some(idx) => {
+                    self.input.drain(..=idx);
+                    self.is_discarding_line = false;
+                }
+                None => {
+                    self.input.clear();
+                    return Ok(None);
+                }
+            }
+        }
+        let (res, consumed) = self.parse_reply();
+        match &res {
+            Err(ReplyParseError::LineTooLong) if consumed == self.input.len() && !self.input.ends_with(b"\n") => {
+                self.input.clear();
+                self.is_discarding_line = true;
+            }
+            Ok(None) => {}
+            _ => {
+                self.input.drain(..consumed);
+            }
+        }
+        res
+    }
+
+    /// parse_reply parses reply at the beginning of input.
+    /// It returns number of bytes occupied by reply, or by it's lines up to the invalid one when it fails.
+    fn parse_reply(&self) -> (Result<Option<Reply>, ReplyParseError>, usize) {
+        let mut reply = Reply::multiline(0, Vec::new());
+        let mut consumed = 0;
+        loop {
+            let rest = &self.input[consumed..];
+            let idx = match rest.iter().position(|b| *b == b'\n') {
+                Some(idx) if idx > MAX_REPLY_LINE_LENGTH => return (Err(ReplyParseError::LineTooLong), consumed + idx + 1),
+                Some(idx) => idx,
+                None if rest.len() > MAX_REPLY_LINE_LENGTH => return (Err(ReplyParseError::LineTooLong), self.input.len()),
+                None => return (Ok(None), 0),
+            };
+            let line = &rest[..idx];
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            consumed += idx + 1;
+
+            let line = match str::from_utf8(line) {
+                Ok(line) => line,
+                Err(e) => return (Err(e.into()), consumed),
+            };
+            let (code, is_last, mut text) = match parse_reply_line(line) {
+                Some(l) => l,
+                None => return (Err(ReplyParseError::InvalidLine), consumed),
+            };
+            if reply.lines.is_empty() {
+                reply.code = code;
+            } else if reply.code != code {
+                return (Err(ReplyParseError::CodeMismatch), consumed);
+            }
+
+            let enhanced_code = EnhancedStatusCode::parse_prefix(text)
+                .filter(|(c, _)| u16::from(c.class.digit()) == code / 100);
+            if let Some((enhanced_code, rest)) = enhanced_code {
+                if reply.lines.is_empty() {
+                    reply.enhanced_code = Some(enhanced_code);
+                }
+                if reply.enhanced_code == Some(enhanced_code) {
+                    text = rest;
+                }
+            }
+            reply.lines.push(text.to_string());
+            if is_last {
+                break;
+            }
+        }
+        (Ok(Some(reply)), consumed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_write_reply() {
+        for (i, o) in vec![
+            (Reply::new(250, "OK"), "250 OK\r\n"),
+            (Reply::multiline(250, vec!["example.com".into(), "PIPELINING".into(), "8BITMIME".into()]),
+             "250-example.com\r\n250-PIPELINING\r\n250 8BITMIME\r\n"),
+            (Reply::new(354, ""), "354\r\n"),
+            (Reply::multiline(550, vec!["a".into(), "".into()]).with_enhanced_code("5.1.1".parse().unwrap()),
+             "550-5.1.1 a\r\n550 5.1.1\r\n"),
+        ].into_iter() {
+            assert_eq!(String::from_utf8(i.to_bytes()).unwrap(), o);
+            assert_eq!(o.parse::<Reply>().unwrap(), i);
+        }
+    }
+
+    #[test]
+    fn test_can_parse_reply() {
+        for (i, o) in [
+            ("250 OK\r\n", Some((250, None, vec!["OK"]))),
+            ("354\r\n", Some((354, None, vec![""]))),
+            ("250-mx\r\n250-PIPELINING\r\n250 8BITMIME\r\n", Some((250, None, vec!["mx", "PIPELINING", "8BITMIME"]))),
+            (
+                "550-5.1.1 <x@y>: Recipient address rejected\n550 5.1.1 User unknown\r\n",
+                Some((550, Some("5.1.1"), vec!["<x@y>: Recipient address rejected", "User unknown"])),
+            ),
+            ("250-2.0.0 a\r\n250 2.1.0 b\r\n", Some((250, Some("2.0.0"), vec!["a", "2.1.0 b"]))),
+            ("250 5.0.0 OK\r\n", Some((250, None, vec!["5.0.0 OK"]))),
+            ("250 2.0.0\r\n", Some((250, Some("2.0.0"), vec![""]))),
+            ("250-OK\r\n", None),
+            ("250-OK\r\n251 OK\r\n", None),
+            ("250OK\r\n", None),
+            ("OK\r\n", None),
+            ("250 OK\r\n250 OK\r\n", None),
+        ].iter() {
+            let res = i.parse::<Reply>().ok();
+            let o = o.as_ref().map(|(code, enhanced_code, lines)| Reply {
+                code: *code,
+                enhanced_code: enhanced_code.map(|c| c.parse().unwrap()),
+                lines: lines.iter().map(|l| l.to_string()).collect(),
+            });
+            assert_eq!(res, o, "input {:?}", i);
+        }
+    }
+
+    #[test]
+    fn test_parser_can_parse_replies_in_chunks() {
+        let data = b"220 mx ready\r\n250-mx\r\n250 SIZE 100\r\n421 4.4.2 Timeout\r\n";
+        let mut parser = ReplyParser::new();
+        let mut replies = Vec::new();
+        for b in data.iter() {
+            parser.feed(&[*b]);
+            while let Some(reply) = parser.next_reply().unwrap() {
+                replies.push(reply);
+            }
+        }
+        assert_eq!(replies, vec![
+            Reply::new(220, "mx ready"),
+            Reply::multiline(250, vec!["mx".into(), "SIZE 100".into()]),
+            Reply::new(421, "Timeout").with_enhanced_code("4.4.2".parse().unwrap()),
+        ]);
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn test_parser_recovers_after_error() {
+        let long_line = format!("250 {}\r\n", "a".repeat(MAX_REPLY_LINE_LENGTH));
+        for (i, o) in [
+            (b"hello\r\n".to_vec(), "reply line does not start with code"),
+            (b"250-a\r\n251 b\r\n".to_vec(), "lines of reply have different codes"),
+            (b"250 \xff\r\n".to_vec(), "reply is not valid utf-8: invalid utf-8 sequence of 1 bytes from index 4"),
+            (long_line.into_bytes(), "reply line is too long"),
+        ].iter() {
+            let mut data = i.clone();
+            data.extend_from_slice(b"250 OK\r\n");
+            for chunk_size in [1, 7, data.len()].iter().cloned() {
+                let mut parser = ReplyParser::new();
+                let mut errors = Vec::new();
+                let mut replies = Vec::new();
+                for chunk in data.chunks(chunk_size) {
+                    parser.feed(chunk);
+                    loop {
+                        match parser.next_reply() {
+                            Ok(Some(reply)) => replies.push(reply),
+                            Ok(None) => break,
+                            Err(e) => errors.push(e.to_string()),
+                        }
+                    }
+                }
+                assert_eq!(errors, vec![o.to_string()], "{}", chunk_size);
+                assert_eq!(replies, vec![Reply::new(250, "OK")]);
+                assert!(parser.is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,622 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::str;
+
+use crate::smtp::reply::Reply;
+
+/// MAX_COMMAND_LINE_LENGTH is maximum length of command line including CRLF. See RFC 5321 4.5.3.1.4
+pub const MAX_COMMAND_LINE_LENGTH: usize = 512;
+
+/// Param is ESMTP parameter of MAIL or RCPT command like `SIZE=1000` or `BODY=8BITMIME`.
+/// Keyword is always upper case.
+pub type Param = (String, Option<String>);
+
+/// ServerEvent is event emitted by `ServerSession` when client does something meaningful.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ServerEvent {
+    /// Hello is emitted after successful HELO or EHLO command. It aborts any transaction in progress.
+    Hello {
+        domain: String,
+        is_extended: bool,
+    },
+    /// MailFrom is emitted when client starts transaction. Empty reverse path is null reverse path.
+    /// It has to be accepted or rejected.
+    MailFrom {
+        reverse_path: String,
+        params: Vec<Param>,
+    },
+    /// RcptTo is emitted when client adds recipient. It has to be accepted or rejected.
+    RcptTo {
+        forward_path: String,
+        params: Vec<Param>,
+    },
+    /// DataStart is emitted when DATA command was accepted and message content follows.
+    DataStart,
+    /// DataChunk contains part of dot-unstuffed message content.
+    DataChunk(Vec<u8>),
+    /// DataEnd is emitted when entire message was received. It has to be accepted or rejected.
+    DataEnd,
+    /// Reset is emitted when client aborts transaction with RSET command
+    Reset,
+    /// Verify is emitted for VRFY command. It has to be accepted or rejected.
+    Verify(String),
+    /// Quit is emitted when client ends session. Session won't process any more input after it.
+    Quit,
+}
+
+/// ServerState is state of SMTP session from server's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ServerState {
+    /// WaitingForHello means that client did not send HELO or EHLO yet
+    WaitingForHello,
+    /// Ready means that no transaction is in progress
+    Ready,
+    /// Mail means that MAIL command was accepted but no recipient was
+    Mail,
+    /// Rcpt means that at least one recipient was accepted
+    Rcpt,
+    /// Data means that message content is being received
+    Data,
+    /// Closed means that client sent QUIT command
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    Mail,
+    Rcpt,
+    Verify,
+    Data,
+}
+
+impl Pending {
+    fn default_reply(self) -> Reply {
+        match self {
+            Pending::Mail | Pending::Rcpt => Reply::new(250, "OK"),
+            Pending::Verify => Reply::new(252, "Cannot VRFY user, but will accept message and attempt delivery"),
+            Pending::Data => Reply::new(250, "OK: message accepted"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineState {
+    LineStart,
+    MidLine,
+    AfterCr,
+}
+
+/// ServerSession is sans-IO SMTP server session state machine. See RFC 5321
+///
+/// It does not perform any IO on it's own. Instead bytes received from client are passed to `feed`,
+/// events are taken with `next_event` and bytes which should be sent to client are taken with `take_output`.
+/// Greeting is put into output when session is created.
+///
+/// Some events(`MailFrom`, `RcptTo`, `Verify` and `DataEnd`) require decision which is passed with `accept`
+/// or `reject`. Until it's made no more input is processed, so pipelined commands are handled in order.
+/// Other commands are replied by session itself.
+///
+/// Message content passed in `DataChunk` events can be fed directly to `MimeEventReader` or `Message::parse`.
+///
+/// # Example
+/// ```rust
+///# use smtpc::smtp::{ServerEvent, ServerSession};
+///let mut session = ServerSession::new("mx.example.com");
+///session.feed(b"EHLO client.example.com\r\nMAIL FROM:<bob@example.com>\r\n");
+///
+///let mut senders = Vec::new();
+///while let Some(event) = session.next_event() {
+///    if let ServerEvent::MailFrom { reverse_path, .. } = event {
+///        senders.push(reverse_path);
+///        session.accept();
+///    }
+///}
+///assert_eq!(senders, vec!["bob@example.com".to_string()]);
+///assert!(session.take_output().ends_with(b"250 OK\r\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServerSession {
+    hostname: String,
+    extensions: Vec<String>,
+    state: ServerState,
+    pending: Option<Pending>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    events: VecDeque<ServerEvent>,
+    data_line_state: LineState,
+    is_discarding_line: bool,
+    // recipients of current transaction
+    accepted_recipients: usize,
+    rejected_recipients: usize,
+}
+
+impl ServerSession {
+    /// new creates session and puts greeting into output
+    pub fn new(hostname: &str) -> Self {
+        let mut session = Self {
+            hostname: hostname.to_string(),
+            extensions: vec!["PIPELINING".to_string(), "8BITMIME".to_string()],
+            state: ServerState::WaitingForHello,
+            pending: None,
+            input: Vec::new(),
+            output: Vec::new(),
+            events: VecDeque::new(),
+            data_line_state: LineState::LineStart,
+            is_discarding_line: false,
+            accepted_recipients: 0,
+            rejected_recipients: 0,
+        };
+        session.write_reply(Reply::new(220, &format!("{} ESMTP ready", hostname)));
+        session
+    }
+
+    /// extension adds extension keyword(with optional parameters) listed in EHLO reply.
+    /// `PIPELINING` and `8BITMIME` are listed by default.
+    pub fn extension(mut self, extension: &str) -> Self {
+        self.extensions.push(extension.to_string());
+        self
+    }
+
+    pub fn state(&self) -> ServerState {
+        self.state
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == ServerState::Closed
+    }
+
+    /// feed passes bytes received from client to session
+    pub fn feed(&mut self, data: &[u8]) {
+        if !self.is_closed() {
+            self.input.extend_from_slice(data);
+        }
+    }
+
+    /// take_output returns bytes which should be sent to client
+    pub fn take_output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.output)
+    }
+
+    /// next_event processes fed input and returns next event.
+    /// It returns none when more input is required or when event awaits decision.
+    pub fn next_event(&mut self) -> Option<ServerEvent> {
+        while self.events.is_empty() && self.pending.is_none() && !self.is_closed() {
+            let has_progressed = if self.state == ServerState::Data {
+                self.process_data()
+            } else {
+                self.process_command()
+            };
+            if !has_progressed {
+                break;
+            }
+        }
+        self.events.pop_front()
+    }
+
+    /// accept accepts event awaiting decision with default positive reply.
+    ///
+    /// # Panics
+    /// It panics when no event awaits decision.
+    pub fn accept(&mut self) {
+        let pending = self.pending.expect("No event awaits decision");
+        self.respond(pending.default_reply());
+    }
+
+    /// reject rejects event awaiting decision with given reply.
+    ///
+    /// # Panics
+    /// It panics when no event awaits decision or reply is not negative one.
+    pub fn reject(&mut self, reply: Reply) {
+        assert!(!reply.is_positive(), "Reply rejecting event has to be negative");
+        self.respond(reply);
+    }
+
+    /// respond passes decision to event awaiting it. Decision is positive when reply is positive.
+    ///
+    /// # Panics
+    /// It panics when no event awaits decision.
+    pub fn respond(&mut self, reply: Reply) {
+        let pending = self.pending.take().expect("No event awaits decision");
+        let is_positive = reply.is_positive();
+        self.write_reply(reply);
+        match pending {
+            Pending::Mail if is_positive => self.start_transaction(ServerState::Mail),
+            Pending::Rcpt if is_positive => {
+                self.accepted_recipients += 1;
+                self.state = ServerState::Rcpt;
+            }
+            Pending::Rcpt => self.rejected_recipients += 1,
+            Pending::Data => self.start_transaction(ServerState::Ready),
+            _ => {}
+        }
+    }
+
+    /// start_transaction forgets recipients of previous transaction and sets state
+    fn start_transaction(&mut self, state: ServerState) {
+        self.accepted_recipients = 0;
+        self.rejected_recipients = 0;
+        self.state = state;
+    }
+
+    fn write_reply(&mut self, reply: Reply) {
+        reply.write_to(&mut self.output).expect("Writing to vector can't fail");
+    }
+
+    /// process_command processes single command line if it's available
+    fn process_command(&mut self) -> bool {
+        let idx = match self.input.iter().position(|b| *b == b'\n') {
+            Some(idx) => idx,
+            None => {
+                if self.input.len() > MAX_COMMAND_LINE_LENGTH {
+                    self.input.clear();
+                    if !self.is_discarding_line {
+                        self.is_discarding_line = true;
+                        self.write_reply(Reply::new(500, "Line too long"));
+                    }
+                }
+                return false;
+            }
+        };
+        let mut line: Vec<u8> = self.input.drain(..=idx).collect();
+        if self.is_discarding_line {
+            self.is_discarding_line = false;
+            return true;
+        }
+        if line.len() > MAX_COMMAND_LINE_LENGTH {
+            self.write_reply(Reply::new(500, "Line too long"));
+            return true;
+        }
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        match str::from_utf8(&line) {
+            Ok(line) => self.handle_command(line),
+            Err(_) => self.write_reply(Reply::new(500, "Syntax error, command unrecognized")),
+        }
+        true
+    }
+
+    fn handle_command(&mut self, line: &str) {
+        let (verb, args) = match line.find(' ') {
+            Some(idx) => (&line[..idx], line[idx + 1..].trim()),
+            None => (line, ""),
+        };
+        match verb.to_ascii_uppercase().as_str() {
+            "HELO" | "EHLO" => {
+                if args.is_empty() {
+                    self.write_reply(Reply::new(501, "Syntax error in parameters or arguments"));
+                    return;
+                }
+                let is_extended = verb.eq_ignore_ascii_case("EHLO");
+                self.start_transaction(ServerState::Ready);
+                let reply = if is_extended {
+                    let lines = std::iter::once(self.hostname.clone())
+                        .chain(self.extensions.iter().cloned())
+                        .collect();
+                    Reply::multiline(250, lines)
+                } else {
+                    Reply::new(250, &self.hostname)
+                };
+                self.write_reply(reply);
+                self.events.push_back(ServerEvent::Hello {
+                    domain: args.to_string(),
+                    is_extended,
+                });
+            }
+            "MAIL" => {
+                match self.state {
+                    ServerState::WaitingForHello => {
+                        self.write_reply(Reply::new(503, "Send HELO or EHLO first"));
+                        return;
+                    }
+                    ServerState::Mail | ServerState::Rcpt => {
+                        self.write_reply(Reply::new(503, "Nested MAIL command"));
+                        return;
+                    }
+                    _ => {}
+                }
+                match parse_path_argument(args, "FROM:") {
+                    Some((reverse_path, params)) => {
+                        self.pending = Some(Pending::Mail);
+                        self.events.push_back(ServerEvent::MailFrom { reverse_path, params });
+                    }
+                    None => self.write_reply(Reply::new(501, "Syntax error in parameters or arguments")),
+                }
+            }
+            "RCPT" => {
+                if self.state != ServerState::Mail && self.state != ServerState::Rcpt {
+                    self.write_reply(Reply::new(503, "Need MAIL command first"));
+                    return;
+                }
+                match parse_path_argument(args, "TO:") {
+                    Some((ref forward_path, _)) if forward_path.is_empty() => {
+                        self.write_reply(Reply::new(501, "Syntax error in parameters or arguments"));
+                    }
+                    Some((forward_path, params)) => {
+                        self.pending = Some(Pending::Rcpt);
+                        self.events.push_back(ServerEvent::RcptTo { forward_path, params });
+                    }
+                    None => self.write_reply(Reply::new(501, "Syntax error in parameters or arguments")),
+                }
+            }
+            "DATA" => {
+                if !args.is_empty() {
+                    self.write_reply(Reply::new(501, "Syntax error in parameters or arguments"));
+                    return;
+                }
+                // pipelined DATA may follow rejected MAIL or RCPT commands, so transaction is checked explicitly.
+                // See RFC 2920 3.1
+                let has_reverse_path = self.state == ServerState::Mail || self.state == ServerState::Rcpt;
+                if !has_reverse_path {
+                    self.write_reply(Reply::new(503, "Need MAIL command first"));
+                } else if self.accepted_recipients == 0 && self.rejected_recipients > 0 {
+                    self.write_reply(Reply::new(554, "No valid recipients"));
+                } else if self.accepted_recipients == 0 {
+                    self.write_reply(Reply::new(503, "No valid recipients"));
+                } else {
+                    self.state = ServerState::Data;
+                    self.data_line_state = LineState::LineStart;
+                    self.write_reply(Reply::new(354, "Start mail input; end with <CRLF>.<CRLF>"));
+                    self.events.push_back(ServerEvent::DataStart);
+                }
+            }
+            "RSET" => {
+                if !args.is_empty() {
+                    self.write_reply(Reply::new(501, "Syntax error in parameters or arguments"));
+                    return;
+                }
+                if self.state != ServerState::WaitingForHello {
+                    self.start_transaction(ServerState::Ready);
+                }
+                self.write_reply(Reply::new(250, "OK"));
+                self.events.push_back(ServerEvent::Reset);
+            }
+            "NOOP" => self.write_reply(Reply::new(250, "OK")),
+            "VRFY" => {
+                if args.is_empty() {
+                    self.write_reply(Reply::new(501, "Syntax error in parameters or arguments"));
+                    return;
+                }
+                self.pending = Some(Pending::Verify);
+                self.events.push_back(ServerEvent::Verify(args.to_string()));
+            }
+            "QUIT" => {
+                self.state = ServerState::Closed;
+                self.input.clear();
+                self.write_reply(Reply::new(221, &format!("{} closing connection", self.hostname)));
+                self.events.push_back(ServerEvent::Quit);
+            }
+            _ => self.write_reply(Reply::new(500, "Syntax error, command unrecognized")),
+        }
+    }
+
+    /// process_data dot-unstuffs available message content and detects it's end. See RFC 5321 4.5.2
+    fn process_data(&mut self) -> bool {
+        let mut chunk = Vec::with_capacity(self.input.len());
+        let mut consumed = 0;
+        let mut is_done = false;
+        while consumed < self.input.len() {
+            let b = self.input[consumed];
+            if self.data_line_state == LineState::LineStart && b == b'.' {
+                let rest = &self.input[consumed..];
+                if rest.len() < 3 && b".\r\n".starts_with(rest) {
+                    // can't tell if it's end of data yet
+                    break;
+                }
+                consumed += 1;
+                if rest.starts_with(b".\r\n") {
+                    consumed += 2;
+                    is_done = true;
+                    break;
+                }
+                self.data_line_state = LineState::MidLine;
+                continue;
+            }
+            chunk.push(b);
+            consumed += 1;
+            self.data_line_state = match b {
+                b'\r' => LineState::AfterCr,
+                b'\n' if self.data_line_state == LineState::AfterCr => LineState::LineStart,
+                _ => LineState::MidLine,
+            };
+        }
+        self.input.drain(..consumed);
+
+        if !chunk.is_empty() {
+            self.events.push_back(ServerEvent::DataChunk(chunk));
+        }
+        if is_done {
+            self.pending = Some(Pending::Data);
+            self.events.push_back(ServerEvent::DataEnd);
+        }
+        consumed > 0
+    }
+}
+
+/// parse_path_argument parses argument of MAIL or RCPT command like `FROM:<a@example.com> SIZE=100`
+fn parse_path_argument(args: &str, prefix: &str) -> Option<(String, Vec<Param>)> {
+    if args.len() < prefix.len() || !args[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    let args = args[prefix.len()..].trim_start();
+    let (path, rest) = if let Some(args) = args.strip_prefix('<') {
+        let idx = args.find('>')?;
+        (&args[..idx], &args[idx + 1..])
+    } else {
+        // some clients do not put path in angle brackets
+        match args.find(' ') {
+            Some(idx) => (&args[..idx], &args[idx..]),
+            None => (args, ""),
+        }
+    };
+    // source route is ignored. See RFC 5321 4.1.2
+    let path = match path.find(':') {
+        Some(idx) if path.starts_with('@') => &path[idx + 1..],
+        _ => path,
+    };
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    let params = rest.split_whitespace()
+        .map(|p| match p.find('=') {
+            Some(idx) => (p[..idx].to_ascii_uppercase(), Some(p[idx + 1..].to_string())),
+            None => (p.to_ascii_uppercase(), None),
+        })
+        .collect();
+    Some((path.to_string(), params))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run_session(session: &mut ServerSession, input: &[u8], byte_by_byte: bool) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        let chunk_size = if byte_by_byte { 1 } else { input.len().max(1) };
+        for chunk in input.chunks(chunk_size) {
+            session.feed(chunk);
+            while let Some(event) = session.next_event() {
+                match &event {
+                    ServerEvent::MailFrom { reverse_path, .. } if reverse_path == "spammer@example.com" => {
+                        session.reject(Reply::new(550, "Go away"));
+                    }
+                    ServerEvent::RcptTo { forward_path, .. } if forward_path == "nobody@example.com" => {
+                        session.reject(Reply::new(550, "No such user"));
+                    }
+                    ServerEvent::MailFrom { .. } | ServerEvent::RcptTo { .. } |
+                    ServerEvent::Verify(_) | ServerEvent::DataEnd => session.accept(),
+                    _ => {}
+                }
+                match (events.last_mut(), event) {
+                    (Some(ServerEvent::DataChunk(prev)), ServerEvent::DataChunk(next)) => prev.extend(next),
+                    (_, event) => events.push(event),
+                }
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_session_can_receive_message() {
+        let input = b"EHLO client.example.com\r\n\
+            MAIL FROM:<bob@example.com> SIZE=100 body=8BITMIME\r\n\
+            RCPT TO:<@relay.example.com:alice@example.com>\r\n\
+            RCPT TO:<john@example.com>\r\n\
+            DATA\r\n\
+            Subject: Hi\r\n\
+            \r\n\
+            ..Line starting with dot\r\n\
+            .\r\n\
+            QUIT\r\n";
+        for byte_by_byte in [false, true].iter().cloned() {
+            let mut session = ServerSession::new("mx.example.com");
+            let events = run_session(&mut session, input, byte_by_byte);
+            assert_eq!(events, vec![
+                ServerEvent::Hello {
+                    domain: "client.example.com".into(),
+                    is_extended: true,
+                },
+                ServerEvent::MailFrom {
+                    reverse_path: "bob@example.com".into(),
+                    params: vec![
+                        ("SIZE".into(), Some("100".into())),
+                        ("BODY".into(), Some("8BITMIME".into())),
+                    ],
+                },
+                ServerEvent::RcptTo {
+                    forward_path: "alice@example.com".into(),
+                    params: vec![],
+                },
+                ServerEvent::RcptTo {
+                    forward_path: "john@example.com".into(),
+                    params: vec![],
+                },
+                ServerEvent::DataStart,
+                ServerEvent::DataChunk(b"Subject: Hi\r\n\r\n.Line starting with dot\r\n".to_vec()),
+                ServerEvent::DataEnd,
+                ServerEvent::Quit,
+            ]);
+            assert!(session.is_closed());
+            assert_eq!(
+                String::from_utf8(session.take_output()).unwrap(),
+                "220 mx.example.com ESMTP ready\r\n\
+                250-mx.example.com\r\n\
+                250-PIPELINING\r\n\
+                250 8BITMIME\r\n\
+                250 OK\r\n\
+                250 OK\r\n\
+                250 OK\r\n\
+                354 Start mail input; end with <CRLF>.<CRLF>\r\n\
+                250 OK: message accepted\r\n\
+                221 mx.example.com closing connection\r\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_session_replies_to_invalid_commands() {
+        for (i, o) in [
+            ("MAIL FROM:<a@example.com>\r\n", "503 Send HELO or EHLO first\r\n"),
+            ("HELO\r\n", "501 Syntax error in parameters or arguments\r\n"),
+            ("HELO a\r\nRCPT TO:<a@example.com>\r\n", "250 mx\r\n503 Need MAIL command first\r\n"),
+            ("HELO a\r\nMAIL FROM:<>\r\nDATA\r\n", "250 mx\r\n250 OK\r\n503 No valid recipients\r\n"),
+            ("HELO a\r\nMAIL FROM:<>\r\nMAIL FROM:<>\r\n", "250 mx\r\n250 OK\r\n503 Nested MAIL command\r\n"),
+            ("HELO a\r\nMAIL TO:<a@example.com>\r\n", "250 mx\r\n501 Syntax error in parameters or arguments\r\n"),
+            ("HELO a\r\nMAIL FROM:<spammer@example.com>\r\nRCPT TO:<a@example.com>\r\n", "250 mx\r\n550 Go away\r\n503 Need MAIL command first\r\n"),
+            ("HELO a\r\nMAIL FROM:<>\r\nRSET\r\nRCPT TO:<a@example.com>\r\n", "250 mx\r\n250 OK\r\n250 OK\r\n503 Need MAIL command first\r\n"),
+            ("NOOP\r\nFOO\r\n", "250 OK\r\n500 Syntax error, command unrecognized\r\n"),
+            ("VRFY bob\r\n", "252 Cannot VRFY user, but will accept message and attempt delivery\r\n"),
+            (&format!("HELO {}\r\nNOOP\r\n", "a".repeat(1000)), "500 Line too long\r\n250 OK\r\n"),
+        ].iter() {
+            let mut session = ServerSession::new("mx");
+            session.take_output();
+            run_session(&mut session, i.as_bytes(), false);
+            assert_eq!(String::from_utf8(session.take_output()).unwrap(), *o);
+        }
+    }
+
+    #[test]
+    fn test_session_waits_for_decision() {
+        let mut session = ServerSession::new("mx");
+        session.feed(b"HELO a\r\nMAIL FROM:<>\r\nRCPT TO:<a@example.com>\r\n");
+        assert!(session.next_event().is_some());
+        assert!(session.next_event().is_some());
+        assert_eq!(session.next_event(), None);
+        assert_eq!(session.state(), ServerState::Ready);
+        session.accept();
+        assert_eq!(session.state(), ServerState::Mail);
+        assert!(session.next_event().is_some());
+        session.reject(Reply::new(550, "No such user"));
+        assert_eq!(session.state(), ServerState::Mail);
+        assert_eq!(session.next_event(), None);
+    }
+
+    #[test]
+    fn test_session_rejects_pipelined_data_without_transaction() {
+        for (i, o) in [
+            (
+                "EHLO a\r\nMAIL FROM:<spammer@example.com>\r\nRCPT TO:<a@example.com>\r\nDATA\r\n",
+                "550 Go away\r\n503 Need MAIL command first\r\n503 Need MAIL command first\r\n",
+            ),
+            (
+                "EHLO a\r\nMAIL FROM:<a@example.com>\r\nRCPT TO:<nobody@example.com>\r\nRCPT TO:<nobody@example.com>\r\nDATA\r\n",
+                "250 OK\r\n550 No such user\r\n550 No such user\r\n554 No valid recipients\r\n",
+            ),
+            (
+                "EHLO a\r\nMAIL FROM:<a@example.com>\r\nRCPT TO:<nobody@example.com>\r\nRCPT TO:<b@example.com>\r\nDATA\r\n",
+                "250 OK\r\n550 No such user\r\n250 OK\r\n354 Start mail input; end with <CRLF>.<CRLF>\r\n",
+            ),
+        ].iter() {
+            for byte_by_byte in [false, true].iter().cloned() {
+                let mut session = ServerSession::new("mx");
+                let events = run_session(&mut session, i.as_bytes(), byte_by_byte);
+                let output = String::from_utf8(session.take_output()).unwrap();
+                assert!(output.ends_with(o), "{:?}", output);
+                assert_eq!(events.contains(&ServerEvent::DataStart), o.contains("354"));
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// StatusClass is class of enhanced status code. See RFC 3463 3.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum StatusClass {
+    Success,
+    PersistentTransientFailure,
+    PermanentFailure,
+}
+
+impl StatusClass {
+    pub fn from_digit(digit: u8) -> Option<Self> {
+        match digit {
+            2 => Some(StatusClass::Success),
+            4 => Some(StatusClass::PersistentTransientFailure),
+            5 => Some(StatusClass::PermanentFailure),
+            _ => None,
+        }
+    }
+
+    pub fn digit(self) -> u8 {
+        match self {
+            StatusClass::Success => 2,
+            StatusClass::PersistentTransientFailure => 4,
+            StatusClass::PermanentFailure => 5,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            StatusClass::Success => "Success",
+            StatusClass::PersistentTransientFailure => "Persistent Transient Failure",
+            StatusClass::PermanentFailure => "Permanent Failure",
+        }
+    }
+}
+
+/// StatusSubject is subject of enhanced status code. See RFC 3463 3.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum StatusSubject {
+    Other,
+    Addressing,
+    Mailbox,
+    MailSystem,
+    NetworkAndRouting,
+    MailDeliveryProtocol,
+    MessageContentOrMedia,
+    SecurityOrPolicy,
+    /// Unknown is any subject not defined in RFC 3463
+    Unknown(u16),
+}
+
+impl StatusSubject {
+    pub fn from_number(number: u16) -> Self {
+        match number {
+            0 => StatusSubject::Other,
+            1 => StatusSubject::Addressing,
+            2 => StatusSubject::Mailbox,
+            3 => StatusSubject::MailSystem,
+            4 => StatusSubject::NetworkAndRouting,
+            5 => StatusSubject::MailDeliveryProtocol,
+            6 => StatusSubject::MessageContentOrMedia,
+            7 => StatusSubject::SecurityOrPolicy,
+            n => StatusSubject::Unknown(n),
+        }
+    }
+
+    pub fn number(self) -> u16 {
+        match self {
+            StatusSubject::Other => 0,
+            StatusSubject::Addressing => 1,
+            StatusSubject::Mailbox => 2,
+            StatusSubject::MailSystem => 3,
+            StatusSubject::NetworkAndRouting => 4,
+            StatusSubject::MailDeliveryProtocol => 5,
+            StatusSubject::MessageContentOrMedia => 6,
+            StatusSubject::SecurityOrPolicy => 7,
+            StatusSubject::Unknown(n) => n,
+        }
+    }
+
+    pub fn description(self) -> Option<&'static str> {
+        Some(match self {
+            StatusSubject::Other => "Other or Undefined Status",
+            StatusSubject::Addressing => "Addressing Status",
+            StatusSubject::Mailbox => "Mailbox Status",
+            StatusSubject::MailSystem => "Mail System Status",
+            StatusSubject::NetworkAndRouting => "Network and Routing Status",
+            StatusSubject::MailDeliveryProtocol => "Mail Delivery Protocol Status",
+            StatusSubject::MessageContentOrMedia => "Message Content or Media Status",
+            StatusSubject::SecurityOrPolicy => "Security or Policy Status",
+            StatusSubject::Unknown(_) => return None,
+        })
+    }
+}
+
+/// EnhancedStatusCode is status code like `5.1.1` which may follow basic SMTP reply code. See RFC 3463 and RFC 2034
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct EnhancedStatusCode {
+    pub class: StatusClass,
+    pub subject: StatusSubject,
+    pub detail: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnhancedStatusCodeParseError;
+
+impl fmt::Display for EnhancedStatusCodeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid enhanced status code")
+    }
+}
+
+impl error::Error for EnhancedStatusCodeParseError {}
+
+impl EnhancedStatusCode {
+    pub fn new(class: StatusClass, subject: StatusSubject, detail: u16) -> Self {
+        Self {
+            class,
+            subject,
+            detail,
+        }
+    }
+
+    /// description returns meaning of subject and detail pair defined in RFC 3463 section 3
+    pub fn description(&self) -> Option<&'static str> {
+        Some(match (self.subject.number(), self.detail) {
+            (0, 0) => "Other undefined Status",
+            (1, 0) => "Other address status",
+            (1, 1) => "Bad destination mailbox address",
+            (1, 2) => "Bad destination system address",
+            (1, 3) => "Bad destination mailbox address syntax",
+            (1, 4) => "Destination mailbox address ambiguous",
+            (1, 5) => "Destination address valid",
+            (1, 6) => "Destination mailbox has moved, No forwarding address",
+            (1, 7) => "Bad sender's mailbox address syntax",
+            (1, 8) => "Bad sender's system address",
+            (2, 0) => "Other or undefined mailbox status",
+            (2, 1) => "Mailbox disabled, not accepting messages",
+            (2, 2) => "Mailbox full",
+            (2, 3) => "Message length exceeds administrative limit",
+            (2, 4) => "Mailing list expansion problem",
+            (3, 0) => "Other or undefined mail system status",
+            (3, 1) => "Mail system full",
+            (3, 2) => "System not accepting network messages",
+            (3, 3) => "System not capable of selected features",
+            (3, 4) => "Message too big for system",
+            (3, 5) => "System incorrectly configured",
+            (4, 0) => "Other or undefined network or routing status",
+            (4, 1) => "No answer from host",
+            (4, 2) => "Bad connection",
+            (4, 3) => "Directory server failure",
+            (4, 4) => "Unable to route",
+            (4, 5) => "Mail system congestion",
+            (4, 6) => "Routing loop detected",
+            (4, 7) => "Delivery time expired",
+            (5, 0) => "Other or undefined protocol status",
+            (5, 1) => "Invalid command",
+            (5, 2) => "Syntax error",
+            (5, 3) => "Too many recipients",
+            (5, 4) => "Invalid command arguments",
+            (5, 5) => "Wrong protocol version",
+            (6, 0) => "Other or undefined media error",
+            (6, 1) => "Media not supported",
+            (6, 2) => "Conversion required and prohibited",
+            (6, 3) => "Conversion required but not supported",
+            (6, 4) => "Conversion with loss performed",
+            (6, 5) => "Conversion Failed",
+            (7, 0) => "Other or undefined security status",
+            (7, 1) => "Delivery not authorized, message refused",
+            (7, 2) => "Mailing list expansion prohibited",
+            (7, 3) => "Security conversion required but not possible",
+            (7, 4) => "Security features not supported",
+            (7, 5) => "Cryptographic failure",
+            (7, 6) => "Cryptographic algorithm not supported",
+            (7, 7) => "Message integrity failure",
+            _ => return None,
+        })
+    }
+
+    /// parse_prefix parses status code at the beginning of reply text.
+    /// It returns code and remaining text without separating space.
+    pub fn parse_prefix(text: &str) -> Option<(Self, &str)> {
+        let (code, rest) = match text.find(' ') {
+            Some(idx) => (&text[..idx], &text[idx + 1..]),
+            None => (text, ""),
+        };
+        code.parse().ok().map(|code| (code, rest))
+    }
+}
+
+impl FromStr for EnhancedStatusCode {
+    type Err = EnhancedStatusCodeParseError;
+
+    /// from_str parses code like `5.1.1`. Class is single digit, subject and detail have up to three digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('.');
+        let mut next_number = |max_len: usize| {
+            parts.next()
+                .filter(|p| !p.is_empty() && p.len() <= max_len && p.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|p| p.parse::<u16>().ok())
+                .ok_or(EnhancedStatusCodeParseError)
+        };
+        let class = StatusClass::from_digit(next_number(1)? as u8).ok_or(EnhancedStatusCodeParseError)?;
+        let subject = StatusSubject::from_number(next_number(3)?);
+        let detail = next_number(3)?;
+        if parts.next().is_some() {
+            return Err(EnhancedStatusCodeParseError);
+        }
+        Ok(Self {
+            class,
+            subject,
+            detail,
+        })
+    }
+}
+
+impl fmt::Display for EnhancedStatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.class.digit(), self.subject.number(), self.detail)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_can_parse_enhanced_status_code() {
+        for (i, o) in [
+            ("5.1.1", Some((StatusClass::PermanentFailure, StatusSubject::Addressing, 1))),
+            ("2.0.0", Some((StatusClass::Success, StatusSubject::Other, 0))),
+            ("4.999.123", Some((StatusClass::PersistentTransientFailure, StatusSubject::Unknown(999), 123))),
+            ("3.1.1", None),
+            ("5.1", None),
+            ("5.1.1.1", None),
+            ("5.1.1234", None),
+            ("5..1", None),
+            ("5.a.1", None),
+        ].iter() {
+            let res = i.parse::<EnhancedStatusCode>().ok();
+            assert_eq!(res, o.map(|(c, s, d)| EnhancedStatusCode::new(c, s, d)));
+            if let Some(res) = res {
+                assert_eq!(res.to_string(), *i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_enhanced_status_code_has_description() {
+        let code: EnhancedStatusCode = "5.1.1".parse().unwrap();
+        assert_eq!(code.class.description(), "Permanent Failure");
+        assert_eq!(code.subject.description(), Some("Addressing Status"));
+        assert_eq!(code.description(), Some("Bad destination mailbox address"));
+        assert_eq!("4.8.1".parse::<EnhancedStatusCode>().unwrap().description(), None);
+    }
+}
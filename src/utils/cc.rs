@@ -27,4 +27,12 @@ pub fn is_atext(c: char, dot: bool, permissive: bool) -> bool {
         '<' | '>' | '"' | ':' => false,
         c => is_vchar(c)
     }
-}
\ No newline at end of file
+}
+/// is_ftext checks if char may be part of header field name. See RFC 5322 3.6.8
+#[inline]
+pub fn is_ftext(c: char) -> bool {
+    match c {
+        ':' => false,
+        c => ('!'..='~').contains(&c)
+    }
+}
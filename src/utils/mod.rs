@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::io::{self, Read};
 
 pub mod hex;
 pub mod quoted;
@@ -18,6 +19,37 @@ pub fn read_stream_to_buf_string(r: &mut impl Read, buf: &mut String) -> Result<
 }
 */
 
+/// PrefixedReader yields given prefix before any data of underlying reader.
+pub(crate) struct PrefixedReader<R> {
+    prefix: &'static [u8],
+    reader: R,
+}
+
+impl<R> PrefixedReader<R> {
+    pub fn new(prefix: &'static [u8], reader: R) -> Self {
+        Self {
+            prefix,
+            reader,
+        }
+    }
+
+    /// into_inner returns underlying reader. Prefix bytes which were not read are lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> Read for PrefixedReader<R> where R: Read {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        if self.prefix.is_empty() {
+            self.reader.read(buf)
+        } else {
+            self.prefix.read(buf)
+        }
+    }
+}
+
 /// BoundaryDetector consumes bytes one by one.
 /// It's able to tell whatever or not was boundary reached and how many data
 /// has to be read again once boundary read filed but started well.
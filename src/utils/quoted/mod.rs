@@ -1,3 +1,8 @@
+use std::error;
+use std::fmt;
+
+use crate::error::ErrorPosition;
+
 pub use quoted::*;
 pub use rfc_2047::*;
 
@@ -7,13 +12,51 @@ mod quoted;
 #[derive(Debug, From)]
 pub enum QuotedStringError {
     InputEmpty,
-    FirstCharIsNotQuote,
-    LastCharacterIsNotQuote,
+    FirstCharIsNotQuote(ErrorPosition),
+    LastCharacterIsNotQuote(ErrorPosition),
     UnexpectedEof,
     InvalidEncoding,
-    DecodingFailed,
-    InvalidCharacter {
-        char_offset: usize,
-        byte_offset: usize,
-    },
+    /// DecodingFailed is returned when encoded word is not valid in it's encoding or charset
+    DecodingFailed(ErrorPosition),
+    InvalidCharacter(ErrorPosition),
+}
+
+impl QuotedStringError {
+    /// position returns place in input where error was found if it's known
+    pub fn position(&self) -> Option<&ErrorPosition> {
+        match self {
+            QuotedStringError::FirstCharIsNotQuote(p) |
+            QuotedStringError::LastCharacterIsNotQuote(p) |
+            QuotedStringError::DecodingFailed(p) |
+            QuotedStringError::InvalidCharacter(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// shift moves position of error by given number of bytes. See `ErrorPosition::shift`
+    pub(crate) fn shift(self, offset: usize) -> Self {
+        match self {
+            QuotedStringError::FirstCharIsNotQuote(p) => QuotedStringError::FirstCharIsNotQuote(p.shift(offset)),
+            QuotedStringError::LastCharacterIsNotQuote(p) => QuotedStringError::LastCharacterIsNotQuote(p.shift(offset)),
+            QuotedStringError::DecodingFailed(p) => QuotedStringError::DecodingFailed(p.shift(offset)),
+            QuotedStringError::InvalidCharacter(p) => QuotedStringError::InvalidCharacter(p.shift(offset)),
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for QuotedStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotedStringError::InputEmpty => write!(f, "quoted string is empty"),
+            QuotedStringError::FirstCharIsNotQuote(p) => write!(f, "quoted string does not start with quote: {}", p),
+            QuotedStringError::LastCharacterIsNotQuote(p) => write!(f, "quoted string does not end with quote: {}", p),
+            QuotedStringError::UnexpectedEof => write!(f, "unexpected end of input"),
+            QuotedStringError::InvalidEncoding => write!(f, "invalid encoding"),
+            QuotedStringError::DecodingFailed(p) => write!(f, "can't decode encoded word: {}", p),
+            QuotedStringError::InvalidCharacter(p) => write!(f, "invalid character in quoted string: {}", p),
+        }
+    }
 }
+
+impl error::Error for QuotedStringError {}
@@ -1,3 +1,4 @@
+use crate::error::ErrorPosition;
 use crate::utils::cc::*;
 use crate::utils::quoted::QuotedStringError;
 
@@ -8,12 +9,11 @@ pub fn unquote_string(text: &str, contains_quotes: bool) -> Result<String, Quote
     let mut is_escaped = false;
     let mut out = String::with_capacity(text.len().checked_sub(2).unwrap_or(1));
 
-    let mut char_offset = 0;
     let mut byte_offset = 0;
     for c in text.chars() {
         if byte_offset == 0 && contains_quotes {
             if c != '"' {
-                return Err(QuotedStringError::FirstCharIsNotQuote);
+                return Err(QuotedStringError::FirstCharIsNotQuote(ErrorPosition::new(text, 0, "`\"`")));
             }
         } else {
             match c {
@@ -35,19 +35,23 @@ pub fn unquote_string(text: &str, contains_quotes: bool) -> Result<String, Quote
                     out.push(c);
                 }
                 _ => {
-                    return Err(QuotedStringError::InvalidCharacter {
-                        byte_offset,
-                        char_offset,
-                    });
+                    return Err(QuotedStringError::InvalidCharacter(
+                        ErrorPosition::new(text, byte_offset, "quoted text")
+                    ));
                 }
             }
         };
-        char_offset += 1;
         byte_offset += c.len_utf8();
     }
 
     if !text.is_empty() && contains_quotes && text.as_bytes().len() - 1 != byte_offset {
-        return Err(QuotedStringError::LastCharacterIsNotQuote);
+        // either there is no closing quote or there is something after it
+        let position = if byte_offset >= text.len() {
+            ErrorPosition::new(text, text.len(), "closing `\"`")
+        } else {
+            ErrorPosition::new(text, byte_offset + 1, "end of quoted string")
+        };
+        return Err(QuotedStringError::LastCharacterIsNotQuote(position));
     }
 
     Ok(out)
@@ -1,12 +1,23 @@
 use std::borrow::Cow;
 use std::io::{Cursor, Read, Write};
+use std::ops::Range;
 
 use crate::encoding::base64::{Base64Reader, Base64Writer};
-use crate::encoding::quoted_printable::{QuotedPrintableReader, QuotedPrintableWriter, SoftLineBreaksMode};
-// use crate::encoding::quoted_printable::{QuotedPrintableDecoder, QuotedPrintableEncoder};
-use crate::utils::quoted::QuotedStringError;
+use crate::encoding::charset::Charset;
+use crate::error::{ErrorPosition, offset_in};
+use crate::utils::cc::is_atext;
+use crate::utils::hex::encode_hex_char;
+use crate::utils::quoted::{quote_string, QuotedStringError};
+
+/// MAX_ENCODED_WORD_LENGTH is maximal length of single encoded word. See RFC 2047 2
+pub const MAX_ENCODED_WORD_LENGTH: usize = 75;
+
+/// MAX_HEADER_LINE_LENGTH is length of header line which should not be exceeded if possible. See RFC 5322 2.1.1
+pub const MAX_HEADER_LINE_LENGTH: usize = 78;
+
+/// ENCODED_WORD_OVERHEAD is length of `=?UTF-8?B?` and `?=` parts of encoded word
+const ENCODED_WORD_OVERHEAD: usize = 12;
 
-#[allow(dead_code)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum RFC2047Encoding {
     Base64,
@@ -14,25 +25,15 @@ pub enum RFC2047Encoding {
 }
 
 impl RFC2047Encoding {
-    #[allow(dead_code)]
     /// encoded_len returns size of encoded data when given encoding would be used.
     /// It does not return length of entire result but length of encoded data.
     /// So:  encoded_length - constant_sized_parts
     pub fn encoded_len(self, data: &str) -> u64 {
         match self {
-            RFC2047Encoding::Base64 => {
-                let n = (data.as_bytes().len() as u64) * 4 / 3;
-                if n % 4 != 0 {
-                    n + (4 - n % 4)
-                } else {
-                    n
-                }
-            }
-            // TODO(teawithsand): this DOES NOT count soft line breaks!
-            RFC2047Encoding::QuotedPrintable => data.chars().map(|c| match c {
-                c if c.is_ascii() => 1,
-                c => (c.len_utf8() * 3) as u64,
-            }).sum(),
+            RFC2047Encoding::Base64 => base64_len(data.len() as u64),
+            RFC2047Encoding::QuotedPrintable => data.bytes()
+                .map(|b| if is_q_safe(b) || b == b' ' { 1 } else { 3 })
+                .sum(),
         }
     }
 
@@ -44,38 +45,58 @@ impl RFC2047Encoding {
     }
 }
 
-/*
-/// optimal_encode_rfc_2047 encodes text using either base64 or quoted printable dependent on which result is smaller
-pub fn optimal_encode_rfc_2047(text: &str) -> String {
+/// base64_len returns length of given number of bytes encoded with padded base64
+fn base64_len(n: u64) -> u64 {
+    let n = n * 4 / 3;
+    if n % 4 != 0 {
+        n + (4 - n % 4)
+    } else {
+        n
+    }
+}
+
+/// is_q_safe checks if byte may be put in "Q" encoded word as is.
+/// Only chars allowed in encoded words in phrases are considered safe, so result may be used anywhere. See RFC 2047 5
+fn is_q_safe(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!*+-/".contains(&b)
+}
+
+/// optimal_encoding returns encoding which gives shorter result for given text.
+/// Quoted printable is preferred when both are equal as it's more readable.
+pub fn optimal_encoding(text: &str) -> RFC2047Encoding {
     let qp_sz = RFC2047Encoding::QuotedPrintable.encoded_len(text);
     let b64sz = RFC2047Encoding::Base64.encoded_len(text);
-    if b64sz <= qp_sz {
-        encode_rfc_2047(text, RFC2047Encoding::Base64)
+    if b64sz < qp_sz {
+        RFC2047Encoding::Base64
     } else {
-        encode_rfc_2047(text, RFC2047Encoding::QuotedPrintable)
+        RFC2047Encoding::QuotedPrintable
     }
 }
-*/
 
+/// optimal_encode_rfc_2047 encodes text using either base64 or quoted printable dependent on which result is smaller
 #[allow(dead_code)]
+pub fn optimal_encode_rfc_2047(text: &str) -> String {
+    encode_rfc_2047(text, optimal_encoding(text))
+}
+
+/// encode_rfc_2047 encodes text as single UTF-8 encoded word. Length of result is not limited.
 pub fn encode_rfc_2047(text: &str, encoding: RFC2047Encoding) -> String {
-    let mut res = String::with_capacity(text.len() + 12);
+    let mut res = String::with_capacity(text.len() + ENCODED_WORD_OVERHEAD);
     res.push_str("=?UTF-8?"); // no other encoding is allowed in rust string
     res.push_str(encoding.rfc_letter());
     res.push('?');
     match encoding {
         RFC2047Encoding::QuotedPrintable => {
-            let mut w = Cursor::new(Vec::new());
-            {
-                let mut w = QuotedPrintableWriter::new(&mut w, SoftLineBreaksMode::Standard);
-                w.write_all(text.as_bytes()).expect("Quoted printable writer filed to write to cursor");
-                w.flush().expect("Quoted printable writer filed to flush to cursor");
+            for b in text.bytes() {
+                match b {
+                    b' ' => res.push('_'),
+                    b if is_q_safe(b) => res.push(b as char),
+                    b => {
+                        res.push('=');
+                        res.push_str(std::str::from_utf8(&encode_hex_char(b)).unwrap());
+                    }
+                }
             }
-
-            res.push_str(
-                &String::from_utf8(w.into_inner())
-                    .expect("Quoted printable writer returned invalid utf-8 string")
-            );
         }
         RFC2047Encoding::Base64 => {
             let mut w = Cursor::new(Vec::new());
@@ -95,93 +116,464 @@ pub fn encode_rfc_2047(text: &str, encoding: RFC2047Encoding) -> String {
     res
 }
 
-pub fn parse_rfc_2047(text: &str) -> Result<String, QuotedStringError> {
-    let mut state = 0;
-    // let mut charset = &text[..];
-    let mut encoding = &text[..];
-    let mut enc_text = &text[..];
-
-    // let mut charset_start_offset = 0;
-    let mut encoding_start_offset = 0;
-    let mut enc_text_start_offset = 0;
-    let mut byte_offset = 0;
-    for c in text.chars() {
-        if state == 0 {
-            if c == '=' {
-                state = 1;
-            } else {
-                return Err(QuotedStringError::UnexpectedEof);
-            }
-        } else if state == 1 {
-            if c == '?' {
-                // charset_start_offset = byte_offset;
-                state = 2;
-            } else {
-                return Err(QuotedStringError::UnexpectedEof);
+/// take_chunk returns length of longest prefix of text which fits in encoded word of given length.
+/// Prefix always ends at char boundary, so UTF-8 sequences are never split between words.
+fn take_chunk(text: &str, encoding: RFC2047Encoding, max_word_len: usize) -> usize {
+    let max_len = match max_word_len.checked_sub(ENCODED_WORD_OVERHEAD) {
+        Some(l) => l as u64,
+        None => return 0,
+    };
+    let mut res = 0;
+    // quoted printable length of each char is known, so it's summed up as prefix grows
+    let mut q_len = 0;
+    for (offset, c) in text.char_indices() {
+        let end = offset + c.len_utf8();
+        let len = match encoding {
+            RFC2047Encoding::Base64 => base64_len(end as u64),
+            RFC2047Encoding::QuotedPrintable => {
+                q_len += encoding.encoded_len(&text[offset..end]);
+                q_len
             }
-        } else if state == 2 {
-            if c == '?' {
-                // charset = &text[charset_start_offset..byte_offset];
-                encoding_start_offset = byte_offset + 1;
-                state = 3;
+        };
+        if len > max_len {
+            break;
+        }
+        res = end;
+    }
+    res
+}
+
+/// encode_rfc_2047_words encodes text as encoded words, each of them at most 75 chars long.
+/// Encoding is chosen using `RFC2047Encoding::encoded_len`.
+pub fn encode_rfc_2047_words(text: &str) -> Vec<String> {
+    let encoding = optimal_encoding(text);
+    let mut res = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        // single char always fits in encoded word
+        let n = take_chunk(rest, encoding, MAX_ENCODED_WORD_LENGTH);
+        res.push(encode_rfc_2047(&rest[..n], encoding));
+        rest = &rest[n..];
+    }
+    res
+}
+
+/// split_words splits text at spaces into words and marks ones which have to be encoded.
+/// Adjacent words which have to be encoded are joined into one along with spaces between them,
+/// since white space between encoded words is dropped when decoding.
+fn split_words(text: &str, needs_encoding: impl Fn(&str) -> bool) -> Vec<(&str, bool)> {
+    let mut segments: Vec<(usize, usize, bool)> = Vec::new();
+    let mut offset = 0;
+    for word in text.split(' ') {
+        let (start, end) = (offset, offset + word.len());
+        offset = end + 1;
+        if !needs_encoding(word) {
+            segments.push((start, end, false));
+            continue;
+        }
+        let empty_words = segments.iter().rev()
+            .take_while(|(s, e, is_encoded)| !is_encoded && s == e)
+            .count();
+        match segments.len().checked_sub(empty_words + 1) {
+            Some(prev) if segments[prev].2 => {
+                segments.truncate(prev + 1);
+                segments[prev].1 = end;
             }
-        } else if state == 3 {
-            if c == '?' {
-                encoding = &text[encoding_start_offset..byte_offset];
-                enc_text_start_offset = byte_offset + 1;
-                state = 4;
+            _ => segments.push((start, end, true)),
+        }
+    }
+    segments.into_iter().map(|(s, e, is_encoded)| (&text[s..e], is_encoded)).collect()
+}
+
+/// is_unstructured_safe checks if word may be put in unstructured header as is
+fn is_unstructured_safe(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) && !word.contains("=?")
+}
+
+/// is_atom checks if word is valid atom, which may be put in phrase as is
+fn is_atom(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_ascii() && is_atext(c, false, false))
+}
+
+/// WordMode selects which words are encoded by `encode_words`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum WordMode {
+    /// Phrase encodes words which are not atoms and drops empty ones
+    Phrase,
+    /// Unstructured encodes words which are not printable ASCII or look like encoded words
+    Unstructured,
+}
+
+impl WordMode {
+    fn needs_encoding(self, word: &str) -> bool {
+        match self {
+            WordMode::Phrase => !is_atom(word),
+            WordMode::Unstructured => !is_unstructured_safe(word),
+        }
+    }
+}
+
+/// encode_words encodes words of text which need it in given mode with encoded words of at most 75 chars.
+/// Result is not folded.
+fn encode_words(text: &str, mode: WordMode) -> String {
+    split_words(text, |w| mode.needs_encoding(w)).into_iter()
+        .filter(|(w, _)| mode != WordMode::Phrase || !w.is_empty())
+        .map(|(w, is_encoded)| if is_encoded {
+            encode_rfc_2047_words(w).join(" ")
+        } else {
+            w.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// encode_phrase encodes phrase, like display name of address, so it's safe to put it in structured header.
+///
+/// Phrase of atoms is left as is and other ASCII phrases are quoted.
+/// Otherwise words which are not atoms are encoded with encoded words of at most 75 chars, which are safe to use in phrase.
+pub fn encode_phrase(text: &str) -> String {
+    if text.split(' ').all(is_atom) {
+        return text.to_string();
+    }
+    if is_unstructured_safe(text) {
+        return quote_string(text, true);
+    }
+    encode_words(text, WordMode::Phrase)
+}
+
+/// HeaderFolder writes header line, folding it at spaces so lines are not longer than 78 chars if possible
+struct HeaderFolder {
+    res: String,
+    line_len: usize,
+    has_word: bool,
+}
+
+impl HeaderFolder {
+    fn new(name: &str) -> Self {
+        let mut res = String::with_capacity(name.len() + MAX_HEADER_LINE_LENGTH);
+        res.push_str(name);
+        res.push(':');
+        Self {
+            line_len: res.len(),
+            res,
+            has_word: false,
+        }
+    }
+
+    /// room returns length of word which fits in current line
+    fn room(&self) -> usize {
+        MAX_HEADER_LINE_LENGTH.saturating_sub(self.line_len + 1)
+    }
+
+    fn fold(&mut self) {
+        self.res.push_str("\r\n");
+        self.line_len = 0;
+        self.has_word = false;
+    }
+
+    /// push_word appends space and word. Line is folded before it if word does not fit.
+    fn push_word(&mut self, word: &str) {
+        if !word.is_empty() && self.has_word && word.len() > self.room() {
+            self.fold();
+        }
+        self.res.push(' ');
+        self.res.push_str(word);
+        self.line_len += 1 + word.len();
+        self.has_word = true;
+    }
+
+    /// push_encoded encodes text as encoded words. First of them is shortened to fit in current line.
+    fn push_encoded(&mut self, text: &str) {
+        let encoding = optimal_encoding(text);
+        let mut rest = text;
+        while !rest.is_empty() {
+            let mut n = take_chunk(rest, encoding, self.room().min(MAX_ENCODED_WORD_LENGTH));
+            if n == 0 {
+                self.fold();
+                n = take_chunk(rest, encoding, MAX_ENCODED_WORD_LENGTH);
             }
-        } else if state == 4 {
-            if c == '?' {
-                state = 5;
+            self.push_word(&encode_rfc_2047(&rest[..n], encoding));
+            rest = &rest[n..];
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.res.push_str("\r\n");
+        self.res
+    }
+}
+
+/// fold_header creates header line of given name and value, ending with CRLF.
+/// Value is folded at spaces so lines are not longer than 78 chars if possible.
+pub fn fold_header(name: &str, value: &str) -> String {
+    let mut folder = HeaderFolder::new(name);
+    for word in value.split(' ') {
+        folder.push_word(word);
+    }
+    folder.finish()
+}
+
+/// encode_unstructured encodes unstructured header value like subject.
+/// Only words which need it are encoded, using encoded words of at most 75 chars. Result is not folded.
+pub fn encode_unstructured(text: &str) -> String {
+    encode_words(text, WordMode::Unstructured)
+}
+
+/// encode_header creates header line of given name and unstructured value like subject, ending with CRLF.
+///
+/// Only words which need it are encoded, using encoded words of at most 75 chars.
+/// Line is folded so it's not longer than 78 chars if possible.
+pub fn encode_header(name: &str, value: &str) -> String {
+    let mut folder = HeaderFolder::new(name);
+    for (word, is_encoded) in split_words(value, |w| WordMode::Unstructured.needs_encoding(w)) {
+        if is_encoded {
+            folder.push_encoded(word);
+        } else {
+            folder.push_word(word);
+        }
+    }
+    folder.finish()
+}
+
+/// EncodedWord is single RFC 2047 encoded word, for instance `=?UTF-8?Q?text?=`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EncodedWord<'a> {
+    pub charset: &'a str,
+    /// language may follow charset like in `=?US-ASCII*EN?Q?text?=`. See RFC 2231 5
+    pub language: Option<&'a str>,
+    pub encoding: RFC2047Encoding,
+    pub encoded_text: &'a str,
+}
+
+impl<'a> EncodedWord<'a> {
+    /// parse_prefix parses encoded word at the beginning of text.
+    /// It returns word and it's length in bytes or `None` if text does not start with valid encoded word.
+    pub fn parse_prefix(text: &'a str) -> Option<(Self, usize)> {
+        let rest = text.strip_prefix("=?")?;
+        let charset_end = rest.find('?')?;
+        let charset = &rest[..charset_end];
+        let rest = &rest[charset_end + 1..];
+        let encoding = match rest.get(..2)? {
+            "B?" | "b?" => RFC2047Encoding::Base64,
+            "Q?" | "q?" => RFC2047Encoding::QuotedPrintable,
+            _ => return None,
+        };
+        let rest = &rest[2..];
+        let text_end = rest.find("?=")?;
+        let encoded_text = &rest[..text_end];
+        let is_token = |t: &str| t.bytes().all(|b| b.is_ascii_graphic());
+        if charset.is_empty() || !is_token(charset) || !is_token(encoded_text) {
+            return None;
+        }
+        let (charset, language) = match charset.find('*') {
+            Some(idx) => (&charset[..idx], Some(&charset[idx + 1..])),
+            None => (charset, None),
+        };
+        let word = Self {
+            charset,
+            language,
+            encoding,
+            encoded_text,
+        };
+        Some((word, 2 + charset_end + 1 + 2 + text_end + 2))
+    }
+
+    /// decode_bytes decodes encoded text without converting it from charset.
+    /// Position of error is relative to the beginning of encoded text.
+    pub fn decode_bytes(&self) -> Result<Vec<u8>, QuotedStringError> {
+        match self.encoding {
+            RFC2047Encoding::Base64 => {
+                let mut res = Vec::new();
+                Base64Reader::new(Cursor::new(self.encoded_text.as_bytes()))
+                    .read_to_end(&mut res)
+                    .map_err(|_| QuotedStringError::DecodingFailed(
+                        ErrorPosition::new(self.encoded_text, 0, "base64 text")
+                    ))?;
+                Ok(res)
             }
-        } else if state == 5 {
-            if c == '=' {
-                enc_text = &text[enc_text_start_offset..byte_offset - 1];
-                break;
-            } else {
-                state = 4;
+            RFC2047Encoding::QuotedPrintable => decode_q(self.encoded_text),
+        }
+    }
+
+    /// decode decodes encoded text and converts it to string
+    pub fn decode(&self) -> Result<String, QuotedStringError> {
+        decode_charset(self.charset, self.decode_bytes()?)
+            .ok_or_else(|| charset_error(self.encoded_text))
+    }
+}
+
+/// decode_q decodes "Q" encoding, which is quoted printable where `_` stands for space. See RFC 2047 4.2
+fn decode_q(text: &str) -> Result<Vec<u8>, QuotedStringError> {
+    let mut res = Vec::with_capacity(text.len());
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'_' => res.push(b' '),
+            b'=' => {
+                let hex = bytes.get(i + 1..i + 3)
+                    .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| QuotedStringError::DecodingFailed(
+                        ErrorPosition::new(text, i + 1, "two hex digits")
+                    ))?;
+                res.push(hex);
+                i += 2;
             }
-        } else {
-            unreachable!("invalid state");
+            b => res.push(b),
         }
+        i += 1;
+    }
+    Ok(res)
+}
 
-        byte_offset += c.len_utf8();
+/// decode_charset converts decoded text of encoded word to string.
+/// Text in unknown charset is accepted only if it's valid UTF-8.
+fn decode_charset(charset: &str, data: Vec<u8>) -> Option<String> {
+    match Charset::for_label(charset) {
+        Some(Charset::Utf8) | None => String::from_utf8(data).ok(),
+        Some(c) => c.decode_strict(&data)
+            .map(|t| t.into_owned())
+            .ok(),
     }
-    if enc_text == text {
-        return Err(QuotedStringError::UnexpectedEof);
+}
+
+/// charset_error returns error of encoded text which is not valid in it's charset
+fn charset_error(encoded_text: &str) -> QuotedStringError {
+    QuotedStringError::DecodingFailed(ErrorPosition::new(encoded_text, 0, "text valid in declared charset"))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RFC2047Token<'a> {
+    Text(&'a str),
+    EncodedWord(EncodedWord<'a>),
+}
+
+/// tokenize_rfc_2047 splits header text into encoded words and text between them.
+/// Encoded words are recognised even if they are not separated from text with white space,
+/// since many mailers produce such headers.
+#[allow(dead_code)]
+pub fn tokenize_rfc_2047(text: &str) -> Vec<RFC2047Token<'_>> {
+    tokenize_with_spans(text).into_iter().map(|(t, _)| t).collect()
+}
+
+/// tokenize_with_spans works like `tokenize_rfc_2047` but it also returns range of text occupied by each token
+fn tokenize_with_spans(text: &str) -> Vec<(RFC2047Token<'_>, Range<usize>)> {
+    let mut res = Vec::new();
+    let mut text_start = 0;
+    let mut offset = 0;
+    while let Some(idx) = text[offset..].find("=?") {
+        let word_start = offset + idx;
+        match EncodedWord::parse_prefix(&text[word_start..]) {
+            Some((word, len)) => {
+                if word_start > text_start {
+                    res.push((RFC2047Token::Text(&text[text_start..word_start]), text_start..word_start));
+                }
+                res.push((RFC2047Token::EncodedWord(word), word_start..word_start + len));
+                offset = word_start + len;
+                text_start = offset;
+            }
+            None => offset = word_start + 1,
+        }
     }
-    let dec_res = if encoding == "B" || encoding == "b" {
-        let mut res = String::new();
-        Base64Reader::new(Cursor::new(enc_text.as_bytes()))
-            .read_to_string(&mut res)
-            .map(|_| res)
-    } else if encoding == "Q" || encoding == "q" {
-        let mut res = String::new();
-        QuotedPrintableReader::new(Cursor::new(enc_text.as_bytes()))
-            .read_to_string(&mut res)
-            .map(|_| res)
-    } else {
-        return Err(QuotedStringError::InvalidEncoding);
-    }.map_err(|_| QuotedStringError::DecodingFailed)?;
-    // ignore encoding. rust can support only utf8 in the end
-    Ok(dec_res)
+    if text_start < text.len() {
+        res.push((RFC2047Token::Text(&text[text_start..]), text_start..text.len()));
+    }
+    res
 }
 
-pub fn parse_maybe_rfc_2047(text: &str) -> Result<Cow<str>, QuotedStringError> {
-    if text.is_empty() {
-        Ok(Cow::Borrowed(text))
-    } else if text.chars().nth(0) == Some('=') && text.chars().nth(1) == Some('?') {
-        Ok(Cow::Owned(parse_rfc_2047(text)?))
-    } else {
-        Ok(Cow::Borrowed(text))
+/// decode_rfc_2047 decodes all encoded words in header text. See RFC 2047 6
+///
+/// White space between adjacent encoded words is dropped and text around them is kept.
+/// Adjacent words in the same charset are decoded together, so chars split between them are joined.
+/// Encoded words which can't be decoded are kept as they are, as RFC 2047 6.3 suggests.
+/// Use `decode_rfc_2047_strict` in order to get error instead.
+pub fn decode_rfc_2047(text: &str) -> Cow<'_, str> {
+    // words which can't be decoded are skipped rather than reported, so it can't fail
+    decode_words(text, false).unwrap_or(Cow::Borrowed(text))
+}
+
+/// decode_rfc_2047_strict decodes all encoded words in header text the same way `decode_rfc_2047` does,
+/// but it fails on first encoded word which can't be decoded.
+pub fn decode_rfc_2047_strict(text: &str) -> Result<Cow<'_, str>, QuotedStringError> {
+    decode_words(text, true)
+}
+
+fn decode_words(text: &str, strict: bool) -> Result<Cow<'_, str>, QuotedStringError> {
+    let tokens = tokenize_with_spans(text);
+    if tokens.iter().all(|(t, _)| matches!(t, RFC2047Token::Text(_))) {
+        return Ok(Cow::Borrowed(text));
+    }
+
+    let mut decoded = Vec::with_capacity(tokens.len());
+    for (token, _) in tokens.iter() {
+        decoded.push(match token {
+            RFC2047Token::EncodedWord(w) => match w.decode_bytes() {
+                Ok(data) => Some(data),
+                Err(e) if strict => return Err(e.shift(offset_in(text.as_bytes(), w.encoded_text.as_bytes()))),
+                Err(_) => None,
+            },
+            RFC2047Token::Text(_) => None,
+        });
+    }
+
+    let is_decoded: Vec<_> = decoded.iter().map(Option::is_some).collect();
+
+    let mut res = String::with_capacity(text.len());
+    // charset, bytes, encoded text of first word and span of adjacent words which are not converted yet
+    let mut pending: Option<(&str, Vec<u8>, &str, Range<usize>)> = None;
+    let flush = |res: &mut String, pending: Option<(&str, Vec<u8>, &str, Range<usize>)>| {
+        if let Some((charset, data, encoded_text, span)) = pending {
+            match decode_charset(charset, data) {
+                Some(t) => res.push_str(&t),
+                None if strict => return Err(charset_error(encoded_text).shift(offset_in(text.as_bytes(), encoded_text.as_bytes()))),
+                None => res.push_str(&text[span]),
+            }
+        }
+        Ok(())
+    };
+    for i in 0..tokens.len() {
+        match (&tokens[i], decoded[i].take()) {
+            ((RFC2047Token::Text(t), _), _) => {
+                let is_between_words = i > 0 && is_decoded[i - 1] && is_decoded.get(i + 1) == Some(&true);
+                if is_between_words && t.chars().all(|c| c == ' ' || c == '\t' || c == '\r' || c == '\n') {
+                    continue;
+                }
+                flush(&mut res, pending.take())?;
+                res.push_str(t);
+            }
+            ((RFC2047Token::EncodedWord(w), span), Some(data)) => match &mut pending {
+                Some((charset, buf, _, pending_span)) if charset.eq_ignore_ascii_case(w.charset) => {
+                    buf.extend(data);
+                    pending_span.end = span.end;
+                }
+                _ => {
+                    flush(&mut res, pending.take())?;
+                    pending = Some((w.charset, data, w.encoded_text, span.clone()));
+                }
+            },
+            ((RFC2047Token::EncodedWord(_), span), None) => {
+                flush(&mut res, pending.take())?;
+                res.push_str(&text[span.clone()]);
+            }
+        }
     }
+    flush(&mut res, pending)?;
+    Ok(Cow::Owned(res))
+}
+
+/// parse_rfc_2047 decodes single encoded word at the beginning of text. Anything after it is ignored.
+#[allow(dead_code)]
+pub fn parse_rfc_2047(text: &str) -> Result<String, QuotedStringError> {
+    let (word, _) = EncodedWord::parse_prefix(text).ok_or(QuotedStringError::UnexpectedEof)?;
+    word.decode()
 }
 
-pub fn parse_maybe_rfc_2047_is_encoded(text: &str) -> Result<(Cow<str>, bool), QuotedStringError> {
-    let is_encoded = text.chars().nth(0) == Some('=') && text.chars().nth(1) == Some('?');
-    let text = parse_maybe_rfc_2047(text)?;
-    Ok((text, is_encoded))
+/// parse_maybe_rfc_2047 decodes all encoded words in text. Text without them is returned as is.
+/// Encoded words which can't be decoded are kept as they are. See `decode_rfc_2047`
+pub fn parse_maybe_rfc_2047(text: &str) -> Cow<'_, str> {
+    decode_rfc_2047(text)
 }
 
 #[cfg(test)]
@@ -196,6 +588,7 @@ mod test {
             (RFC2047Encoding::QuotedPrintable, "asdf", 4),
             (RFC2047Encoding::QuotedPrintable, "ŁŁŁŁ", 4 * 2 * 3),
             (RFC2047Encoding::QuotedPrintable, "aaŁŁŁŁ", 2 + 4 * 2 * 3),
+            (RFC2047Encoding::QuotedPrintable, "a b_=?", 3 + 3 * 3),
             // // // //
             (RFC2047Encoding::Base64, "", 0),
             (RFC2047Encoding::Base64, "ŁŁ", 8),
@@ -224,6 +617,19 @@ mod test {
             ("=?utf-8?b?QUFB?=", Some("AAA")),
             ("=?utf-8?q?ABCD?=", Some("ABCD")),
             ("=?utf-8?q?aa=C5=81=C5=81?=", Some("aaŁŁ")),
+            // // // // other charsets
+            ("=?ISO-8859-2?Q?=A3=F3d=BC?=", Some("Łódź")),
+            ("=?iso-8859-2*pl?Q?=A3=F3d=BC?=", Some("Łódź")),
+            ("=?windows-1251?B?z/Do4uXy?=", Some("Привет")),
+            ("=?KOI8-R?B?8NLJ18XU?=", Some("Привет")),
+            ("=?ISO-2022-JP?B?GyRCRnxLXDhsGyhC?=", Some("日本語")),
+            ("=?Shift_JIS?B?k/qWe4zq?=", Some("日本語")),
+            ("=?GB2312?B?1tDOxA==?=", Some("中文")),
+            ("=?Big5?B?pKSk5Q==?=", Some("中文")),
+            ("=?EUC-KR?B?x9Gxub7u?=", Some("한국어")),
+            ("=?x-unknown?Q?abc?=", Some("abc")),
+            ("=?x-unknown?Q?=FF?=", None),
+            ("=?windows-1252?Q?=81?=", None),
         ].iter() {
             if let Some(o) = o {
                 let res = parse_rfc_2047(*i).unwrap();
@@ -234,6 +640,65 @@ mod test {
         }
     }
 
+    //noinspection SpellCheckingInspection
+    #[test]
+    fn test_can_decode_rfc_2047() {
+        for (i, o, is_valid) in [
+            ("", "", true),
+            ("plain text", "plain text", true),
+            ("=?ISO-8859-1?Q?a?=", "a", true),
+            ("=?ISO-8859-1?Q?a?= b", "a b", true),
+            ("=?ISO-8859-1?Q?a?= =?ISO-8859-1?Q?b?=", "ab", true),
+            ("=?ISO-8859-1?Q?a?=  =?ISO-8859-1?Q?b?=", "ab", true),
+            ("=?ISO-8859-1?Q?a?=\r\n    =?ISO-8859-1?Q?b?=", "ab", true),
+            ("=?ISO-8859-1?Q?a_b?=", "a b", true),
+            ("=?ISO-8859-1?Q?a?= =?ISO-8859-2?Q?_b?=", "a b", true),
+            ("Re: =?UTF-8?Q?a?= =?UTF-8?Q?b?= plain text", "Re: ab plain text", true),
+            ("=?UTF-8?B?xQ==?= =?UTF-8?B?gQ==?=", "Ł", true),
+            ("=?UTF-8?Q?=C5?= x =?UTF-8?Q?=81?=", "=?UTF-8?Q?=C5?= x =?UTF-8?Q?=81?=", false),
+            ("=?ISO-8859-2?Q?=A3?==?UTF-8?Q?=C5=81?=", "ŁŁ", true),
+            ("=?ISO-2022-JP?B?GyRCRnxLXBsoQg==?= =?ISO-2022-JP?B?GyRCOGwbKEI=?=", "日本語", true),
+            ("=?US-ASCII*EN?Q?Keith_Moore?=", "Keith Moore", true),
+            ("a=?UTF-8?Q?b?=c", "abc", true),
+            ("=?UTF-8?Q?not encoded?=", "=?UTF-8?Q?not encoded?=", true),
+            ("=?UTF-8?X?abc?=", "=?UTF-8?X?abc?=", true),
+            ("=?UTF-8?Q?abc", "=?UTF-8?Q?abc", true),
+            ("=?UTF-8?Q?=ZZ?=", "=?UTF-8?Q?=ZZ?=", false),
+            ("=?UTF-8?B?qq?=", "=?UTF-8?B?qq?=", false),
+            // words around invalid one are still decoded and white space next to it is kept
+            ("=?UTF-8?Q?a?= =?UTF-8?Q?=ZZ?= =?UTF-8?Q?b?=", "a =?UTF-8?Q?=ZZ?= b", false),
+            ("=?UTF-8?Q?a?= =?UTF-8?Q?=FF?= =?ISO-8859-1?Q?b?=", "=?UTF-8?Q?a?= =?UTF-8?Q?=FF?=b", false),
+        ].iter() {
+            assert_eq!(decode_rfc_2047(i), *o, "{}", i);
+            assert_eq!(decode_rfc_2047_strict(i).ok().as_deref(), if *is_valid { Some(*o) } else { None }, "{}", i);
+        }
+    }
+
+    #[test]
+    fn test_can_tokenize_rfc_2047() {
+        let word = |charset, encoded_text| RFC2047Token::EncodedWord(EncodedWord {
+            charset,
+            language: None,
+            encoding: RFC2047Encoding::QuotedPrintable,
+            encoded_text,
+        });
+        for (i, o) in vec![
+            ("", vec![]),
+            ("a", vec![RFC2047Token::Text("a")]),
+            ("=?a?Q?b?=", vec![word("a", "b")]),
+            ("x =?a?Q?b?= =?c?q?d?=y", vec![
+                RFC2047Token::Text("x "),
+                word("a", "b"),
+                RFC2047Token::Text(" "),
+                word("c", "d"),
+                RFC2047Token::Text("y"),
+            ]),
+            ("=?=?a?Q??=", vec![RFC2047Token::Text("=?"), word("a", "")]),
+        ].into_iter() {
+            assert_eq!(tokenize_rfc_2047(i), o);
+        }
+    }
+
     #[test]
     fn test_can_encode_and_parse() {
         for i in [
@@ -247,7 +712,103 @@ mod test {
 
             assert_eq!(&parse_rfc_2047(&b64_res).unwrap(), i);
             assert_eq!(&parse_rfc_2047(&qp_res).unwrap(), i);
+            assert_eq!(&parse_rfc_2047(&optimal_encode_rfc_2047(i)).unwrap(), i);
         }
     }
-}
 
+    #[test]
+    fn test_can_encode_phrase() {
+        for (i, o) in [
+            ("John Doe", "John Doe"),
+            ("Doe, John", "\"Doe, John\""),
+            ("Bob Źdźbło", "Bob =?UTF-8?B?xblkxbpixYJv?="),
+            ("Bob Zamówienie", "Bob =?UTF-8?Q?Zam=C3=B3wienie?="),
+            ("Zamówienie (x) Bob", "=?UTF-8?B?WmFtw7N3aWVuaWUgKHgp?= Bob"),
+            ("ŁŁŁŁ", "=?UTF-8?B?xYHFgcWBxYE=?="),
+        ].iter() {
+            let res = encode_phrase(i);
+            assert_eq!(res, *o);
+            assert_eq!(decode_rfc_2047(&res), if res.starts_with('"') { &res } else { *i });
+        }
+    }
+
+    #[test]
+    fn test_can_encode_header() {
+        for (n, v, o) in [
+            ("Subject", "Hello  world", "Subject: Hello  world\r\n"),
+            ("Subject", "Zażółć", "Subject: =?UTF-8?B?WmHFvMOzxYLEhw==?=\r\n"),
+            ("Subject", "Re: Zamówienie", "Subject: Re: =?UTF-8?Q?Zam=C3=B3wienie?=\r\n"),
+            ("Subject", "Re: Zażółć gęślą", "Subject: Re: =?UTF-8?B?WmHFvMOzxYLEhyBnxJnFm2zEhQ==?=\r\n"),
+            ("Subject", "not =?encoded?= word", "Subject: not =?UTF-8?B?PT9lbmNvZGVkPz0=?= word\r\n"),
+        ].iter() {
+            assert_eq!(encode_header(n, v), *o);
+        }
+    }
+
+    #[test]
+    fn test_encoded_header_is_folded_and_can_be_decoded() {
+        for i in [
+            "Zażółć gęślą jaźń. Zażółć gęślą jaźń. Zażółć gęślą jaźń. Zażółć gęślą jaźń.",
+            &"ł".repeat(100),
+            &"€𝄞".repeat(40),
+            &format!("{} ł {}", "a".repeat(100), "b".repeat(30)),
+            "Plain ascii text which is long enough to be folded into two lines of header",
+        ].iter() {
+            let res = encode_header("Subject", i);
+            assert!(res.ends_with("\r\n"));
+            let mut unfolded = String::new();
+            for line in res[..res.len() - 2].split("\r\n") {
+                assert!(line.len() <= MAX_HEADER_LINE_LENGTH || !line.contains("=?"), "{:?}", line);
+                for word in line.split(' ').filter(|w| w.starts_with("=?")) {
+                    assert!(word.len() <= MAX_ENCODED_WORD_LENGTH);
+                }
+                unfolded.push_str(line);
+            }
+            assert_eq!(decode_rfc_2047(&unfolded["Subject: ".len()..]), *i);
+        }
+    }
+
+    #[test]
+    fn test_encoded_words_are_limited() {
+        for i in [
+            "",
+            "a b",
+            &"ą".repeat(100),
+            &"a€".repeat(100),
+        ].iter() {
+            let words = encode_rfc_2047_words(i);
+            for w in words.iter() {
+                assert!(w.len() <= MAX_ENCODED_WORD_LENGTH);
+            }
+            assert_eq!(decode_rfc_2047(&words.join(" ")), *i);
+        }
+    }
+
+    #[test]
+    fn test_chunk_is_longest_prefix_which_fits() {
+        for i in ["", "abc def", "Zażółć gęślą jaźń", "€𝄞 a=b_c"].iter() {
+            for encoding in [RFC2047Encoding::Base64, RFC2047Encoding::QuotedPrintable].iter() {
+                for max_word_len in [0, 12, 13, 16, 20, 75].iter() {
+                    let n = take_chunk(i, *encoding, *max_word_len);
+                    let fits = |end: usize| encoding.encoded_len(&i[..end]) as usize + ENCODED_WORD_OVERHEAD <= *max_word_len;
+                    let longest = (0..=i.len()).filter(|end| i.is_char_boundary(*end) && fits(*end)).max().unwrap_or(0);
+                    assert_eq!(n, longest, "{:?} {:?} {}", i, encoding, max_word_len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoding_errors_point_at_encoded_word() {
+        for (i, offset, expected) in [
+            ("a =?utf-8?q?=ZZ?=", 13, "two hex digits"),
+            ("a =?utf-8?q?b=4?=", 14, "two hex digits"),
+            ("=?utf-8?b?/w==?=", 10, "text valid in declared charset"),
+            ("=?utf-8?b?YQ==?= =?utf-8?b?/w==?=", 10, "text valid in declared charset"),
+        ].iter() {
+            let err = decode_rfc_2047_strict(i).unwrap_err();
+            let position = err.position().unwrap();
+            assert_eq!((position.offset, position.expected), (*offset, *expected), "{}", i);
+        }
+    }
+}
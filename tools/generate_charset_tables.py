@@ -0,0 +1,113 @@
+#!/usr/bin/env python3
+"""Generates lookup tables used by `smtpc::encoding::charset`.
+
+Tables are built from codecs shipped with CPython, so regenerating them does not
+require network access. Run from repository root:
+
+    python3 tools/generate_charset_tables.py
+
+Every table maps pointer (offset computed from bytes of encoded character) to
+Unicode scalar value. Zero means that pointer is not mapped.
+"""
+
+import os
+
+OUT_DIR = os.path.join("src", "encoding", "charset", "tables")
+
+SINGLE_BYTE = [
+    ("ISO_8859_1", "iso8859_1"),
+    ("ISO_8859_2", "iso8859_2"),
+    ("ISO_8859_3", "iso8859_3"),
+    ("ISO_8859_4", "iso8859_4"),
+    ("ISO_8859_5", "iso8859_5"),
+    ("ISO_8859_6", "iso8859_6"),
+    ("ISO_8859_7", "iso8859_7"),
+    ("ISO_8859_8", "iso8859_8"),
+    ("ISO_8859_9", "iso8859_9"),
+    ("ISO_8859_10", "iso8859_10"),
+    ("ISO_8859_11", "iso8859_11"),
+    ("ISO_8859_13", "iso8859_13"),
+    ("ISO_8859_14", "iso8859_14"),
+    ("ISO_8859_15", "iso8859_15"),
+    ("ISO_8859_16", "iso8859_16"),
+    ("WINDOWS_1250", "cp1250"),
+    ("WINDOWS_1251", "cp1251"),
+    ("WINDOWS_1252", "cp1252"),
+    ("WINDOWS_1253", "cp1253"),
+    ("WINDOWS_1254", "cp1254"),
+    ("WINDOWS_1255", "cp1255"),
+    ("WINDOWS_1256", "cp1256"),
+    ("WINDOWS_1257", "cp1257"),
+    ("WINDOWS_1258", "cp1258"),
+    ("KOI8_R", "koi8_r"),
+    ("KOI8_U", "koi8_u"),
+]
+
+
+def decode_char(data, codecs):
+    for codec in codecs:
+        try:
+            text = data.decode(codec)
+        except UnicodeDecodeError:
+            continue
+        if len(text) == 1 and ord(text) <= 0xFFFF:
+            return ord(text)
+    return 0
+
+
+def double_byte_table(leads, trails, codecs):
+    return [decode_char(bytes([lead, trail]), codecs) for lead in leads for trail in trails]
+
+
+def write_table(out, name, values, doc):
+    out.write("/// %s\n" % doc)
+    out.write("pub(crate) static %s: [u16; %d] = [\n" % (name, len(values)))
+    for i in range(0, len(values), 12):
+        out.write("    " + " ".join("0x%04X," % v for v in values[i:i + 12]) + "\n")
+    out.write("];\n")
+
+
+def write_file(name, tables):
+    with open(os.path.join(OUT_DIR, name), "w") as out:
+        out.write("// This file is generated by tools/generate_charset_tables.py. Do not edit it manually.\n")
+        for table_name, values, doc in tables:
+            out.write("\n")
+            write_table(out, table_name, values, doc)
+
+
+def main():
+    write_file("single_byte.rs", [
+        (name, [decode_char(bytes([b]), [codec]) for b in range(0x80, 0x100)],
+         "%s maps bytes 0x80-0xFF of %s" % (name, codec))
+        for name, codec in SINGLE_BYTE
+    ])
+
+    sjis_leads = list(range(0x81, 0xA0)) + list(range(0xE0, 0xFD))
+    sjis_trails = list(range(0x40, 0x7F)) + list(range(0x80, 0xFD))
+    write_file("jis0208.rs", [(
+        "JIS0208",
+        double_byte_table(sjis_leads, sjis_trails, ["cp932"]),
+        "JIS0208 is JIS X 0208 with Microsoft extensions indexed by Shift_JIS pointer",
+    )])
+
+    write_file("gbk.rs", [(
+        "GBK",
+        double_byte_table(range(0x81, 0xFF), list(range(0x40, 0x7F)) + list(range(0x80, 0xFF)), ["gb18030", "gbk"]),
+        "GBK is two byte part of GB18030 indexed by `(lead - 0x81) * 190 + trail offset`",
+    )])
+
+    write_file("big5.rs", [(
+        "BIG5",
+        double_byte_table(range(0x81, 0xFF), list(range(0x40, 0x7F)) + list(range(0xA1, 0xFF)), ["big5hkscs", "cp950"]),
+        "BIG5 is Big5 with HKSCS extensions indexed by `(lead - 0x81) * 157 + trail offset`",
+    )])
+
+    write_file("euc_kr.rs", [(
+        "EUC_KR",
+        double_byte_table(range(0x81, 0xFF), range(0x41, 0xFF), ["cp949"]),
+        "EUC_KR is Unified Hangul Code indexed by `(lead - 0x81) * 190 + trail - 0x41`",
+    )])
+
+
+if __name__ == "__main__":
+    main()